@@ -1,4 +1,5 @@
 use crate::bs_read::ByteStreamReadBuffer;
+use crate::error::Converter;
 use crate::RecordValue;
 use crate::Result;
 use std::collections::VecDeque;
@@ -61,4 +62,33 @@ impl BitPack {
         }
         Ok(())
     }
+
+    /// Strings have a length prefix followed by the UTF-8 bytes.
+    /// See also `serialize_string()` for details about the encoding.
+    /// Incomplete strings are left in the stream until more bytes are available.
+    pub fn unpack_strings(
+        stream: &mut ByteStreamReadBuffer,
+        output: &mut VecDeque<RecordValue>,
+    ) -> Result<()> {
+        while let Some(first) = stream.peek(8) {
+            let (prefix_bits, length) = if first & 1 == 0 {
+                (8, (first & 0xFF) >> 1)
+            } else if let Some(prefix) = stream.peek(64) {
+                (64, prefix >> 1)
+            } else {
+                break;
+            };
+            let available_bytes = (stream.available() - prefix_bits) as u64 / 8;
+            if length > available_bytes {
+                break;
+            }
+            stream.skip(prefix_bits as u64);
+            let bytes = stream
+                .extract_bytes(length as usize)
+                .internal_err("Failed to extract string bytes")?;
+            let string = String::from_utf8(bytes).invalid_err("Found invalid UTF-8 string")?;
+            output.push_back(RecordValue::String(string));
+        }
+        Ok(())
+    }
 }
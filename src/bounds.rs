@@ -1,5 +1,6 @@
 use crate::xml;
 use crate::Result;
+use crate::Transform;
 use roxmltree::Node;
 
 /// Optional minimum and maximum values for Cartesian X, Y and Z coordinates.
@@ -50,6 +51,38 @@ impl CartesianBounds {
         xml += "</cartesianBounds>\n";
         xml
     }
+
+    /// Transforms the bounds, for example from the local coordinate system of a point cloud
+    /// into the file-level coordinate system with the pose of the point cloud.
+    /// The result is the axis-aligned box around all eight transformed corners,
+    /// which might be larger than the tight bounds of the transformed points.
+    /// Returns None if any of the minimum or maximum values is missing.
+    pub fn transform(&self, transform: &Transform) -> Option<Self> {
+        let min = [self.x_min?, self.y_min?, self.z_min?];
+        let max = [self.x_max?, self.y_max?, self.z_max?];
+        let mut new_min = [f64::INFINITY; 3];
+        let mut new_max = [f64::NEG_INFINITY; 3];
+        for corner in 0..8 {
+            let p = [
+                if corner & 1 == 0 { min[0] } else { max[0] },
+                if corner & 2 == 0 { min[1] } else { max[1] },
+                if corner & 4 == 0 { min[2] } else { max[2] },
+            ];
+            let t = transform.apply_point(p);
+            for i in 0..3 {
+                new_min[i] = new_min[i].min(t[i]);
+                new_max[i] = new_max[i].max(t[i]);
+            }
+        }
+        Some(Self {
+            x_min: Some(new_min[0]),
+            x_max: Some(new_max[0]),
+            y_min: Some(new_min[1]),
+            y_max: Some(new_max[1]),
+            z_min: Some(new_min[2]),
+            z_max: Some(new_max[2]),
+        })
+    }
 }
 
 /// Optional minimum and maximum values for spherical coordinates.
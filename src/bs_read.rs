@@ -31,6 +31,13 @@ impl ByteStreamReadBuffer {
     /// Please make sure to ignore/mask the additional bits!
     /// Returns None if the request cannot be satisfied.
     pub fn extract(&mut self, bits: usize) -> Option<u64> {
+        let data = self.peek(bits)?;
+        self.offset += bits;
+        Some(data)
+    }
+
+    /// Same as `extract()` but without consuming the bits.
+    pub fn peek(&self, bits: usize) -> Option<u64> {
         if self.available() < bits {
             return None;
         }
@@ -45,11 +52,31 @@ impl ByteStreamReadBuffer {
         let src = &self.buffer[start_offset..end_offset];
         dst.copy_from_slice(src);
 
-        self.offset += bits;
         let data = u128::from_le_bytes(data) >> offset;
         Some(data as u64)
     }
 
+    /// Extracts the requested number of full bytes from the stream.
+    /// The current position in the stream must be aligned to a full byte.
+    /// Returns None if the request cannot be satisfied.
+    pub fn extract_bytes(&mut self, count: usize) -> Option<Vec<u8>> {
+        if !self.offset.is_multiple_of(8) || self.available() < count * 8 {
+            return None;
+        }
+        let start = self.offset / 8;
+        self.offset += count * 8;
+        Some(self.buffer[start..start + count].to_vec())
+    }
+
+    /// Skips up to the requested number of bits in the stream.
+    /// Returns the number of bits that were actually skipped,
+    /// which might be less than requested if not enough bits are available.
+    pub fn skip(&mut self, bits: u64) -> u64 {
+        let skipped = bits.min(self.available() as u64);
+        self.offset += skipped as usize;
+        skipped
+    }
+
     /// Returns the number of available bits in the stream
     pub fn available(&self) -> usize {
         (self.buffer.len() * 8) - self.offset
@@ -98,6 +125,37 @@ mod tests {
         assert_eq!(result, 215685);
     }
 
+    #[test]
+    fn peek_and_extract_bytes() {
+        let mut bs = ByteStreamReadBuffer::new();
+        bs.append(&[1, 2, 3]);
+
+        assert_eq!(bs.peek(8).unwrap() & 0xFF, 1);
+        assert_eq!(bs.peek(16).unwrap() & 0xFFFF, 0x0201);
+        assert!(bs.peek(32).is_none());
+        assert_eq!(bs.available(), 24);
+
+        assert_eq!(bs.extract_bytes(2).unwrap(), vec![1, 2]);
+        assert!(bs.extract_bytes(2).is_none());
+        assert_eq!(bs.extract_bytes(1).unwrap(), vec![3]);
+        assert_eq!(bs.available(), 0);
+    }
+
+    #[test]
+    fn skip_bits() {
+        let mut bs = ByteStreamReadBuffer::new();
+        bs.append(&[0b10110000, 0b00000001]);
+
+        assert_eq!(bs.skip(4), 4);
+        assert_eq!(bs.available(), 12);
+        let result = bs.extract(5).unwrap();
+        assert_eq!(result & 0b11111, 0b11011);
+
+        // Skipping more than available stops at the end of the stream
+        assert_eq!(bs.skip(100), 7);
+        assert_eq!(bs.available(), 0);
+    }
+
     #[test]
     fn remove_consume_when_appending() {
         let mut bs = ByteStreamReadBuffer::new();
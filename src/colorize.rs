@@ -0,0 +1,424 @@
+use crate::{Error, Image, Projection, Result, Transform};
+
+#[cfg(feature = "image-decoding")]
+use crate::error::Converter;
+#[cfg(feature = "image-decoding")]
+use crate::{E57Reader, ImageFormat, PointCloud};
+#[cfg(feature = "image-decoding")]
+use std::io::{Cursor, Read, Seek};
+
+/// Decoded image with 8 bit RGB pixels, used to colorize points.
+#[derive(Clone, Debug)]
+pub struct RgbImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl RgbImage {
+    /// Creates an image from row-major pixel data with three bytes per pixel.
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Result<Self> {
+        let expected = width as usize * height as usize * 3;
+        if pixels.len() != expected {
+            Error::invalid(format!(
+                "Expected {expected} bytes for RGB image with size {width}x{height}, got {}",
+                pixels.len()
+            ))?
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Decodes PNG or JPEG image data.
+    /// Grayscale images are converted to RGB and alpha channels are dropped.
+    /// This function is only available with the `image-decoding` crate feature.
+    #[cfg(feature = "image-decoding")]
+    pub fn decode(format: &ImageFormat, data: &[u8]) -> Result<Self> {
+        match format {
+            ImageFormat::Png => Self::decode_png(data),
+            ImageFormat::Jpeg => Self::decode_jpeg(data),
+        }
+    }
+
+    /// Returns the width of the image in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the image in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the color of the pixel that contains the given pixel coordinates.
+    fn sample(&self, pixel: [f64; 2]) -> Option<[u8; 3]> {
+        let x = pixel[0].floor() as usize;
+        let y = pixel[1].floor() as usize;
+        if x >= self.width as usize || y >= self.height as usize {
+            return None;
+        }
+        let offset = (y * self.width as usize + x) * 3;
+        let rgb = self.pixels.get(offset..offset + 3)?;
+        Some([rgb[0], rgb[1], rgb[2]])
+    }
+
+    #[cfg(feature = "image-decoding")]
+    fn decode_png(data: &[u8]) -> Result<Self> {
+        let mut decoder = png::Decoder::new(Cursor::new(data));
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder
+            .read_info()
+            .invalid_err("Failed to read PNG header")?;
+        let size = reader
+            .output_buffer_size()
+            .invalid_err("PNG image is too big")?;
+        let mut buffer = vec![0; size];
+        let info = reader
+            .next_frame(&mut buffer)
+            .invalid_err("Failed to decode PNG image")?;
+        let channels = match info.color_type {
+            png::ColorType::Grayscale => 1,
+            png::ColorType::GrayscaleAlpha => 2,
+            png::ColorType::Rgb => 3,
+            png::ColorType::Rgba => 4,
+            png::ColorType::Indexed => {
+                Error::not_implemented("Indexed PNG colors are not expanded")?
+            }
+        };
+        let mut pixels = Vec::with_capacity(info.width as usize * info.height as usize * 3);
+        for line in buffer.chunks(info.line_size).take(info.height as usize) {
+            for pixel in line.chunks(channels).take(info.width as usize) {
+                if channels < 3 {
+                    pixels.extend_from_slice(&[pixel[0]; 3]);
+                } else {
+                    pixels.extend_from_slice(&pixel[..3]);
+                }
+            }
+        }
+        Self::new(info.width, info.height, pixels)
+    }
+
+    #[cfg(feature = "image-decoding")]
+    fn decode_jpeg(data: &[u8]) -> Result<Self> {
+        let mut decoder = jpeg_decoder::Decoder::new(Cursor::new(data));
+        let buffer = decoder
+            .decode()
+            .invalid_err("Failed to decode JPEG image")?;
+        let info = decoder
+            .info()
+            .invalid_err("Failed to get JPEG image information")?;
+        let pixels = match info.pixel_format {
+            jpeg_decoder::PixelFormat::RGB24 => buffer,
+            jpeg_decoder::PixelFormat::L8 => buffer.iter().flat_map(|v| [*v; 3]).collect(),
+            jpeg_decoder::PixelFormat::L16 => buffer
+                .chunks(2)
+                .flat_map(|v| [v[0]; 3]) // Big endian, keep only the most significant byte
+                .collect(),
+            jpeg_decoder::PixelFormat::CMYK32 => {
+                Error::not_implemented("JPEG images with CMYK colors are not supported")?
+            }
+        };
+        Self::new(info.width as u32, info.height as u32, pixels)
+    }
+}
+
+/// Projects points into images to determine their colors.
+///
+/// Colorizing works in two passes over all points of a point cloud:
+/// First all points are added with [`Colorizer::add_depth`] to build a coarse depth map for each image.
+/// Then [`Colorizer::color`] returns the color for each point.
+/// The depth maps are used to detect points that are hidden behind other points,
+/// these points get no color from the corresponding image.
+/// If you skip the first pass, no occlusion test is done.
+///
+/// All points are expected in file-level coordinates,
+/// which means with the pose of the point cloud applied.
+#[derive(Clone, Debug)]
+pub struct Colorizer {
+    sources: Vec<Source>,
+    cell_size: u32,
+    tolerance: f64,
+}
+
+#[derive(Clone, Debug)]
+struct Source {
+    projection: Projection,
+    to_local: Transform,
+    rgb: RgbImage,
+    columns: usize,
+    rows: usize,
+    depth: Vec<f64>,
+}
+
+impl Colorizer {
+    /// Creates a new colorizer without any images.
+    pub fn new() -> Self {
+        Self {
+            sources: Vec::new(),
+            cell_size: 4,
+            tolerance: 0.05,
+        }
+    }
+
+    /// Sets the size of the depth map cells in pixels. Default value is 4.
+    /// Larger cells detect more occlusions for sparse point clouds but may hide close points at edges.
+    /// Only affects images that are added after calling this function.
+    pub fn set_depth_cell_size(&mut self, pixels: u32) {
+        self.cell_size = pixels.max(1);
+    }
+
+    /// Sets the relative depth tolerance for the occlusion test. Default value is 0.05 (5%).
+    /// Points are considered visible if their distance to the image is not larger than
+    /// the closest distance in the same depth map cell plus this fraction.
+    pub fn set_depth_tolerance(&mut self, tolerance: f64) {
+        self.tolerance = tolerance;
+    }
+
+    /// Adds an image with a projection model and its decoded pixels.
+    /// The size of the decoded image must match the size in the image properties.
+    /// If a point is visible in multiple images, the color is taken from the closest image.
+    pub fn add_image(&mut self, image: &Image, rgb: RgbImage) -> Result<()> {
+        let Some(projection) = &image.projection else {
+            Error::invalid("Image has no projection model and cannot be used for colorizing")?
+        };
+        let (width, height) = match projection {
+            Projection::Pinhole(p) => (p.properties.width, p.properties.height),
+            Projection::Spherical(s) => (s.properties.width, s.properties.height),
+            Projection::Cylindrical(c) => (c.properties.width, c.properties.height),
+        };
+        if (width, height) != (rgb.width, rgb.height) {
+            Error::invalid(format!(
+                "Decoded image size {}x{} does not match image properties {width}x{height}",
+                rgb.width, rgb.height
+            ))?
+        }
+        let columns = width.div_ceil(self.cell_size) as usize;
+        let rows = height.div_ceil(self.cell_size) as usize;
+        self.sources.push(Source {
+            projection: projection.clone(),
+            to_local: image.transform.clone().unwrap_or_default().inverse(),
+            rgb,
+            columns,
+            rows,
+            depth: vec![f64::INFINITY; columns * rows],
+        });
+        Ok(())
+    }
+
+    /// Loads and decodes all projectable images associated with the given point cloud.
+    /// Images are associated by the point cloud GUID referenced in the image metadata.
+    /// Images that are stored in unsupported formats are skipped.
+    /// This function is only available with the `image-decoding` crate feature.
+    #[cfg(feature = "image-decoding")]
+    pub fn from_pointcloud<T: Read + Seek>(
+        reader: &mut E57Reader<T>,
+        pc: &PointCloud,
+    ) -> Result<Self> {
+        let mut colorizer = Self::new();
+        for image in reader.images() {
+            if pc.guid.is_none() || image.pointcloud_guid != pc.guid {
+                continue;
+            }
+            let blob = match &image.projection {
+                Some(Projection::Pinhole(p)) => &p.blob,
+                Some(Projection::Spherical(s)) => &s.blob,
+                Some(Projection::Cylindrical(c)) => &c.blob,
+                None => continue,
+            };
+            let mut data = Vec::new();
+            reader.blob(&blob.data, &mut data)?;
+            match RgbImage::decode(&blob.format, &data) {
+                Ok(rgb) => colorizer.add_image(&image, rgb)?,
+                Err(Error::NotImplemented { .. }) => continue,
+                Err(err) => Err(err)?,
+            }
+        }
+        Ok(colorizer)
+    }
+
+    /// Returns the number of images used for colorizing.
+    pub fn images(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Updates the depth maps of all images with a point in file-level coordinates.
+    pub fn add_depth(&mut self, point: [f64; 3]) {
+        for source in &mut self.sources {
+            if let Some((cell, distance)) = source.locate(point, self.cell_size) {
+                let depth = &mut source.depth[cell];
+                if distance < *depth {
+                    *depth = distance;
+                }
+            }
+        }
+    }
+
+    /// Returns the color for a point in file-level coordinates.
+    /// Returns None if the point is not visible in any of the images.
+    pub fn color(&self, point: [f64; 3]) -> Option<[u8; 3]> {
+        let mut best: Option<(f64, [u8; 3])> = None;
+        for source in &self.sources {
+            let local = source.to_local.apply_point(point);
+            let Some(pixel) = source.projection.project(local) else {
+                continue;
+            };
+            let distance = length(local);
+            let Some(cell) = source.cell(pixel, self.cell_size) else {
+                continue;
+            };
+            if distance > source.depth[cell] * (1.0 + self.tolerance) {
+                continue;
+            }
+            if best.is_some_and(|(d, _)| d <= distance) {
+                continue;
+            }
+            if let Some(rgb) = source.rgb.sample(pixel) {
+                best = Some((distance, rgb));
+            }
+        }
+        best.map(|(_, rgb)| rgb)
+    }
+}
+
+impl Default for Colorizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Source {
+    /// Returns the depth map cell and the distance of a point in file-level coordinates.
+    fn locate(&self, point: [f64; 3], cell_size: u32) -> Option<(usize, f64)> {
+        let local = self.to_local.apply_point(point);
+        let pixel = self.projection.project(local)?;
+        Some((self.cell(pixel, cell_size)?, length(local)))
+    }
+
+    fn cell(&self, pixel: [f64; 2], cell_size: u32) -> Option<usize> {
+        let column = (pixel[0] / cell_size as f64) as usize;
+        let row = (pixel[1] / cell_size as f64) as usize;
+        if column < self.columns && row < self.rows {
+            Some(row * self.columns + column)
+        } else {
+            None
+        }
+    }
+}
+
+fn length(v: [f64; 3]) -> f64 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Blob, ImageBlob, ImageFormat, SphericalImage, SphericalImageProperties};
+    use std::f64::consts::PI;
+
+    fn spherical_image(width: u32, height: u32) -> Image {
+        Image {
+            guid: None,
+            visual_reference: None,
+            projection: Some(Projection::Spherical(SphericalImage {
+                blob: ImageBlob {
+                    data: Blob::new(0, 0),
+                    format: ImageFormat::Png,
+                },
+                mask: None,
+                properties: SphericalImageProperties {
+                    width,
+                    height,
+                    pixel_width: 2.0 * PI / width as f64,
+                    pixel_height: PI / height as f64,
+                },
+            })),
+            transform: None,
+            pointcloud_guid: None,
+            name: None,
+            description: None,
+            acquisition: None,
+            sensor_vendor: None,
+            sensor_model: None,
+            sensor_serial: None,
+            additional_xml: Vec::new(),
+        }
+    }
+
+    /// Creates an image where the red channel contains the column and the green channel the row.
+    fn gradient(width: u32, height: u32) -> RgbImage {
+        let mut pixels = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                pixels.extend_from_slice(&[x as u8, y as u8, 0]);
+            }
+        }
+        RgbImage::new(width, height, pixels).unwrap()
+    }
+
+    #[test]
+    fn rgb_image_size() {
+        assert!(RgbImage::new(2, 2, vec![0; 12]).is_ok());
+        assert!(RgbImage::new(2, 2, vec![0; 11]).is_err());
+        let mut colorizer = Colorizer::new();
+        assert!(colorizer
+            .add_image(&spherical_image(36, 18), gradient(18, 18))
+            .is_err());
+        assert_eq!(colorizer.images(), 0);
+    }
+
+    #[test]
+    fn color_lookup() {
+        let image = spherical_image(36, 18);
+        let mut colorizer = Colorizer::new();
+        colorizer.add_image(&image, gradient(36, 18)).unwrap();
+        assert_eq!(colorizer.images(), 1);
+
+        let point = [5.0, 1.0, 0.5];
+        let pixel = image.project(point).unwrap();
+        let expected = [pixel[0].floor() as u8, pixel[1].floor() as u8, 0];
+        assert_eq!(colorizer.color(point), Some(expected));
+    }
+
+    #[test]
+    fn occlusion() {
+        let mut colorizer = Colorizer::new();
+        colorizer
+            .add_image(&spherical_image(36, 18), gradient(36, 18))
+            .unwrap();
+        let near = [5.0, 0.0, 0.0];
+        let far = [10.0, 0.0, 0.0];
+
+        // Without depth information all points are visible
+        assert!(colorizer.color(far).is_some());
+
+        colorizer.add_depth(near);
+        colorizer.add_depth(far);
+        assert!(colorizer.color(near).is_some());
+        assert_eq!(colorizer.color(far), None);
+
+        // Points within the depth tolerance are still visible
+        assert!(colorizer.color([5.2, 0.0, 0.0]).is_some());
+    }
+
+    #[test]
+    #[cfg(feature = "image-decoding")]
+    fn decode_png() {
+        let mut data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut data, 2, 1);
+            encoder.set_color(png::ColorType::Grayscale);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[10, 200]).unwrap();
+        }
+        let image = RgbImage::decode(&ImageFormat::Png, &data).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert_eq!(image.sample([0.5, 0.5]), Some([10, 10, 10]));
+        assert_eq!(image.sample([1.5, 0.5]), Some([200, 200, 200]));
+        assert_eq!(image.sample([2.5, 0.5]), None);
+        assert!(RgbImage::decode(&ImageFormat::Jpeg, &data).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::error::Converter;
+use crate::Error;
+use crate::Record;
+use crate::RecordDataType;
+use crate::RecordName;
+use crate::RecordValue;
+use crate::Result;
+
+/// Values of a single prototype record for multiple points.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Column {
+    /// Values of single, double and scaled integer records.
+    /// Scaled integers already have their scale and offset applied.
+    F64(Vec<f64>),
+    /// Values of integer records.
+    I64(Vec<i64>),
+    /// Values of string records.
+    String(Vec<String>),
+}
+
+impl Column {
+    /// Returns the number of values in the column.
+    pub fn len(&self) -> usize {
+        match self {
+            Column::F64(values) => values.len(),
+            Column::I64(values) => values.len(),
+            Column::String(values) => values.len(),
+        }
+    }
+
+    /// Returns true if the column contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn clear(&mut self) {
+        match self {
+            Column::F64(values) => values.clear(),
+            Column::I64(values) => values.clear(),
+            Column::String(values) => values.clear(),
+        }
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        match self {
+            Column::F64(values) => values.reserve(additional),
+            Column::I64(values) => values.reserve(additional),
+            Column::String(values) => values.reserve(additional),
+        }
+    }
+
+    pub(crate) fn push(&mut self, value: RecordValue, data_type: &RecordDataType) -> Result<()> {
+        match self {
+            Column::F64(values) => values.push(value.to_f64(data_type)?),
+            Column::I64(values) => values.push(value.to_i64(data_type)?),
+            Column::String(values) => match value {
+                RecordValue::String(s) => values.push(s),
+                _ => Error::internal("Tried to add non-string value to string column")?,
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Caller-owned buffers with one typed column per prototype record.
+///
+/// Used to read many points at once without allocating memory for each individual point.
+/// The buffers keep their capacity and can be reused for subsequent reads.
+/// See also [`PointCloudReaderRaw::read_batch`](crate::PointCloudReaderRaw::read_batch).
+#[derive(Clone, Debug)]
+pub struct ColumnBuffers {
+    names: Vec<RecordName>,
+    columns: Vec<Column>,
+}
+
+impl ColumnBuffers {
+    /// Creates empty column buffers matching the given point cloud prototype.
+    pub fn new(prototype: &[Record]) -> Self {
+        let names = prototype.iter().map(|r| r.name.clone()).collect();
+        let columns = prototype
+            .iter()
+            .map(|r| match r.data_type {
+                RecordDataType::Integer { .. } => Column::I64(Vec::new()),
+                RecordDataType::String => Column::String(Vec::new()),
+                _ => Column::F64(Vec::new()),
+            })
+            .collect();
+        Self { names, columns }
+    }
+
+    /// Returns the number of points stored in the buffers.
+    pub fn len(&self) -> usize {
+        self.columns.first().map(|c| c.len()).unwrap_or(0)
+    }
+
+    /// Returns true if the buffers contain no points.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all values from the buffers but keeps the allocated memory.
+    pub fn clear(&mut self) {
+        self.columns.iter_mut().for_each(Column::clear);
+    }
+
+    /// Returns all columns in the same order as the prototype records.
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Returns the column of the record with the given name.
+    pub fn column(&self, name: &RecordName) -> Option<&Column> {
+        let index = self.names.iter().position(|n| n == name)?;
+        self.columns.get(index)
+    }
+
+    /// Returns the floating point values of the record with the given name.
+    /// Returns None if there is no such record or if it is not stored as floating point column.
+    pub fn f64(&self, name: &RecordName) -> Option<&[f64]> {
+        match self.column(name)? {
+            Column::F64(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer values of the record with the given name.
+    /// Returns None if there is no such record or if it is not stored as integer column.
+    pub fn i64(&self, name: &RecordName) -> Option<&[i64]> {
+        match self.column(name)? {
+            Column::I64(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Returns the string values of the record with the given name.
+    /// Returns None if there is no such record or if it is not stored as string column.
+    pub fn strings(&self, name: &RecordName) -> Option<&[String]> {
+        match self.column(name)? {
+            Column::String(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn validate(&self, prototype: &[Record]) -> Result<()> {
+        let matching = self.names.len() == prototype.len()
+            && self.names.iter().zip(prototype).all(|(n, r)| *n == r.name);
+        if !matching {
+            Error::invalid("Column buffers do not match the point cloud prototype")?
+        }
+        Ok(())
+    }
+
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        self.columns.iter_mut().for_each(|c| c.reserve(additional));
+    }
+
+    pub(crate) fn column_mut(&mut self, index: usize) -> Result<&mut Column> {
+        self.columns
+            .get_mut(index)
+            .internal_err("Column index is out of bounds")
+    }
+}
@@ -0,0 +1,245 @@
+use crate::error::Converter;
+use crate::extension::{get_extension_data, set_extension_data};
+use crate::paged_writer::{PagedWriter, PAGE_SIZE};
+use crate::root::{serialize_root, Root};
+use crate::{
+    Blob, DateTime, E57Reader, Error, Extension, ExtensionData, Header, Image, ImageBlob,
+    PointCloud, Projection, Result, XmlElement,
+};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+
+/// Interface for editing the metadata of existing E57 files in place.
+///
+/// The editor allows modifying the descriptors of point clouds and images and the fields of the root.
+/// Saving the changes appends a new XML section at the end of the file and updates the file header.
+/// Binary sections like point data and image blobs are not touched.
+/// The old XML section stays in the file as unused data,
+/// which means the file grows with every save by the size of the XML section.
+/// Only files with the default page size of 1024 bytes can be edited.
+pub struct E57Editor<T: Read + Write + Seek> {
+    writer: PagedWriter<T>,
+    header: Header,
+    root: Root,
+    pointclouds: Vec<PointCloud>,
+    images: Vec<Image>,
+    extensions: Vec<Extension>,
+    binary_references: Vec<String>,
+    image_references: Vec<String>,
+}
+
+impl<T: Read + Write + Seek> E57Editor<T> {
+    /// Creates a new E57 editor from a reader that must also implement Write.
+    /// Most typical use cases should prefer `E57Editor::from_file()` over this constructor.
+    pub fn new(mut reader: T) -> Result<Self> {
+        let (header, root, pointclouds, images, extensions) =
+            E57Reader::new(&mut reader)?.into_metadata();
+        if header.page_size != PAGE_SIZE {
+            Error::not_implemented(format!(
+                "Editing files with a page size of {} bytes is not supported, only {PAGE_SIZE} bytes",
+                header.page_size
+            ))?
+        }
+        let writer = PagedWriter::from_existing(reader)?;
+        let binary_references = pointclouds.iter().map(binary_references).collect();
+        let image_references = images.iter().map(image_binary_references).collect();
+        Ok(Self {
+            writer,
+            header,
+            root,
+            pointclouds,
+            images,
+            extensions,
+            binary_references,
+            image_references,
+        })
+    }
+
+    /// Returns the contents of E57 binary file header structure.
+    pub fn header(&self) -> Header {
+        self.header.clone()
+    }
+
+    /// Returns GUID stored in the XML section.
+    pub fn guid(&self) -> &str {
+        &self.root.guid
+    }
+
+    /// Sets a new GUID for the file.
+    pub fn set_guid(&mut self, guid: &str) {
+        self.root.guid = guid.to_owned();
+    }
+
+    /// Returns the optional creation date and time of the file.
+    pub fn creation(&self) -> Option<DateTime> {
+        self.root.creation.clone()
+    }
+
+    /// Set optional creation date time.
+    pub fn set_creation(&mut self, value: Option<DateTime>) {
+        self.root.creation = value;
+    }
+
+    /// Returns the optional coordinate system metadata of the file.
+    pub fn coordinate_metadata(&self) -> Option<&str> {
+        self.root.coordinate_metadata.as_deref()
+    }
+
+    /// Set optional coordinate metadata string.
+    pub fn set_coordinate_metadata(&mut self, value: Option<String>) {
+        self.root.coordinate_metadata = value;
+    }
+
+    /// Returns a list of all extensions registered in this file.
+    pub fn extensions(&self) -> &[Extension] {
+        &self.extensions
+    }
+
+    /// Registers a new E57 extension used by this file.
+    /// Registering an extension that already exists with the same URL has no effect.
+    pub fn register_extension(&mut self, extension: Extension) -> Result<()> {
+        Extension::register(&mut self.extensions, extension)
+    }
+
+    /// Returns the additional XML elements of the root that are not defined by the E57 standard.
+    pub fn additional_xml(&self) -> &[XmlElement] {
+        &self.root.additional_xml
+    }
+
+    /// Sets additional XML elements for the root of the E57 file.
+    /// Replaces all existing elements, including typed extension data.
+    /// Namespaces used by the elements must be registered as extensions.
+    pub fn set_additional_xml(&mut self, elements: Vec<XmlElement>) {
+        self.root.additional_xml = elements;
+    }
+
+    /// Parses the typed extension data from the additional XML elements of the root.
+    /// Returns None if the file has no element for this extension data.
+    pub fn extension_data<D: ExtensionData>(&self) -> Result<Option<D>> {
+        get_extension_data(&self.root.additional_xml)
+    }
+
+    /// Attaches typed extension data to the root of the E57 file.
+    /// Replaces any existing data of the same type.
+    /// The extension of the data is registered automatically, if required.
+    pub fn set_extension_data<D: ExtensionData>(&mut self, data: &D) -> Result<()> {
+        set_extension_data(&mut self.root.additional_xml, &mut self.extensions, data)
+    }
+
+    /// Returns all point cloud descriptors of the file.
+    pub fn pointclouds(&self) -> &[PointCloud] {
+        &self.pointclouds
+    }
+
+    /// Returns all point cloud descriptors of the file for modification.
+    ///
+    /// The file offset, number of records, prototype and line grouping of a point cloud
+    /// describe its binary data and must not be changed.
+    /// This is checked when saving the changes.
+    pub fn pointclouds_mut(&mut self) -> &mut [PointCloud] {
+        &mut self.pointclouds
+    }
+
+    /// Returns all image descriptors of the file.
+    pub fn images(&self) -> &[Image] {
+        &self.images
+    }
+
+    /// Returns all image descriptors of the file for modification.
+    ///
+    /// The blobs of an image refer to its binary data and must not be changed.
+    /// This is checked when saving the changes.
+    pub fn images_mut(&mut self) -> &mut [Image] {
+        &mut self.images
+    }
+
+    /// Writes all changes into the E57 file.
+    ///
+    /// Appends a new XML section at the end of the file and updates the header to point to it.
+    /// The header is written last, so the file stays valid with the old metadata if saving fails.
+    /// Can be called multiple times, each call appends another XML section.
+    pub fn save(&mut self) -> Result<()> {
+        for (i, pc) in self.pointclouds.iter().enumerate() {
+            if self.binary_references.get(i) != Some(&binary_references(pc)) {
+                Error::invalid(format!(
+                    "Binary data references of point cloud {i} must not be changed"
+                ))?
+            }
+        }
+        for (i, img) in self.images.iter().enumerate() {
+            if self.image_references.get(i) != Some(&image_binary_references(img)) {
+                Error::invalid(format!(
+                    "Binary data references of image {i} must not be changed"
+                ))?
+            }
+        }
+        let xml = serialize_root(
+            &self.root,
+            &self.pointclouds,
+            &self.images,
+            &self.extensions,
+        )?;
+
+        // Append new XML section after all existing pages
+        let xml_offset = self.writer.physical_size()?;
+        self.writer.physical_seek(xml_offset)?;
+        self.writer
+            .write_all(xml.as_bytes())
+            .write_err("Failed to write XML data")?;
+        let phys_length = self.writer.physical_size()?;
+
+        // Update header at start of the file
+        self.header.phys_xml_offset = xml_offset;
+        self.header.xml_length = xml.len() as u64;
+        self.header.phys_length = phys_length;
+        self.writer.physical_seek(0)?;
+        self.header.write(&mut self.writer)?;
+        self.writer
+            .flush()
+            .write_err("Failed to flush writer at the end")
+    }
+}
+
+impl E57Editor<File> {
+    /// Opens an existing E57 file for editing.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .read_err("Unable to open file for reading and writing")?;
+        Self::new(file)
+    }
+}
+
+/// Serializes all parts of a point cloud descriptor that refer to binary data.
+fn binary_references(pc: &PointCloud) -> String {
+    let mut xml = format!("{}:{}\n", pc.file_offset, pc.records);
+    for record in &pc.prototype {
+        xml += &record.xml_string();
+    }
+    if let Some(grouping) = &pc.line_grouping {
+        xml += &grouping.xml_string();
+    }
+    xml
+}
+
+/// Serializes all blobs of an image descriptor.
+fn image_binary_references(img: &Image) -> String {
+    let blobs = |blob: &ImageBlob, mask: &Option<Blob>| {
+        let mask = mask.as_ref().map(|m| m.xml_string("imageMask"));
+        blob.xml_string() + &mask.unwrap_or_default()
+    };
+    let mut xml = String::new();
+    if let Some(rep) = &img.visual_reference {
+        xml += &blobs(&rep.blob, &rep.mask);
+    }
+    xml += &match &img.projection {
+        Some(Projection::Pinhole(rep)) => blobs(&rep.blob, &rep.mask),
+        Some(Projection::Spherical(rep)) => blobs(&rep.blob, &rep.mask),
+        Some(Projection::Cylindrical(rep)) => blobs(&rep.blob, &rep.mask),
+        None => String::new(),
+    };
+    xml
+}
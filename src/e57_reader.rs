@@ -1,17 +1,27 @@
 use crate::error::Converter;
+use crate::extension::get_extension_data;
 use crate::paged_reader::PagedReader;
 use crate::root::root_from_document;
 use crate::root::Root;
 use crate::Blob;
 use crate::DateTime;
+use crate::E57ReaderOptions;
 use crate::Error;
 use crate::Extension;
+use crate::ExtensionData;
 use crate::Header;
 use crate::Image;
+use crate::LineGroupReader;
+use crate::LineGrouping;
 use crate::PointCloud;
+use crate::PointCloudReaderParallel;
 use crate::PointCloudReaderRaw;
 use crate::PointCloudReaderSimple;
+use crate::RecordName;
 use crate::Result;
+use crate::StructuredGrid;
+use crate::XmlElement;
+use crate::{ReadAt, SourceReader};
 use roxmltree::Document;
 use std::fs::File;
 use std::io::BufReader;
@@ -19,8 +29,7 @@ use std::io::Read;
 use std::io::Seek;
 use std::io::Write;
 use std::path::Path;
-
-const MAX_XML_SIZE: usize = 1024 * 1024 * 50;
+use std::sync::Arc;
 
 /// Main interface for reading E57 files.
 pub struct E57Reader<T: Read + Seek> {
@@ -31,30 +40,39 @@ pub struct E57Reader<T: Read + Seek> {
     pointclouds: Vec<PointCloud>,
     images: Vec<Image>,
     extensions: Vec<Extension>,
+    options: E57ReaderOptions,
 }
 
 impl<T: Read + Seek> E57Reader<T> {
     /// Creates a new E57 instance for from a reader.
-    pub fn new(mut reader: T) -> Result<Self> {
+    pub fn new(reader: T) -> Result<Self> {
+        Self::with_options(reader, E57ReaderOptions::default())
+    }
+
+    /// Creates a new E57 instance from a reader with custom options.
+    pub fn with_options(mut reader: T, options: E57ReaderOptions) -> Result<Self> {
         // Read, parse and validate E57 header
         let header = Header::read(&mut reader)?;
 
         // Set up paged reader for the CRC page layer
-        let mut reader = PagedReader::new(reader, header.page_size)
-            .read_err("Failed creating paged CRC reader")?;
+        let mut reader = Self::paged_reader(reader, header.page_size, &options)?;
 
         // Read and parse XML data
         let xml_raw = Self::extract_xml(
             &mut reader,
             header.phys_xml_offset,
             header.xml_length as usize,
+            options.max_xml_size,
         )?;
         let xml = String::from_utf8(xml_raw).read_err("Failed to parse XML as UTF8")?;
+
+        // Corrupt pages are only tolerated for binary data, the XML section must be valid
+        reader.set_recovery(options.recover_corrupt_pages);
         let document = Document::parse(&xml).invalid_err("Failed to parse XML data")?;
         let root = root_from_document(&document)?;
-        let pointclouds = PointCloud::vec_from_document(&document)?;
-        let images = Image::vec_from_document(&document)?;
         let extensions = Extension::vec_from_document(&document);
+        let pointclouds = PointCloud::vec_from_document(&document, &extensions)?;
+        let images = Image::vec_from_document(&document)?;
 
         Ok(Self {
             reader,
@@ -64,6 +82,7 @@ impl<T: Read + Seek> E57Reader<T> {
             pointclouds,
             images,
             extensions,
+            options,
         })
     }
 
@@ -97,6 +116,21 @@ impl<T: Read + Seek> E57Reader<T> {
         self.extensions.clone()
     }
 
+    /// Parses the typed extension data from the additional XML elements of the root.
+    /// Returns None if the file has no element for this extension data.
+    /// Data attached to point clouds and images is available through their descriptors,
+    /// see [`PointCloud::extension_data`] and [`Image::extension_data`].
+    pub fn extension_data<D: ExtensionData>(&self) -> Result<Option<D>> {
+        get_extension_data(&self.root.additional_xml)
+    }
+
+    /// Returns the additional XML elements of the root that are not defined by the E57 standard.
+    /// Use [`E57Writer::set_additional_xml`](crate::E57Writer::set_additional_xml)
+    /// to copy them into a new file.
+    pub fn additional_xml(&self) -> &[XmlElement] {
+        &self.root.additional_xml
+    }
+
     /// Returns a list of all point cloud descriptors in the file.
     pub fn pointclouds(&self) -> Vec<PointCloud> {
         self.pointclouds.clone()
@@ -106,7 +140,19 @@ impl<T: Read + Seek> E57Reader<T> {
     /// The data provided by this interface is already normalized for convenience.
     /// There is also a raw iterator for advanced use-cases that require direct access.
     pub fn pointcloud_simple(&mut self, pc: &PointCloud) -> Result<PointCloudReaderSimple<'_, T>> {
-        PointCloudReaderSimple::new(pc, &mut self.reader)
+        PointCloudReaderSimple::new(pc, &self.extensions, &mut self.reader)
+    }
+
+    /// Reads all points of a structured point cloud into a two-dimensional grid.
+    /// The point cloud must have row and column indices.
+    /// Points are read with the default settings of the simple point cloud reader.
+    /// Use [`StructuredGrid::from_points`] if you need a different reader configuration.
+    pub fn structured_grid(&mut self, pc: &PointCloud) -> Result<StructuredGrid> {
+        if !pc.has_row_column() {
+            Error::invalid("Point cloud has no row and column indices")?
+        }
+        let reader = self.pointcloud_simple(pc)?;
+        StructuredGrid::from_points(reader, pc.index_bounds.as_ref())
     }
 
     /// Returns an iterator for reading raw low level point cloud data.
@@ -117,6 +163,25 @@ impl<T: Read + Seek> E57Reader<T> {
         PointCloudReaderRaw::new(pc, &mut self.reader)
     }
 
+    /// Returns an iterator for reading only the selected records of the raw low level point cloud data.
+    /// The byte streams of all other records are skipped without decoding them,
+    /// which is much faster when only a few records of a big prototype are required.
+    /// The values of each point are returned in the order of the point cloud prototype,
+    /// see also [`PointCloudReaderRaw::prototype`].
+    pub fn pointcloud_raw_projected(
+        &mut self,
+        pc: &PointCloud,
+        records: &[RecordName],
+    ) -> Result<PointCloudReaderRaw<'_, T>> {
+        PointCloudReaderRaw::new_projected(pc, &mut self.reader, records)
+    }
+
+    /// Returns an iterator for reading the line groups of a point cloud.
+    /// See also [`PointCloud::line_grouping`] for the grouping metadata.
+    pub fn line_groups(&mut self, grouping: &LineGrouping) -> Result<LineGroupReader<'_, T>> {
+        LineGroupReader::new(grouping, &mut self.reader)
+    }
+
     /// Returns a list of all image descriptors in the file.
     pub fn images(&self) -> Vec<Image> {
         self.images.clone()
@@ -124,8 +189,13 @@ impl<T: Read + Seek> E57Reader<T> {
 
     /// Reads the content of a blob and copies it into the supplied writer.
     /// Returns the number of written bytes.
+    /// Blobs are always read with strict CRC validation, also in recovery mode,
+    /// since corrupt pages would silently be replaced with zeros.
     pub fn blob(&mut self, blob: &Blob, writer: &mut dyn Write) -> Result<u64> {
-        blob.read(&mut self.reader, writer)
+        self.reader.set_recovery(false);
+        let result = blob.read(&mut self.reader, writer);
+        self.reader.set_recovery(self.options.recover_corrupt_pages);
+        result
     }
 
     /// Returns the optional creation date and time of the file.
@@ -172,17 +242,66 @@ impl<T: Read + Seek> E57Reader<T> {
     /// This standalone function does only the minimal parsing required
     /// to get the XML section without any other checks or any other
     /// validation than basic CRC ckecking for the XML section itself.
-    pub fn raw_xml(mut reader: T) -> Result<Vec<u8>> {
+    pub fn raw_xml(reader: T) -> Result<Vec<u8>> {
+        Self::raw_xml_with_options(reader, E57ReaderOptions::default())
+    }
+
+    /// Same as [`E57Reader::raw_xml`] but with custom options.
+    /// The CRC validation, maximum page size and maximum XML size of the options are applied.
+    pub fn raw_xml_with_options(mut reader: T, options: E57ReaderOptions) -> Result<Vec<u8>> {
         let page_size = Self::get_u64(&mut reader, 40, "page size")?;
         let xml_offset = Self::get_u64(&mut reader, 24, "XML offset")?;
         let xml_length = Self::get_u64(&mut reader, 32, "XML length")?;
 
         // Create paged CRC reader
-        let mut paged_reader =
-            PagedReader::new(reader, page_size).read_err("Failed creating paged CRC reader")?;
+        let mut paged_reader = Self::paged_reader(reader, page_size, &options)?;
 
         // Read XML data
-        Self::extract_xml(&mut paged_reader, xml_offset, xml_length as usize)
+        Self::extract_xml(
+            &mut paged_reader,
+            xml_offset,
+            xml_length as usize,
+            options.max_xml_size,
+        )
+    }
+
+    /// Consumes the reader and returns the parsed header and XML metadata.
+    pub(crate) fn into_metadata(
+        self,
+    ) -> (Header, Root, Vec<PointCloud>, Vec<Image>, Vec<Extension>) {
+        (
+            self.header,
+            self.root,
+            self.pointclouds,
+            self.images,
+            self.extensions,
+        )
+    }
+
+    /// Creates a parallel point cloud reader with worker readers for the given shared source.
+    fn parallel_reader<R: ReadAt + Send + Sync + 'static>(
+        &mut self,
+        pc: &PointCloud,
+        source: Arc<R>,
+        threads: usize,
+    ) -> Result<PointCloudReaderParallel> {
+        let threads = if threads == 0 {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        } else {
+            threads
+        };
+        let mut worker_readers = Vec::with_capacity(threads);
+        for _ in 0..threads {
+            let source = SourceReader::new(source.clone());
+            worker_readers.push(E57Reader::paged_reader(
+                source,
+                self.header.page_size,
+                &self.options,
+            )?);
+        }
+        PointCloudReaderParallel::new(pc, &mut self.reader, worker_readers)
     }
 
     fn get_u64(reader: &mut T, offset: u64, name: &str) -> Result<u64> {
@@ -196,10 +315,26 @@ impl<T: Read + Seek> E57Reader<T> {
         Ok(u64::from_le_bytes(buf))
     }
 
-    fn extract_xml(reader: &mut PagedReader<T>, offset: u64, length: usize) -> Result<Vec<u8>> {
-        if length > MAX_XML_SIZE {
+    fn paged_reader(
+        reader: T,
+        page_size: u64,
+        options: &E57ReaderOptions,
+    ) -> Result<PagedReader<T>> {
+        let mut reader = PagedReader::with_max_page_size(reader, page_size, options.max_page_size)
+            .read_err("Failed creating paged CRC reader")?;
+        reader.set_crc_validation(options.crc_validation);
+        Ok(reader)
+    }
+
+    fn extract_xml(
+        reader: &mut PagedReader<T>,
+        offset: u64,
+        length: usize,
+        max_size: usize,
+    ) -> Result<Vec<u8>> {
+        if length > max_size {
             Error::not_implemented(format!(
-                "XML sections larger than {MAX_XML_SIZE} bytes are not supported"
+                "XML sections larger than {max_size} bytes are not supported"
             ))?
         }
         reader.seek_physical(offset).read_err(format!(
@@ -216,12 +351,106 @@ impl<T: Read + Seek> E57Reader<T> {
 impl E57Reader<BufReader<File>> {
     /// Creates an E57 instance from a Path.
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_file_with_options(path, E57ReaderOptions::default())
+    }
+
+    /// Creates an E57 instance from a Path with custom options.
+    pub fn from_file_with_options(
+        path: impl AsRef<Path>,
+        options: E57ReaderOptions,
+    ) -> Result<Self> {
         let file = File::open(path).read_err("Unable to open file")?;
 
         // Use a bigger buffer than the 8 KB default to reduce
         // the number of syscalls when reading big files sequentially.
-        let reader = BufReader::with_capacity(256 * 1024, file);
+        let reader = BufReader::with_capacity(options.buffer_size, file);
+
+        Self::with_options(reader, options)
+    }
+
+    /// Returns an iterator for reading raw low level point cloud data with multiple threads.
+    /// The points and values are identical to the ones returned by the raw iterator.
+    /// Each worker thread opens its own handle of the file and reads and decodes a separate range of points.
+    /// Use zero threads to automatically pick the available parallelism of the system.
+    #[cfg(any(unix, windows))]
+    pub fn pointcloud_raw_parallel(
+        &mut self,
+        pc: &PointCloud,
+        threads: usize,
+    ) -> Result<PointCloudReaderParallel> {
+        let file = self
+            .reader
+            .inner()
+            .get_ref()
+            .try_clone()
+            .read_err("Failed to clone file handle for worker threads")?;
+        self.parallel_reader(pc, Arc::new(file), threads)
+    }
+}
+
+impl<R: ReadAt> E57Reader<SourceReader<R>> {
+    /// Creates an E57 instance from a source that supports positional reads.
+    ///
+    /// In contrast to other readers, the source can be shared between multiple point cloud readers.
+    /// The `*_shared` methods only need a shared reference to the E57 reader,
+    /// so several point clouds can be read at the same time, for example from different threads.
+    /// The parsed header, XML and descriptors are not duplicated for that.
+    pub fn from_source(source: R) -> Result<Self> {
+        Self::from_source_with_options(source, E57ReaderOptions::default())
+    }
+
+    /// Creates an E57 instance from a source that supports positional reads with custom options.
+    /// The options are also used for all independent point cloud readers.
+    pub fn from_source_with_options(source: R, options: E57ReaderOptions) -> Result<Self> {
+        Self::with_options(SourceReader::new(Arc::new(source)), options)
+    }
+
+    /// Returns an independent iterator for reading point cloud data.
+    /// Works like [`E57Reader::pointcloud_simple`] but does not require exclusive access.
+    pub fn pointcloud_simple_shared(
+        &self,
+        pc: &PointCloud,
+    ) -> Result<PointCloudReaderSimple<'_, SourceReader<R>>> {
+        PointCloudReaderSimple::new(pc, &self.extensions, self.shared_reader()?)
+    }
+
+    /// Returns an independent iterator for reading raw low level point cloud data.
+    /// Works like [`E57Reader::pointcloud_raw`] but does not require exclusive access.
+    pub fn pointcloud_raw_shared(
+        &self,
+        pc: &PointCloud,
+    ) -> Result<PointCloudReaderRaw<'_, SourceReader<R>>> {
+        PointCloudReaderRaw::new(pc, self.shared_reader()?)
+    }
+
+    /// Returns an iterator for reading raw low level point cloud data with multiple threads.
+    /// The points and values are identical to the ones returned by the raw iterator.
+    /// Each worker thread has its own reader for the shared source and reads and decodes a separate range of points.
+    /// Use zero threads to automatically pick the available parallelism of the system.
+    pub fn pointcloud_raw_parallel(
+        &mut self,
+        pc: &PointCloud,
+        threads: usize,
+    ) -> Result<PointCloudReaderParallel>
+    where
+        R: Send + Sync + 'static,
+    {
+        let source = self.reader.inner().source().clone();
+        self.parallel_reader(pc, source, threads)
+    }
+
+    /// Reads the content of a blob and copies it into the supplied writer.
+    /// Works like [`E57Reader::blob`] but does not require exclusive access.
+    pub fn blob_shared(&self, blob: &Blob, writer: &mut dyn Write) -> Result<u64> {
+        let mut reader = self.shared_reader()?;
+        reader.set_recovery(false);
+        blob.read(&mut reader, writer)
+    }
 
-        Self::new(reader)
+    fn shared_reader(&self) -> Result<PagedReader<SourceReader<R>>> {
+        let source = SourceReader::new(self.reader.inner().source().clone());
+        let mut reader = Self::paged_reader(source, self.header.page_size, &self.options)?;
+        reader.set_recovery(self.options.recover_corrupt_pages);
+        Ok(reader)
     }
 }
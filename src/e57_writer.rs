@@ -1,12 +1,14 @@
 use crate::error::Converter;
-use crate::paged_writer::PagedWriter;
+use crate::extension::{set_extension_data, SURFACE_NORMALS_NAMES};
+use crate::paged_writer::{PagedWriter, PAGE_SIZE};
 use crate::pc_writer::PointCloudWriter;
 use crate::root::{serialize_root, Root};
 use crate::{
-    Blob, DateTime, Error, Extension, Header, Image, ImageWriter, PointCloud, Record, Result,
+    Blob, DateTime, E57Reader, Error, Extension, ExtensionData, Header, Image, ImageWriter,
+    PointCloud, Record, RecordName, Result, XmlElement,
 };
 use std::fs::{File, OpenOptions};
-use std::io::{Read, Seek, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
 
 /// Main interface for creating and writing E57 files.
@@ -61,13 +63,32 @@ impl<T: Write + Read + Seek> E57Writer<T> {
     }
 
     /// Creates a new writer for adding a new point cloud to the E57 file.
+    /// If the prototype contains surface normals with the default namespace and this namespace is not yet used,
+    /// the surface normals extension is registered automatically.
     pub fn add_pointcloud(
         &mut self,
         guid: &str,
         prototype: Vec<Record>,
     ) -> Result<PointCloudWriter<'_, T>> {
+        let normals = SURFACE_NORMALS_NAMES
+            .map(RecordName::surface_normal)
+            .iter()
+            .any(|n| prototype.iter().any(|r| &r.name == n));
+        let registered = self
+            .extensions
+            .iter()
+            .any(|e| e.namespace == Extension::SURFACE_NORMALS_NAMESPACE);
+        if normals && !registered {
+            self.extensions.push(Extension::surface_normals());
+        }
         Extension::validate_prototype(&prototype, &self.extensions)?;
-        PointCloudWriter::new(&mut self.writer, &mut self.pointclouds, guid, prototype)
+        PointCloudWriter::new(
+            &mut self.writer,
+            &mut self.pointclouds,
+            &mut self.extensions,
+            guid,
+            prototype,
+        )
     }
 
     /// Adds a new binary data section to the E57 file.
@@ -78,7 +99,26 @@ impl<T: Write + Read + Seek> E57Writer<T> {
 
     /// Creates a new image writer for adding an image to the E57 file.
     pub fn add_image(&mut self, guid: &str) -> Result<ImageWriter<'_, T>> {
-        ImageWriter::new(&mut self.writer, &mut self.images, guid)
+        ImageWriter::new(
+            &mut self.writer,
+            &mut self.images,
+            &mut self.extensions,
+            guid,
+        )
+    }
+
+    /// Attaches typed extension data to the root of the E57 file.
+    /// Replaces any previously set data of the same type.
+    /// The extension of the data is registered automatically, if required.
+    pub fn set_extension_data<D: ExtensionData>(&mut self, data: &D) -> Result<()> {
+        set_extension_data(&mut self.root.additional_xml, &mut self.extensions, data)
+    }
+
+    /// Sets additional XML elements for the root of the E57 file, for example from an existing file.
+    /// Replaces all previously set elements, including typed extension data.
+    /// Namespaces used by the elements must be registered as extensions.
+    pub fn set_additional_xml(&mut self, elements: Vec<XmlElement>) {
+        self.root.additional_xml = elements;
     }
 
     /// Registers a new E57 extension used by this file.
@@ -161,4 +201,62 @@ impl E57Writer<File> {
             .read_err("Unable to create file for writing, reading and seeking")?;
         Self::new(file, guid)
     }
+
+    /// Opens an existing E57 file to append new point clouds, images and blobs.
+    ///
+    /// All existing point clouds, images, extensions and root metadata are kept.
+    /// New data is written over the old XML section, if it is located at the end of the file.
+    /// Otherwise it is appended after the end of the file.
+    /// The file is incomplete and invalid until `finalize()` writes the merged XML section!
+    /// Only files with the default page size of 1024 bytes are supported.
+    pub fn open_append(path: impl AsRef<Path>) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .read(true)
+            .open(path)
+            .read_err("Unable to open file for reading and writing")?;
+        let (header, root, pointclouds, images, extensions) =
+            E57Reader::new(&mut file)?.into_metadata();
+        if header.page_size != PAGE_SIZE {
+            Error::not_implemented(format!(
+                "Appending to files with a page size of {} bytes is not supported, only {PAGE_SIZE} bytes",
+                header.page_size
+            ))?
+        }
+
+        // Find position after the last binary section
+        let file_size = file
+            .seek(SeekFrom::End(0))
+            .read_err("Unable to seek length of file")?;
+        let start = if xml_end(&header) >= file_size {
+            header.phys_xml_offset
+        } else {
+            file_size
+        };
+
+        // Remove all following pages, they contain only the old XML section
+        file.set_len(start.div_ceil(header.page_size) * header.page_size)
+            .write_err("Failed to remove old XML section")?;
+
+        let mut writer = PagedWriter::from_existing(file)?;
+        writer.physical_seek(start)?;
+        writer.align()?;
+        Ok(Self {
+            writer,
+            pointclouds,
+            images,
+            extensions,
+            root,
+        })
+    }
+}
+
+/// Calculates the physical end offset of the last page of the XML section.
+fn xml_end(header: &Header) -> u64 {
+    // Each page ends with a 4 byte checksum
+    let payload_size = header.page_size - 4;
+    let page = header.phys_xml_offset / header.page_size;
+    let page_offset = header.phys_xml_offset % header.page_size;
+    let logical_end = page * payload_size + page_offset + header.xml_length;
+    logical_end.div_ceil(payload_size) * header.page_size
 }
@@ -1,6 +1,21 @@
-use crate::{Error, Record, RecordName, Result};
+use crate::{Error, Record, RecordDataType, RecordName, Result, XmlElement};
 use roxmltree::Document;
 
+/// Record names of the surface normal components defined by the libE57 extension.
+pub(crate) const SURFACE_NORMALS_NAMES: [&str; 3] = ["normalX", "normalY", "normalZ"];
+
+/// Returns the record names of the surface normal components.
+/// The namespace is resolved by the URL of the extension, because files can use any namespace name for it.
+pub(crate) fn surface_normal_names(extensions: &[Extension]) -> Option<[RecordName; 3]> {
+    let extension = extensions
+        .iter()
+        .find(|e| e.url == Extension::SURFACE_NORMALS_URL)?;
+    Some(SURFACE_NORMALS_NAMES.map(|name| RecordName::Unknown {
+        namespace: extension.namespace.clone(),
+        name: name.to_owned(),
+    }))
+}
+
 /// Describes an E57 extension by name and URL.
 ///
 /// The E57 specification includes an mechanism for extensions.
@@ -21,8 +36,13 @@ use roxmltree::Document;
 /// * reading and defining of XML namespaces for extensions
 /// * reading and writing additional custom point attributes
 /// * reading and writing of binary blobs
+/// * reading and writing of surface normals with the libE57 extension, see [`Extension::surface_normals`]
 ///
-/// Extensions that require specific XML parsing are possible.
+/// Extensions that store custom metadata in the XML section can implement the [`ExtensionData`] trait.
+/// Such data can be attached to the root of the file, to point clouds and to images.
+/// The library takes care of registering the namespace, writing the XML and parsing it when reading.
+///
+/// Extensions that require other XML manipulation are also possible.
 /// You need to load your E57 file and then call `E57Reader::xml()` method to get the full original XML string.
 /// This will return an UTF8 string that can be feed into an XML parser.
 /// This library is using `roxmltree` for lightweight XML parsing.
@@ -47,6 +67,20 @@ pub struct Extension {
 }
 
 impl Extension {
+    /// XML namespace of the surface normals extension defined by libE57.
+    pub const SURFACE_NORMALS_NAMESPACE: &'static str = "nor";
+
+    /// XML namespace URL of the surface normals extension defined by libE57.
+    pub const SURFACE_NORMALS_URL: &'static str =
+        "http://www.libe57.org/E57_EXT_surface_normals.txt";
+
+    /// Returns the surface normals extension defined by libE57.
+    /// The E57 writer registers this extension automatically when a prototype contains normals.
+    /// See also [`Record::normal_x_f32`], [`Record::normal_y_f32`] and [`Record::normal_z_f32`].
+    pub fn surface_normals() -> Self {
+        Self::new(Self::SURFACE_NORMALS_NAMESPACE, Self::SURFACE_NORMALS_URL)
+    }
+
     /// Intialize and return a new Extension structure with the given values.
     pub fn new(namespace: &str, url: &str) -> Self {
         Self {
@@ -68,6 +102,26 @@ impl Extension {
         extensions
     }
 
+    /// Registers an extension if its namespace is not yet in the list.
+    /// Fails if the namespace is already used with a different URL.
+    pub(crate) fn register(extensions: &mut Vec<Extension>, extension: Extension) -> Result<()> {
+        if let Some(existing) = extensions
+            .iter()
+            .find(|e| e.namespace == extension.namespace)
+        {
+            if existing.url != extension.url {
+                Error::invalid(format!(
+                    "The extension namespace {} is already registered with the URL {}",
+                    extension.namespace, existing.url
+                ))?
+            }
+            return Ok(());
+        }
+        Self::validate_name(&extension.namespace)?;
+        extensions.push(extension);
+        Ok(())
+    }
+
     pub(crate) fn validate_prototype(prototype: &[Record], extensions: &[Extension]) -> Result<()> {
         for record in prototype {
             if let RecordName::Unknown { namespace, name } = &record.name {
@@ -80,6 +134,39 @@ impl Extension {
                 }
             }
         }
+        Self::validate_normals(prototype, extensions)
+    }
+
+    /// Checks the surface normal records of a prototype.
+    /// Records are identified by the URL of their namespace and not by the namespace name.
+    fn validate_normals(prototype: &[Record], extensions: &[Extension]) -> Result<()> {
+        let mut normals = 0;
+        for record in prototype {
+            let RecordName::Unknown { namespace, name } = &record.name else {
+                continue;
+            };
+            let url = extensions
+                .iter()
+                .find(|e| &e.namespace == namespace)
+                .map(|e| e.url.as_str());
+            if url != Some(Self::SURFACE_NORMALS_URL) || !SURFACE_NORMALS_NAMES.contains(&&**name) {
+                continue;
+            }
+            normals += 1;
+            match record.data_type {
+                RecordDataType::Single { .. }
+                | RecordDataType::Double { .. }
+                | RecordDataType::ScaledInteger { .. } => {}
+                _ => Error::invalid(
+                    "Surface normals must have a floating point or scaled integer type",
+                )?,
+            }
+        }
+        if normals != 0 && normals != 3 {
+            Error::invalid(
+                "You have to include all three surface normal components for X, Y and Z",
+            )?
+        }
         Ok(())
     }
 
@@ -104,6 +191,69 @@ impl Extension {
     }
 }
 
+/// Typed metadata of an extension that is stored as XML element.
+///
+/// Implement this trait for your own types to attach them to the root of an E57 file,
+/// to point clouds or to images. When writing, the extension is registered automatically
+/// and the data is serialized into the XML section of the file.
+/// When reading, the data can be parsed back from the XML elements that were
+/// collected while opening the file.
+///
+/// See [`E57Writer::set_extension_data`](crate::E57Writer::set_extension_data),
+/// [`PointCloudWriter::set_extension_data`](crate::PointCloudWriter::set_extension_data),
+/// [`ImageWriter::set_extension_data`](crate::ImageWriter::set_extension_data),
+/// [`E57Reader::extension_data`](crate::E57Reader::extension_data),
+/// [`PointCloud::extension_data`](crate::PointCloud::extension_data) and
+/// [`Image::extension_data`](crate::Image::extension_data).
+pub trait ExtensionData: Sized {
+    /// Returns the extension with the XML namespace used for the data.
+    /// When reading, elements are identified by the URL of the extension,
+    /// so files that use another namespace prefix for the same URL are supported.
+    fn extension() -> Extension;
+
+    /// Returns the local name of the XML element that contains the data.
+    fn element_name() -> &'static str;
+
+    /// Parses the data from its XML element.
+    fn from_xml(element: &XmlElement) -> Result<Self>;
+
+    /// Serializes the data into an XML element.
+    /// The library sets name and namespace of the returned element.
+    /// All child elements without namespace are moved into the namespace of the extension.
+    fn to_xml(&self) -> Result<XmlElement>;
+}
+
+/// Finds and parses the element of an extension data type.
+pub(crate) fn get_extension_data<D: ExtensionData>(elements: &[XmlElement]) -> Result<Option<D>> {
+    let extension = D::extension();
+    let name = D::element_name();
+    elements
+        .iter()
+        .find(|e| e.url == extension.url && e.name == name)
+        .map(D::from_xml)
+        .transpose()
+}
+
+/// Serializes extension data and replaces any existing element with the same name.
+pub(crate) fn set_extension_data<D: ExtensionData>(
+    elements: &mut Vec<XmlElement>,
+    extensions: &mut Vec<Extension>,
+    data: &D,
+) -> Result<()> {
+    let extension = D::extension();
+    let name = D::element_name();
+    Extension::validate_name(name)?;
+    let mut element = data.to_xml()?;
+    element.name = name.to_owned();
+    element.namespace.clear();
+    element.url.clear();
+    element.set_default_namespace(&extension);
+    Extension::register(extensions, extension)?;
+    elements.retain(|e| e.url != element.url || e.name != element.name);
+    elements.push(element);
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,336 @@
+use crate::error::Converter;
+use crate::{CartesianBounds, Error, Quaternion, Record, RecordName, RecordValue, Result};
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// Signature of custom filter functions.
+/// Receives the prototype and the raw values of a point and returns true to keep the point.
+pub type CustomFilter = dyn Fn(&[Record], &[RecordValue]) -> bool + Send + Sync;
+
+/// Filter to select points while reading a point cloud.
+///
+/// Filters are evaluated directly after decoding the raw values of a point,
+/// rejected points are never converted or returned by the readers.
+/// See also [`PointCloudReaderSimple::add_filter`](crate::PointCloudReaderSimple::add_filter)
+/// and [`PointCloudReaderRaw::add_filter`](crate::PointCloudReaderRaw::add_filter).
+///
+/// All spatial filters work with the coordinates as stored in the point cloud,
+/// which means without the pose of the point cloud applied.
+/// Spherical coordinates are converted to Cartesian coordinates if required and vice versa.
+#[derive(Clone)]
+pub enum PointFilter {
+    /// Keeps points inside an axis-aligned box.
+    Box { min: [f64; 3], max: [f64; 3] },
+    /// Keeps points inside a box with a rotation.
+    /// The rotation describes the orientation of the box axes.
+    OrientedBox {
+        center: [f64; 3],
+        half_size: [f64; 3],
+        rotation: Quaternion,
+    },
+    /// Keeps points inside a sphere.
+    Sphere { center: [f64; 3], radius: f64 },
+    /// Keeps points with a distance to the origin between min and max.
+    Range { min: f64, max: f64 },
+    /// Keeps points with an elevation angle in radians between min and max.
+    Elevation { min: f64, max: f64 },
+    /// Keeps points with an intensity of at least the given value.
+    /// The threshold is compared with the raw intensity value and not with a normalized intensity.
+    /// Points without valid intensity are rejected.
+    Intensity { min: f64 },
+    /// Rejects points where the invalid state record with the given name is not zero.
+    /// For example [`RecordName::CartesianInvalidState`] or [`RecordName::IsColorInvalid`].
+    ExcludeInvalid(RecordName),
+    /// Keeps points for which the custom function returns true.
+    Custom(Arc<CustomFilter>),
+}
+
+impl PointFilter {
+    /// Creates a filter with a custom function that receives the prototype and the raw values of each point.
+    pub fn custom(f: impl Fn(&[Record], &[RecordValue]) -> bool + Send + Sync + 'static) -> Self {
+        Self::Custom(Arc::new(f))
+    }
+
+    /// Returns false if the filter cannot keep any points inside the given bounds.
+    fn intersects(&self, bounds: &CartesianBounds) -> bool {
+        let min = [
+            bounds.x_min.unwrap_or(f64::NEG_INFINITY),
+            bounds.y_min.unwrap_or(f64::NEG_INFINITY),
+            bounds.z_min.unwrap_or(f64::NEG_INFINITY),
+        ];
+        let max = [
+            bounds.x_max.unwrap_or(f64::INFINITY),
+            bounds.y_max.unwrap_or(f64::INFINITY),
+            bounds.z_max.unwrap_or(f64::INFINITY),
+        ];
+        match self {
+            PointFilter::Box {
+                min: box_min,
+                max: box_max,
+            } => (0..3).all(|i| box_min[i] <= max[i] && box_max[i] >= min[i]),
+            PointFilter::OrientedBox {
+                center,
+                half_size,
+                rotation,
+            } => {
+                // Compare with the axis-aligned box around the oriented box
+                let m = rotation.normalized().to_matrix();
+                (0..3).all(|i| {
+                    let extent = (0..3).map(|j| m[i][j].abs() * half_size[j]).sum::<f64>();
+                    center[i] - extent <= max[i] && center[i] + extent >= min[i]
+                })
+            }
+            PointFilter::Sphere { center, radius } => {
+                let squared_distance = (0..3)
+                    .map(|i| center[i] - center[i].clamp(min[i], max[i]))
+                    .map(|d| d * d)
+                    .sum::<f64>();
+                squared_distance <= radius * radius
+            }
+            _ => true,
+        }
+    }
+}
+
+impl Debug for PointFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Box { min, max } => f
+                .debug_struct("Box")
+                .field("min", min)
+                .field("max", max)
+                .finish(),
+            Self::OrientedBox {
+                center,
+                half_size,
+                rotation,
+            } => f
+                .debug_struct("OrientedBox")
+                .field("center", center)
+                .field("half_size", half_size)
+                .field("rotation", rotation)
+                .finish(),
+            Self::Sphere { center, radius } => f
+                .debug_struct("Sphere")
+                .field("center", center)
+                .field("radius", radius)
+                .finish(),
+            Self::Range { min, max } => f
+                .debug_struct("Range")
+                .field("min", min)
+                .field("max", max)
+                .finish(),
+            Self::Elevation { min, max } => f
+                .debug_struct("Elevation")
+                .field("min", min)
+                .field("max", max)
+                .finish(),
+            Self::Intensity { min } => f.debug_struct("Intensity").field("min", min).finish(),
+            Self::ExcludeInvalid(name) => f.debug_tuple("ExcludeInvalid").field(name).finish(),
+            Self::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+/// Lookup table to extract coordinates from the raw values of points.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Coordinates {
+    cartesian: Option<(usize, usize, usize)>,
+    spherical: Option<(usize, usize, usize)>,
+}
+
+impl Coordinates {
+    pub fn new(prototype: &[Record]) -> Self {
+        let fi = |name: RecordName| prototype.iter().position(|r| r.name == name);
+        let cartesian = match (
+            fi(RecordName::CartesianX),
+            fi(RecordName::CartesianY),
+            fi(RecordName::CartesianZ),
+        ) {
+            (Some(x), Some(y), Some(z)) => Some((x, y, z)),
+            _ => None,
+        };
+        let spherical = match (
+            fi(RecordName::SphericalRange),
+            fi(RecordName::SphericalAzimuth),
+            fi(RecordName::SphericalElevation),
+        ) {
+            (Some(r), Some(a), Some(e)) => Some((r, a, e)),
+            _ => None,
+        };
+        Self {
+            cartesian,
+            spherical,
+        }
+    }
+
+    /// Returns true if the prototype has neither Cartesian nor spherical coordinates.
+    pub fn is_empty(&self) -> bool {
+        self.cartesian.is_none() && self.spherical.is_none()
+    }
+
+    /// Extracts Cartesian coordinates, converts spherical coordinates if required.
+    pub fn cartesian(&self, prototype: &[Record], values: &[RecordValue]) -> Result<[f64; 3]> {
+        if let Some((x, y, z)) = self.cartesian {
+            Ok([
+                values[x].to_f64(&prototype[x].data_type)?,
+                values[y].to_f64(&prototype[y].data_type)?,
+                values[z].to_f64(&prototype[z].data_type)?,
+            ])
+        } else {
+            let [range, azimuth, elevation] = self.spherical(prototype, values)?;
+            let cos_ele = elevation.cos();
+            Ok([
+                range * cos_ele * azimuth.cos(),
+                range * cos_ele * azimuth.sin(),
+                range * elevation.sin(),
+            ])
+        }
+    }
+
+    /// Extracts spherical coordinates, converts Cartesian coordinates if required.
+    pub fn spherical(&self, prototype: &[Record], values: &[RecordValue]) -> Result<[f64; 3]> {
+        if let Some((r, a, e)) = self.spherical {
+            Ok([
+                values[r].to_f64(&prototype[r].data_type)?,
+                values[a].to_f64(&prototype[a].data_type)?,
+                values[e].to_f64(&prototype[e].data_type)?,
+            ])
+        } else if self.cartesian.is_some() {
+            let [x, y, z] = self.cartesian(prototype, values)?;
+            let range = (x * x + y * y + z * z).sqrt();
+            Ok([range, y.atan2(x), z.atan2((x * x + y * y).sqrt())])
+        } else {
+            Error::internal("Cannot extract coordinates without coordinate records")
+        }
+    }
+}
+
+/// Set of filters for a specific prototype that are evaluated on the raw values of points.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PointFilters {
+    filters: Vec<PointFilter>,
+    coordinates: Coordinates,
+    intensity: Option<usize>,
+    intensity_invalid: Option<usize>,
+    invalid_states: Vec<usize>,
+}
+
+impl PointFilters {
+    pub fn new(prototype: &[Record]) -> Self {
+        let fi = |name: RecordName| prototype.iter().position(|r| r.name == name);
+        Self {
+            filters: Vec::new(),
+            coordinates: Coordinates::new(prototype),
+            intensity: fi(RecordName::Intensity),
+            intensity_invalid: fi(RecordName::IsIntensityInvalid),
+            invalid_states: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    /// Adds a filter after checking that all required records exist in the prototype.
+    pub fn add(&mut self, filter: PointFilter, prototype: &[Record]) -> Result<()> {
+        match &filter {
+            PointFilter::Box { .. }
+            | PointFilter::OrientedBox { .. }
+            | PointFilter::Sphere { .. }
+            | PointFilter::Range { .. }
+            | PointFilter::Elevation { .. } => {
+                if self.coordinates.is_empty() {
+                    Error::invalid("Spatial filters require Cartesian or spherical coordinates")?
+                }
+            }
+            PointFilter::Intensity { .. } => {
+                if self.intensity.is_none() {
+                    Error::invalid("Intensity filter requires an intensity record")?
+                }
+            }
+            PointFilter::ExcludeInvalid(name) => {
+                let index = prototype
+                    .iter()
+                    .position(|r| r.name == *name)
+                    .invalid_err(format!("Cannot find invalid state record {name:?}"))?;
+                self.invalid_states.push(index);
+            }
+            PointFilter::Custom(_) => {}
+        }
+        self.filters.push(filter);
+        Ok(())
+    }
+
+    /// Returns false if the filters will reject all points inside the given bounds.
+    pub fn intersects(&self, bounds: &Option<CartesianBounds>) -> bool {
+        match bounds {
+            Some(bounds) => self.filters.iter().all(|f| f.intersects(bounds)),
+            None => true,
+        }
+    }
+
+    /// Returns true if the point with the given raw values passes all filters.
+    pub fn matches(&self, prototype: &[Record], values: &[RecordValue]) -> Result<bool> {
+        for index in &self.invalid_states {
+            if values[*index].to_i64(&prototype[*index].data_type)? != 0 {
+                return Ok(false);
+            }
+        }
+        for filter in &self.filters {
+            let keep = match filter {
+                PointFilter::Box { min, max } => {
+                    let p = self.coordinates.cartesian(prototype, values)?;
+                    (0..3).all(|i| p[i] >= min[i] && p[i] <= max[i])
+                }
+                PointFilter::OrientedBox {
+                    center,
+                    half_size,
+                    rotation,
+                } => {
+                    let p = self.coordinates.cartesian(prototype, values)?;
+                    let m = rotation.normalized().to_matrix();
+                    let d = [p[0] - center[0], p[1] - center[1], p[2] - center[2]];
+                    // Transform into the local box coordinate system with the transposed rotation
+                    (0..3).all(|j| {
+                        let local = (0..3).map(|i| m[i][j] * d[i]).sum::<f64>();
+                        local.abs() <= half_size[j]
+                    })
+                }
+                PointFilter::Sphere { center, radius } => {
+                    let p = self.coordinates.cartesian(prototype, values)?;
+                    let squared_distance =
+                        (0..3).map(|i| p[i] - center[i]).map(|d| d * d).sum::<f64>();
+                    squared_distance <= radius * radius
+                }
+                PointFilter::Range { min, max } => {
+                    let range = self.coordinates.spherical(prototype, values)?[0];
+                    range >= *min && range <= *max
+                }
+                PointFilter::Elevation { min, max } => {
+                    let elevation = self.coordinates.spherical(prototype, values)?[2];
+                    elevation >= *min && elevation <= *max
+                }
+                PointFilter::Intensity { min } => self.intensity(prototype, values)? >= *min,
+                PointFilter::ExcludeInvalid(_) => true,
+                PointFilter::Custom(f) => f(prototype, values),
+            };
+            if !keep {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn intensity(&self, prototype: &[Record], values: &[RecordValue]) -> Result<f64> {
+        if let Some(ind) = self.intensity_invalid {
+            if values[ind].to_i64(&prototype[ind].data_type)? != 0 {
+                return Ok(f64::NAN);
+            }
+        }
+        let ind = self
+            .intensity
+            .internal_err("Intensity filter without intensity record")?;
+        values[ind].to_f64(&prototype[ind].data_type)
+    }
+}
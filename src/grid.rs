@@ -0,0 +1,213 @@
+use crate::error::Converter;
+use crate::{Error, IndexBounds, Point, Result};
+
+/// Marker for empty cells in the lookup table.
+const EMPTY: usize = usize::MAX;
+
+/// Two-dimensional grid with the points of a structured scan.
+///
+/// The points are placed in the grid according to their row and column indices.
+/// Grid coordinates start at zero for the smallest row and column index of the point cloud,
+/// see [`StructuredGrid::row_offset`] and [`StructuredGrid::column_offset`].
+/// Each cell can hold multiple points with different return indices.
+/// Points without return index are treated as first return.
+/// If a cell contains multiple points with the same return index, the last one is kept.
+///
+/// Use [`E57Reader::structured_grid`](crate::E57Reader::structured_grid) to load a point cloud as grid.
+#[derive(Clone, Debug)]
+pub struct StructuredGrid {
+    rows: usize,
+    columns: usize,
+    returns: usize,
+    row_offset: i64,
+    column_offset: i64,
+    cells: Vec<usize>,
+    points: Vec<Point>,
+}
+
+impl StructuredGrid {
+    /// Creates a grid from points with row and column indices.
+    /// The optional index bounds of the point cloud are used to extend the grid size,
+    /// for example to keep empty rows or columns at the borders of the scan.
+    /// Use this function if you need a custom configuration of the point cloud reader.
+    pub fn from_points(
+        points: impl IntoIterator<Item = Result<Point>>,
+        bounds: Option<&IndexBounds>,
+    ) -> Result<Self> {
+        let points = points.into_iter().collect::<Result<Vec<Point>>>()?;
+
+        let mut row_min = bounds.and_then(|b| b.row_min).unwrap_or(i64::MAX);
+        let mut row_max = bounds.and_then(|b| b.row_max).unwrap_or(i64::MIN);
+        let mut column_min = bounds.and_then(|b| b.column_min).unwrap_or(i64::MAX);
+        let mut column_max = bounds.and_then(|b| b.column_max).unwrap_or(i64::MIN);
+        let mut return_max = 0;
+        for p in &points {
+            row_min = row_min.min(p.row);
+            row_max = row_max.max(p.row);
+            column_min = column_min.min(p.column);
+            column_max = column_max.max(p.column);
+            return_max = return_max.max(p.return_index);
+        }
+
+        if points.is_empty() || row_min > row_max || column_min > column_max {
+            return Ok(Self {
+                rows: 0,
+                columns: 0,
+                returns: 0,
+                row_offset: 0,
+                column_offset: 0,
+                cells: Vec::new(),
+                points,
+            });
+        }
+
+        let rows = extent(row_min, row_max)?;
+        let columns = extent(column_min, column_max)?;
+        let returns = extent(0, return_max)?;
+        let Some(size) = rows
+            .checked_mul(columns)
+            .and_then(|s| s.checked_mul(returns))
+        else {
+            Error::invalid(format!(
+                "Grid with {rows}x{columns} cells and {returns} returns is too big"
+            ))?
+        };
+        let mut cells = vec![EMPTY; size];
+        for (i, p) in points.iter().enumerate() {
+            let row = p.row.abs_diff(row_min) as usize;
+            let column = p.column.abs_diff(column_min) as usize;
+            let ret = p.return_index.max(0) as usize;
+            cells[(row * columns + column) * returns + ret] = i;
+        }
+
+        Ok(Self {
+            rows,
+            columns,
+            returns,
+            row_offset: row_min,
+            column_offset: column_min,
+            cells,
+            points,
+        })
+    }
+
+    /// Returns the number of rows of the grid.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the number of columns of the grid.
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Returns the maximum number of returns per cell.
+    /// This is one for point clouds without return index.
+    pub fn returns(&self) -> usize {
+        self.returns
+    }
+
+    /// Returns the row index of the point cloud that corresponds to the first grid row.
+    pub fn row_offset(&self) -> i64 {
+        self.row_offset
+    }
+
+    /// Returns the column index of the point cloud that corresponds to the first grid column.
+    pub fn column_offset(&self) -> i64 {
+        self.column_offset
+    }
+
+    /// Returns all points of the grid in the order they were read.
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    /// Returns the first return of the cell at the given grid coordinates.
+    /// Returns None for empty cells and coordinates outside of the grid.
+    pub fn get(&self, row: usize, column: usize) -> Option<&Point> {
+        self.cell(row, column)?.iter().find_map(|i| self.point(*i))
+    }
+
+    /// Returns the point with the given return index in the cell at the given grid coordinates.
+    pub fn get_return(&self, row: usize, column: usize, return_index: usize) -> Option<&Point> {
+        self.point(*self.cell(row, column)?.get(return_index)?)
+    }
+
+    /// Returns an iterator over all returns of the cell at the given grid coordinates.
+    pub fn returns_at(&self, row: usize, column: usize) -> impl Iterator<Item = &Point> {
+        self.cell(row, column)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|i| self.point(*i))
+    }
+
+    /// Returns true if the cell at the given grid coordinates contains at least one point.
+    pub fn is_valid(&self, row: usize, column: usize) -> bool {
+        self.get(row, column).is_some()
+    }
+
+    /// Returns a row-major mask with one entry per cell, which is true for cells containing points.
+    pub fn validity_mask(&self) -> Vec<bool> {
+        self.cells
+            .chunks(self.returns.max(1))
+            .map(|c| c.iter().any(|i| *i != EMPTY))
+            .collect()
+    }
+
+    /// Returns an iterator over the non-empty direct and diagonal neighbours of a cell.
+    /// Each item contains the grid coordinates of the neighbour and its first return.
+    pub fn neighbours(
+        &self,
+        row: usize,
+        column: usize,
+    ) -> impl Iterator<Item = (usize, usize, &Point)> {
+        (-1..=1_isize)
+            .flat_map(|dr| (-1..=1_isize).map(move |dc| (dr, dc)))
+            .filter(|d| *d != (0, 0))
+            .filter_map(move |(dr, dc)| {
+                let r = row.checked_add_signed(dr)?;
+                let c = column.checked_add_signed(dc)?;
+                self.get(r, c).map(|p| (r, c, p))
+            })
+    }
+
+    /// Returns an iterator over the first returns of all cells in the given row.
+    /// Empty cells are returned as None.
+    pub fn row(&self, row: usize) -> impl Iterator<Item = Option<&Point>> {
+        let columns = if row < self.rows { self.columns } else { 0 };
+        (0..columns).map(move |c| self.get(row, c))
+    }
+
+    /// Returns an iterator over the first returns of all cells in the given column.
+    /// Empty cells are returned as None.
+    pub fn column(&self, column: usize) -> impl Iterator<Item = Option<&Point>> {
+        let rows = if column < self.columns { self.rows } else { 0 };
+        (0..rows).map(move |r| self.get(r, column))
+    }
+
+    fn cell(&self, row: usize, column: usize) -> Option<&[usize]> {
+        if row >= self.rows || column >= self.columns {
+            return None;
+        }
+        let start = (row * self.columns + column) * self.returns;
+        self.cells.get(start..start + self.returns)
+    }
+
+    fn point(&self, index: usize) -> Option<&Point> {
+        if index == EMPTY {
+            None
+        } else {
+            self.points.get(index)
+        }
+    }
+}
+
+/// Returns the number of values between the minimum and maximum index, including both.
+fn extent(min: i64, max: i64) -> Result<usize> {
+    max.checked_sub(min)
+        .and_then(|d| d.checked_add(1))
+        .and_then(|d| usize::try_from(d).ok())
+        .invalid_err(format!(
+            "Index range from {min} to {max} is too big for a grid"
+        ))
+}
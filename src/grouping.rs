@@ -0,0 +1,378 @@
+use crate::error::Converter;
+use crate::xml;
+use crate::{
+    CartesianBounds, Error, RawValues, Record, RecordDataType, RecordName, RecordValue, Result,
+    SphericalBounds,
+};
+use roxmltree::Node;
+use std::collections::HashSet;
+
+/// Describes how the points of a point cloud are grouped into lines.
+///
+/// The line groups itself are stored in a separate binary section of the E57 file.
+/// Use [`E57Reader::line_groups`](crate::E57Reader::line_groups) to read them.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct LineGrouping {
+    /// Name of the point attribute that identifies the line of a point, for example `rowIndex` or `columnIndex`.
+    pub id_element_name: String,
+    /// Number of line groups.
+    pub group_count: u64,
+    /// Physical file offset of the start of the binary section with the line groups.
+    pub file_offset: u64,
+    /// Flattened list of the attributes stored for each line group.
+    pub(crate) prototype: Vec<GroupRecord>,
+}
+
+/// Attribute of the line group prototype.
+/// Nested structures like `cartesianBounds` are flattened,
+/// each attribute keeps the names of its enclosing structures.
+#[derive(Clone, Debug)]
+pub(crate) struct GroupRecord {
+    /// Names of the enclosing structures from the outside to the inside, empty for top level attributes.
+    pub parents: Vec<String>,
+    /// Name of the attribute itself.
+    pub name: String,
+    pub data_type: RecordDataType,
+}
+
+impl GroupRecord {
+    fn new(parents: &[&str], name: &str, data_type: RecordDataType) -> Self {
+        Self {
+            parents: parents.iter().map(|p| (*p).to_owned()).collect(),
+            name: name.to_owned(),
+            data_type,
+        }
+    }
+
+    /// Returns a record with the same data type, which can be used to read and write the compressed vector.
+    pub fn to_record(&self) -> Record {
+        Record {
+            name: RecordName::Unknown {
+                namespace: String::new(),
+                name: self.name.clone(),
+            },
+            data_type: self.data_type.clone(),
+        }
+    }
+}
+
+impl LineGrouping {
+    pub(crate) fn from_node(node: &Node) -> Result<Option<Self>> {
+        let line_node = match node.children().find(|n| n.has_tag_name("groupingByLine")) {
+            Some(line_node) => line_node,
+            None => return Ok(None),
+        };
+
+        let id_element_name = xml::req_string(&line_node, "idElementName")?;
+        let groups_tag = line_node
+            .children()
+            .find(|n| n.has_tag_name("groups") && n.attribute("type") == Some("CompressedVector"))
+            .invalid_err("Cannot find 'groups' tag inside 'groupingByLine'")?;
+        let file_offset = groups_tag
+            .attribute("fileOffset")
+            .invalid_err("Cannot find 'fileOffset' attribute in 'groups' tag")?
+            .parse::<u64>()
+            .invalid_err("Cannot parse 'fileOffset' attribute value as u64")?;
+        let group_count = groups_tag
+            .attribute("recordCount")
+            .invalid_err("Cannot find 'recordCount' attribute in 'groups' tag")?
+            .parse::<u64>()
+            .invalid_err("Cannot parse 'recordCount' attribute value as u64")?;
+        let prototype_tag = groups_tag
+            .children()
+            .find(|n| n.has_tag_name("prototype") && n.attribute("type") == Some("Structure"))
+            .invalid_err("Cannot find 'prototype' child in 'groups' tag")?;
+
+        let mut prototype = Vec::new();
+        parse_prototype(&prototype_tag, &[], &mut prototype)?;
+        for required in ["startPointIndex", "pointCount"] {
+            if !prototype
+                .iter()
+                .any(|r| r.parents.is_empty() && r.name == required)
+            {
+                Error::invalid(format!(
+                    "Cannot find '{required}' in prototype of line groups"
+                ))?
+            }
+        }
+
+        Ok(Some(Self {
+            id_element_name,
+            group_count,
+            file_offset,
+            prototype,
+        }))
+    }
+
+    pub(crate) fn xml_string(&self) -> String {
+        let mut xml = String::new();
+        xml += "<pointGroupingSchemes type=\"Structure\">\n";
+        xml += "<groupingByLine type=\"Structure\">\n";
+        xml += &xml::gen_string("idElementName", &self.id_element_name);
+        xml += &format!(
+            "<groups type=\"CompressedVector\" fileOffset=\"{}\" recordCount=\"{}\">\n",
+            self.file_offset, self.group_count
+        );
+        xml += "<prototype type=\"Structure\">\n";
+
+        // Restore nested structures from the parents of the flattened records
+        let mut open: Vec<&str> = Vec::new();
+        for record in &self.prototype {
+            let parents = &record.parents;
+            let common = open
+                .iter()
+                .zip(parents)
+                .take_while(|(a, b)| **a == b.as_str())
+                .count();
+            while open.len() > common {
+                if let Some(name) = open.pop() {
+                    xml += &format!("</{name}>\n");
+                }
+            }
+            for parent in &parents[common..] {
+                xml += &format!("<{parent} type=\"Structure\">\n");
+                open.push(parent);
+            }
+            xml += &record.data_type.xml_string(&record.name);
+        }
+        while let Some(name) = open.pop() {
+            xml += &format!("</{name}>\n");
+        }
+
+        xml += "</prototype>\n";
+        xml += "</groups>\n";
+        xml += "</groupingByLine>\n";
+        xml += "</pointGroupingSchemes>\n";
+        xml
+    }
+}
+
+/// Nested structures in the group prototype are flattened into a list of records.
+fn parse_prototype(node: &Node, parents: &[&str], prototype: &mut Vec<GroupRecord>) -> Result<()> {
+    for n in node.children() {
+        if !n.is_element() {
+            continue;
+        }
+        let name = n.tag_name().name();
+        if n.attribute("type") == Some("Structure") {
+            parse_prototype(&n, &[parents, &[name]].concat(), prototype)?;
+        } else {
+            let data_type = RecordDataType::from_node(&n)?;
+            prototype.push(GroupRecord::new(parents, name, data_type));
+        }
+    }
+    Ok(())
+}
+
+/// A group of points that belong to the same line.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct LineGroup {
+    /// Value of the identifying point attribute shared by all points of the line.
+    pub id: i64,
+    /// Index of the first point of the line in the point cloud.
+    pub start_point_index: u64,
+    /// Number of points in the line.
+    pub point_count: u64,
+    /// Optional Cartesian bounds of all points in the line.
+    pub cartesian_bounds: Option<CartesianBounds>,
+    /// Optional spherical bounds of all points in the line.
+    pub spherical_bounds: Option<SphericalBounds>,
+}
+
+impl LineGroup {
+    pub(crate) fn from_values(prototype: &[GroupRecord], values: &RawValues) -> Result<Self> {
+        let mut group = Self::default();
+        for (record, value) in prototype.iter().zip(values) {
+            let dt = &record.data_type;
+            let name = record.name.as_str();
+            match record.parents.as_slice() {
+                [] => match name {
+                    "idElementValue" => group.id = value.to_i64(dt)?,
+                    "startPointIndex" => group.start_point_index = value.to_i64(dt)? as u64,
+                    "pointCount" => group.point_count = value.to_i64(dt)? as u64,
+                    _ => {}
+                },
+                [parent] if parent == "cartesianBounds" => {
+                    let bounds = group.cartesian_bounds.get_or_insert_with(Default::default);
+                    let value = Some(value.to_f64(dt)?);
+                    match name {
+                        "xMinimum" => bounds.x_min = value,
+                        "xMaximum" => bounds.x_max = value,
+                        "yMinimum" => bounds.y_min = value,
+                        "yMaximum" => bounds.y_max = value,
+                        "zMinimum" => bounds.z_min = value,
+                        "zMaximum" => bounds.z_max = value,
+                        _ => {}
+                    }
+                }
+                [parent] if parent == "sphericalBounds" => {
+                    let bounds = group.spherical_bounds.get_or_insert_with(Default::default);
+                    let value = Some(value.to_f64(dt)?);
+                    match name {
+                        "rangeMinimum" => bounds.range_min = value,
+                        "rangeMaximum" => bounds.range_max = value,
+                        "elevationMinimum" => bounds.elevation_min = value,
+                        "elevationMaximum" => bounds.elevation_max = value,
+                        "azimuthStart" => bounds.azimuth_start = value,
+                        "azimuthEnd" => bounds.azimuth_end = value,
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(group)
+    }
+}
+
+/// Collects line groups while points are added to a point cloud.
+/// The points of each line are expected to be stored contiguously.
+pub(crate) struct LineGroupCollector {
+    id_element_name: String,
+    id_index: usize,
+    cartesian_indices: Option<[usize; 3]>,
+    cartesian_invalid_index: Option<usize>,
+    groups: Vec<LineGroup>,
+    finished: HashSet<i64>,
+}
+
+impl LineGroupCollector {
+    pub fn new(prototype: &[Record], id_name: RecordName) -> Result<Self> {
+        if id_name != RecordName::RowIndex && id_name != RecordName::ColumnIndex {
+            Error::invalid("Line groups can only be identified by row or column index")?
+        }
+        let find = |name: RecordName| prototype.iter().position(|r| r.name == name);
+        let id_index = find(id_name.clone()).invalid_err(format!(
+            "Cannot find '{}' in prototype for line grouping",
+            id_name.tag_name()
+        ))?;
+        let cartesian_indices = match (
+            find(RecordName::CartesianX),
+            find(RecordName::CartesianY),
+            find(RecordName::CartesianZ),
+        ) {
+            (Some(x), Some(y), Some(z)) => Some([x, y, z]),
+            _ => None,
+        };
+        Ok(Self {
+            id_element_name: id_name.tag_name().to_owned(),
+            id_index,
+            cartesian_indices,
+            cartesian_invalid_index: find(RecordName::CartesianInvalidState),
+            groups: Vec::new(),
+            finished: HashSet::new(),
+        })
+    }
+
+    /// Adds the point with the given index to its line group.
+    pub fn add_point(
+        &mut self,
+        index: u64,
+        values: &RawValues,
+        prototype: &[Record],
+    ) -> Result<()> {
+        let id = values[self.id_index].to_i64(&prototype[self.id_index].data_type)?;
+
+        let current = self.groups.last().map(|g| g.id);
+        if current != Some(id) {
+            if self.finished.contains(&id) {
+                Error::invalid(format!(
+                    "Points of line group {id} are not stored contiguously"
+                ))?
+            }
+            if let Some(current) = current {
+                self.finished.insert(current);
+            }
+            self.groups.push(LineGroup {
+                id,
+                start_point_index: index,
+                ..Default::default()
+            });
+        }
+        let group = self
+            .groups
+            .last_mut()
+            .internal_err("Cannot find current line group")?;
+        group.point_count += 1;
+
+        // Only valid Cartesian coordinates are considered for the bounds
+        if let Some([x, y, z]) = self.cartesian_indices {
+            let valid = if let Some(index) = self.cartesian_invalid_index {
+                values[index].to_i64(&prototype[index].data_type)? == 0
+            } else {
+                true
+            };
+            if valid {
+                let x = values[x].to_f64(&prototype[x].data_type)?;
+                let y = values[y].to_f64(&prototype[y].data_type)?;
+                let z = values[z].to_f64(&prototype[z].data_type)?;
+                let bounds = group.cartesian_bounds.get_or_insert(CartesianBounds {
+                    x_min: Some(x),
+                    x_max: Some(x),
+                    y_min: Some(y),
+                    y_max: Some(y),
+                    z_min: Some(z),
+                    z_max: Some(z),
+                });
+                bounds.x_min = bounds.x_min.map(|v| v.min(x));
+                bounds.x_max = bounds.x_max.map(|v| v.max(x));
+                bounds.y_min = bounds.y_min.map(|v| v.min(y));
+                bounds.y_max = bounds.y_max.map(|v| v.max(y));
+                bounds.z_min = bounds.z_min.map(|v| v.min(z));
+                bounds.z_max = bounds.z_max.map(|v| v.max(z));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the name of the identifying point attribute,
+    /// the prototype for the line groups and the values for each line group.
+    pub fn finish(self) -> (String, Vec<GroupRecord>, Vec<RawValues>) {
+        let int_record = |name: &str, min: i64, max: i64| {
+            GroupRecord::new(&[], name, RecordDataType::Integer { min, max })
+        };
+        let id_min = self.groups.iter().map(|g| g.id).min().unwrap_or(0);
+        let id_max = self.groups.iter().map(|g| g.id).max().unwrap_or(0);
+        let start_max = self.groups.last().map(|g| g.start_point_index).unwrap_or(0);
+        let count_max = self.groups.iter().map(|g| g.point_count).max().unwrap_or(0);
+        let mut prototype = vec![
+            int_record("idElementValue", id_min, id_max),
+            int_record("startPointIndex", 0, start_max as i64),
+            int_record("pointCount", 0, count_max as i64),
+        ];
+
+        // Bounds are only stored if they are available for all groups
+        let bounds = self.groups.iter().all(|g| g.cartesian_bounds.is_some());
+        if bounds {
+            for name in [
+                "xMinimum", "xMaximum", "yMinimum", "yMaximum", "zMinimum", "zMaximum",
+            ] {
+                prototype.push(GroupRecord::new(
+                    &["cartesianBounds"],
+                    name,
+                    RecordDataType::F64,
+                ));
+            }
+        }
+
+        let mut values = Vec::with_capacity(self.groups.len());
+        for group in self.groups {
+            let mut group_values = vec![
+                RecordValue::Integer(group.id),
+                RecordValue::Integer(group.start_point_index as i64),
+                RecordValue::Integer(group.point_count as i64),
+            ];
+            if let (true, Some(b)) = (bounds, group.cartesian_bounds) {
+                for value in [b.x_min, b.x_max, b.y_min, b.y_max, b.z_min, b.z_max] {
+                    group_values.push(RecordValue::Double(value.unwrap_or_default()));
+                }
+            }
+            values.push(group_values);
+        }
+
+        (self.id_element_name, prototype, values)
+    }
+}
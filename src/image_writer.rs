@@ -1,9 +1,12 @@
+use crate::extension::set_extension_data;
 use crate::paged_writer::PagedWriter;
 use crate::Blob;
 use crate::CylindricalImage;
 use crate::CylindricalImageProperties;
 use crate::DateTime;
 use crate::Error;
+use crate::Extension;
+use crate::ExtensionData;
 use crate::Image;
 use crate::ImageBlob;
 use crate::ImageFormat;
@@ -16,12 +19,14 @@ use crate::SphericalImageProperties;
 use crate::Transform;
 use crate::VisualReferenceImage;
 use crate::VisualReferenceImageProperties;
+use crate::XmlElement;
 use std::io::{Read, Seek, Write};
 
 /// Defines a new image and writes it into an E57 file.
 pub struct ImageWriter<'a, T: Read + Write + Seek> {
     writer: &'a mut PagedWriter<T>,
     images: &'a mut Vec<Image>,
+    extensions: &'a mut Vec<Extension>,
     image: Image,
 }
 
@@ -29,11 +34,13 @@ impl<'a, T: Read + Write + Seek> ImageWriter<'a, T> {
     pub(crate) fn new(
         writer: &'a mut PagedWriter<T>,
         images: &'a mut Vec<Image>,
+        extensions: &'a mut Vec<Extension>,
         guid: &str,
     ) -> Result<Self> {
         Ok(Self {
             writer,
             images,
+            extensions,
             image: Image {
                 guid: Some(guid.to_owned()),
                 visual_reference: None,
@@ -46,6 +53,7 @@ impl<'a, T: Read + Write + Seek> ImageWriter<'a, T> {
                 sensor_vendor: None,
                 sensor_model: None,
                 sensor_serial: None,
+                additional_xml: Vec::new(),
             },
         })
     }
@@ -99,6 +107,20 @@ impl<'a, T: Read + Write + Seek> ImageWriter<'a, T> {
         self.image.sensor_serial = Some(value.to_owned());
     }
 
+    /// Attaches typed extension data to the image.
+    /// Replaces any previously set data of the same type.
+    /// The extension of the data is registered automatically, if required.
+    pub fn set_extension_data<D: ExtensionData>(&mut self, data: &D) -> Result<()> {
+        set_extension_data(&mut self.image.additional_xml, self.extensions, data)
+    }
+
+    /// Sets additional XML elements for the image descriptor, for example from an existing file.
+    /// Replaces all previously set elements, including typed extension data.
+    /// Namespaces used by the elements must be registered as extensions.
+    pub fn set_additional_xml(&mut self, elements: Vec<XmlElement>) {
+        self.image.additional_xml = elements;
+    }
+
     /// Adds an optional visual reference image, also known as preview image.
     /// See also `VisualReferenceImageProperties` struct for more details.
     /// The optional PNG mask image can be used to indicate valid/invalid
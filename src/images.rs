@@ -1,5 +1,6 @@
+use crate::extension::get_extension_data;
 use crate::xml;
-use crate::{Blob, DateTime, Error, Result, Transform};
+use crate::{Blob, DateTime, Error, ExtensionData, Result, Transform, XmlElement};
 use roxmltree::{Document, Node};
 use std::f64::consts::PI;
 
@@ -30,9 +31,66 @@ pub struct Image {
     pub sensor_model: Option<String>,
     /// The serial number of the sensor used to capture the image.
     pub sensor_serial: Option<String>,
+    /// Additional XML elements of the image descriptor that are not defined by the E57 standard,
+    /// for example extension data or vendor specific metadata.
+    /// They are written back unchanged, which means elements referring to binary sections
+    /// of the original file, like blobs, will no longer be valid in a new file.
+    /// See [`Image::extension_data`] for typed access.
+    pub additional_xml: Vec<XmlElement>,
 }
 
+/// Tag names of all image descriptor elements defined by the E57 standard.
+const KNOWN_TAGS: &[&str] = &[
+    "guid",
+    "visualReferenceRepresentation",
+    "pinholeRepresentation",
+    "sphericalRepresentation",
+    "cylindricalRepresentation",
+    "pose",
+    "associatedData3DGuid",
+    "name",
+    "description",
+    "acquisitionDateTime",
+    "sensorVendor",
+    "sensorModel",
+    "sensorSerialNumber",
+];
+
 impl Image {
+    /// Parses the typed extension data from the additional XML elements of the image.
+    /// Returns None if the image has no element for this extension data.
+    pub fn extension_data<D: ExtensionData>(&self) -> Result<Option<D>> {
+        get_extension_data(&self.additional_xml)
+    }
+
+    /// Projects a point in file-level coordinates to pixel coordinates of the image.
+    /// Uses the pose of the image to transform the point into the local image coordinate system.
+    /// Returns None for images without projection model and for points that are not visible in the image.
+    /// See [`Projection::project`] for details.
+    pub fn project(&self, point: [f64; 3]) -> Option<[f64; 2]> {
+        let projection = self.projection.as_ref()?;
+        let local = match &self.transform {
+            Some(transform) => transform.inverse().apply_point(point),
+            None => point,
+        };
+        projection.project(local)
+    }
+
+    /// Calculates the ray through the given pixel coordinates in file-level coordinates.
+    /// Returns the origin of the ray and the normalized direction vector.
+    /// Returns None for images without projection model.
+    /// See [`Projection::ray`] for details.
+    pub fn ray(&self, pixel: [f64; 2]) -> Option<([f64; 3], [f64; 3])> {
+        let (origin, direction) = self.projection.as_ref()?.ray(pixel);
+        Some(match &self.transform {
+            Some(transform) => (
+                transform.apply_point(origin),
+                transform.apply_direction(direction),
+            ),
+            None => (origin, direction),
+        })
+    }
+
     fn from_node(node: &Node) -> Result<Self> {
         let guid = xml::opt_string(node, "guid")?;
         let pointcloud_guid = xml::opt_string(node, "associatedData3DGuid")?;
@@ -66,6 +124,7 @@ impl Image {
             sensor_serial,
             projection,
             visual_reference,
+            additional_xml: XmlElement::unknown_children(node, KNOWN_TAGS),
         })
     }
 
@@ -118,6 +177,9 @@ impl Image {
         if let Some(serial) = &self.sensor_serial {
             xml += &xml::gen_string("sensorSerialNumber", &serial);
         }
+        for element in &self.additional_xml {
+            xml += &element.xml_string();
+        }
         xml += "</vectorChild>\n";
         xml
     }
@@ -160,6 +222,32 @@ impl Projection {
         Ok(None)
     }
 
+    /// Projects a point in the local coordinate system of the image to pixel coordinates.
+    ///
+    /// Pixel coordinates are continuous values with the origin in the upper left corner of the image.
+    /// The X coordinate increases to the right along the columns and the Y coordinate increases downwards along the rows.
+    /// The center of the upper left pixel has the coordinates (0.5, 0.5).
+    /// Returns None if the point cannot be seen in the image,
+    /// for example because it is behind the camera or outside of the image area.
+    pub fn project(&self, point: [f64; 3]) -> Option<[f64; 2]> {
+        match self {
+            Projection::Pinhole(p) => p.properties.project(point),
+            Projection::Spherical(s) => s.properties.project(point),
+            Projection::Cylindrical(c) => c.properties.project(point),
+        }
+    }
+
+    /// Calculates the ray through the given pixel coordinates in the local coordinate system of the image.
+    /// Returns the origin of the ray and the normalized direction vector.
+    /// See [`Projection::project`] for the definition of the pixel coordinates.
+    pub fn ray(&self, pixel: [f64; 2]) -> ([f64; 3], [f64; 3]) {
+        match self {
+            Projection::Pinhole(p) => p.properties.ray(pixel),
+            Projection::Spherical(s) => s.properties.ray(pixel),
+            Projection::Cylindrical(c) => c.properties.ray(pixel),
+        }
+    }
+
     pub(crate) fn xml_string(&self) -> String {
         match self {
             Projection::Pinhole(p) => p.xml_string(),
@@ -286,6 +374,33 @@ pub struct PinholeImageProperties {
     pub principal_y: f64,
 }
 
+impl PinholeImageProperties {
+    /// Projects a point in the local coordinate system of the image to pixel coordinates.
+    ///
+    /// The camera looks along the positive Z axis,
+    /// the X axis points to the right and the Y axis points down in the image.
+    /// The principal point is where the Z axis intersects the image plane.
+    /// Returns None for points behind the camera or outside of the image.
+    /// See also [`Projection::project`].
+    pub fn project(&self, point: [f64; 3]) -> Option<[f64; 2]> {
+        let [x, y, z] = point;
+        if z <= 0.0 {
+            return None;
+        }
+        let column = self.principal_x + self.focal_length * x / z / self.pixel_width;
+        let row = self.principal_y + self.focal_length * y / z / self.pixel_height;
+        inside_image([column, row], self.width, self.height)
+    }
+
+    /// Calculates the ray through the given pixel coordinates in the local coordinate system of the image.
+    /// Returns the center of projection as origin of the ray and the normalized direction vector.
+    pub fn ray(&self, pixel: [f64; 2]) -> ([f64; 3], [f64; 3]) {
+        let x = (pixel[0] - self.principal_x) * self.pixel_width;
+        let y = (pixel[1] - self.principal_y) * self.pixel_height;
+        ([0.0; 3], normalize([x, y, self.focal_length]))
+    }
+}
+
 /// Describes an image with a pinhole camera projection model.
 #[derive(Clone, Debug)]
 #[non_exhaustive]
@@ -352,6 +467,42 @@ pub struct SphericalImageProperties {
     pub pixel_height: f64,
 }
 
+impl SphericalImageProperties {
+    /// Projects a point in the local coordinate system of the image to pixel coordinates.
+    ///
+    /// The center of the image corresponds to the direction of the X axis.
+    /// The azimuth angle decreases from left to right and the elevation angle decreases from top to bottom,
+    /// as seen from the center of projection looking at the inside of the sphere.
+    /// Returns None for points outside of the image and for the origin.
+    /// See also [`Projection::project`].
+    pub fn project(&self, point: [f64; 3]) -> Option<[f64; 2]> {
+        let [x, y, z] = point;
+        let horizontal = (x * x + y * y).sqrt();
+        if horizontal == 0.0 && z == 0.0 {
+            return None;
+        }
+        let azimuth = y.atan2(x);
+        let elevation = z.atan2(horizontal);
+        let column = self.width as f64 / 2.0 - azimuth / self.pixel_width;
+        let row = self.height as f64 / 2.0 - elevation / self.pixel_height;
+        inside_image([column, row], self.width, self.height)
+    }
+
+    /// Calculates the ray through the given pixel coordinates in the local coordinate system of the image.
+    /// Returns the center of projection as origin of the ray and the normalized direction vector.
+    pub fn ray(&self, pixel: [f64; 2]) -> ([f64; 3], [f64; 3]) {
+        let azimuth = (self.width as f64 / 2.0 - pixel[0]) * self.pixel_width;
+        let elevation = (self.height as f64 / 2.0 - pixel[1]) * self.pixel_height;
+        let cos_ele = elevation.cos();
+        let direction = [
+            cos_ele * azimuth.cos(),
+            cos_ele * azimuth.sin(),
+            elevation.sin(),
+        ];
+        ([0.0; 3], direction)
+    }
+}
+
 /// Describes an image with a spherical projection model.
 #[derive(Clone, Debug)]
 #[non_exhaustive]
@@ -416,10 +567,46 @@ pub struct CylindricalImageProperties {
     pub principal_y: f64,
     /// The width of a pixel in radians.
     pub pixel_width: f64,
-    /// The height of a pixel in radians.
+    /// The height of a pixel in meters.
     pub pixel_height: f64,
 }
 
+impl CylindricalImageProperties {
+    /// Projects a point in the local coordinate system of the image to pixel coordinates.
+    ///
+    /// The Z axis is the axis of the cylinder and the center column of the image corresponds to the direction of the X axis.
+    /// The azimuth angle decreases from left to right and the Z coordinate decreases from top to bottom,
+    /// as seen from the center of projection looking at the inside of the cylinder.
+    /// The principal point is the row where the XY plane intersects the cylinder.
+    /// Returns None for points outside of the image and for points on the Z axis.
+    /// See also [`Projection::project`].
+    pub fn project(&self, point: [f64; 3]) -> Option<[f64; 2]> {
+        let [x, y, z] = point;
+        let horizontal = (x * x + y * y).sqrt();
+        if horizontal == 0.0 {
+            return None;
+        }
+        let azimuth = y.atan2(x);
+        let height = z * self.radius / horizontal;
+        let column = self.width as f64 / 2.0 - azimuth / self.pixel_width;
+        let row = self.principal_y - height / self.pixel_height;
+        inside_image([column, row], self.width, self.height)
+    }
+
+    /// Calculates the ray through the given pixel coordinates in the local coordinate system of the image.
+    /// Returns the center of projection as origin of the ray and the normalized direction vector.
+    pub fn ray(&self, pixel: [f64; 2]) -> ([f64; 3], [f64; 3]) {
+        let azimuth = (self.width as f64 / 2.0 - pixel[0]) * self.pixel_width;
+        let height = (self.principal_y - pixel[1]) * self.pixel_height;
+        let direction = [
+            self.radius * azimuth.cos(),
+            self.radius * azimuth.sin(),
+            height,
+        ];
+        ([0.0; 3], normalize(direction))
+    }
+}
+
 /// Describes an image with a cylindrical projection model.
 #[derive(Clone, Debug)]
 #[non_exhaustive]
@@ -462,7 +649,7 @@ impl CylindricalImage {
         }
         xml += &xml::gen_int("imageWidth", self.properties.width);
         xml += &xml::gen_int("imageHeight", self.properties.height);
-        xml += &xml::gen_float("readius", self.properties.radius);
+        xml += &xml::gen_float("radius", self.properties.radius);
         xml += &xml::gen_float("principalPointY", self.properties.principal_y);
         xml += &xml::gen_float("pixelWidth", self.properties.pixel_width);
         xml += &xml::gen_float("pixelHeight", self.properties.pixel_height);
@@ -470,3 +657,156 @@ impl CylindricalImage {
         xml
     }
 }
+
+/// Returns the pixel coordinates if they are inside of an image with the given size.
+fn inside_image(pixel: [f64; 2], width: u32, height: u32) -> Option<[f64; 2]> {
+    let inside =
+        pixel[0] >= 0.0 && pixel[0] < width as f64 && pixel[1] >= 0.0 && pixel[1] < height as f64;
+    if inside {
+        Some(pixel)
+    } else {
+        None
+    }
+}
+
+fn normalize(v: [f64; 3]) -> [f64; 3] {
+    let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if len == 0.0 {
+        v
+    } else {
+        [v[0] / len, v[1] / len, v[2] / len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Quaternion, Translation};
+
+    fn assert_close(a: &[f64], b: &[f64]) {
+        for (a, b) in a.iter().zip(b) {
+            assert!((a - b).abs() < 1e-9, "{a} != {b}");
+        }
+    }
+
+    fn check_roundtrip(projection: &Projection, point: [f64; 3]) {
+        let pixel = projection.project(point).unwrap();
+        let (origin, direction) = projection.ray(pixel);
+        let len = (point[0] * point[0] + point[1] * point[1] + point[2] * point[2]).sqrt();
+        let expected = [point[0] / len, point[1] / len, point[2] / len];
+        assert_close(&origin, &[0.0; 3]);
+        assert_close(&direction, &expected);
+    }
+
+    fn blob() -> ImageBlob {
+        ImageBlob {
+            data: Blob::new(0, 0),
+            format: ImageFormat::Png,
+        }
+    }
+
+    #[test]
+    fn pinhole_projection() {
+        let projection = Projection::Pinhole(PinholeImage {
+            blob: blob(),
+            mask: None,
+            properties: PinholeImageProperties {
+                width: 200,
+                height: 100,
+                focal_length: 0.01,
+                pixel_width: 0.0001,
+                pixel_height: 0.0001,
+                principal_x: 100.0,
+                principal_y: 50.0,
+            },
+        });
+        assert_eq!(projection.project([0.0, 0.0, 5.0]), Some([100.0, 50.0]));
+        assert_eq!(projection.project([1.0, 0.5, 10.0]), Some([110.0, 55.0]));
+        assert_eq!(projection.project([0.0, 0.0, -5.0]), None);
+        assert_eq!(projection.project([10.0, 0.0, 1.0]), None);
+        check_roundtrip(&projection, [0.3, -0.2, 2.0]);
+    }
+
+    #[test]
+    fn spherical_projection() {
+        let projection = Projection::Spherical(SphericalImage {
+            blob: blob(),
+            mask: None,
+            properties: SphericalImageProperties {
+                width: 360,
+                height: 180,
+                pixel_width: PI / 180.0,
+                pixel_height: PI / 180.0,
+            },
+        });
+        let pixel = projection.project([1.0, 0.0, 0.0]).unwrap();
+        assert_close(&pixel, &[180.0, 90.0]);
+        let pixel = projection.project([0.0, 1.0, 1.0]).unwrap();
+        assert_close(&pixel, &[90.0, 45.0]);
+        assert_eq!(projection.project([0.0, 0.0, 0.0]), None);
+        check_roundtrip(&projection, [-1.0, -2.0, 0.5]);
+    }
+
+    #[test]
+    fn cylindrical_projection() {
+        let projection = Projection::Cylindrical(CylindricalImage {
+            blob: blob(),
+            mask: None,
+            properties: CylindricalImageProperties {
+                width: 360,
+                height: 100,
+                radius: 1.0,
+                principal_y: 50.0,
+                pixel_width: PI / 180.0,
+                pixel_height: 0.01,
+            },
+        });
+        let pixel = projection.project([2.0, 0.0, 0.5]).unwrap();
+        assert_close(&pixel, &[180.0, 25.0]);
+        assert_eq!(projection.project([0.0, 0.0, 1.0]), None);
+        assert_eq!(projection.project([1.0, 0.0, 1.0]), None);
+        check_roundtrip(&projection, [0.5, 2.0, -0.3]);
+    }
+
+    #[test]
+    fn image_pose() {
+        let projection = Projection::Spherical(SphericalImage {
+            blob: blob(),
+            mask: None,
+            properties: SphericalImageProperties {
+                width: 360,
+                height: 180,
+                pixel_width: PI / 180.0,
+                pixel_height: PI / 180.0,
+            },
+        });
+        let image = Image {
+            guid: None,
+            visual_reference: None,
+            projection: Some(projection),
+            transform: Some(Transform {
+                rotation: Quaternion::from_axis_angle([0.0, 0.0, 1.0], PI / 2.0),
+                translation: Translation {
+                    x: 10.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+            }),
+            pointcloud_guid: None,
+            name: None,
+            description: None,
+            acquisition: None,
+            sensor_vendor: None,
+            sensor_model: None,
+            sensor_serial: None,
+            additional_xml: Vec::new(),
+        };
+
+        // The local X axis points along the file-level Y axis
+        let pixel = image.project([10.0, 5.0, 0.0]).unwrap();
+        assert_close(&pixel, &[180.0, 90.0]);
+        let (origin, direction) = image.ray(pixel).unwrap();
+        assert_close(&origin, &[10.0, 0.0, 0.0]);
+        assert_close(&direction, &[0.0, 1.0, 0.0]);
+    }
+}
@@ -4,6 +4,8 @@
 //!
 //! ### Extensions
 //! This library supports reading and writing [extensions](Extension) as defined in the E57 specification.
+//! Surface normals as defined by the libE57 extension are supported directly, see [`Normal`].
+//! Custom metadata of extensions can be attached to files, point clouds and images, see [`ExtensionData`].
 //!
 //! ### Optional Crate Features
 //! There is an optional feature called `crc32c`.
@@ -11,6 +13,12 @@
 //! This crate provides a faster CRC implementation with HW support.
 //! It can speed up reading and writing of larger E57 files.
 //! The feature is **disabled by default** to keep the number dependencies as small as possible.
+//!
+//! There is another optional feature called `image-decoding`.
+//! If enabled, it will include external crates to decode PNG and JPEG images as additional dependencies.
+//! This allows loading images directly from E57 files for colorizing point clouds,
+//! See [`Colorizer`] and [`RgbImage`] for more details.
+//! The feature is also **disabled by default**.
 
 #![forbid(unsafe_code)]
 #![deny(
@@ -28,29 +36,43 @@ mod blob;
 mod bounds;
 mod bs_read;
 mod bs_write;
+mod colorize;
+mod columns;
 mod cv_section;
 mod date_time;
+mod e57_editor;
 mod e57_reader;
 mod e57_writer;
 mod error;
 mod extension;
+mod filter;
+mod grid;
+mod grouping;
 mod header;
 mod image_writer;
 mod images;
 mod limits;
+mod line_group_reader;
 mod packet;
+mod packet_index;
 mod paged_reader;
 mod paged_writer;
+mod pc_reader_parallel;
 mod pc_reader_raw;
 mod pc_reader_simple;
 mod pc_writer;
 mod point;
 mod pointcloud;
 mod queue_reader;
+mod reader_options;
 mod record;
 mod root;
+mod salvage;
+mod source;
+mod subsample;
 mod transform;
 mod xml;
+mod xml_element;
 
 #[cfg(not(feature = "crc32c"))]
 mod crc32;
@@ -60,12 +82,23 @@ pub use self::blob::Blob;
 pub use self::bounds::CartesianBounds;
 pub use self::bounds::IndexBounds;
 pub use self::bounds::SphericalBounds;
+pub use self::colorize::Colorizer;
+pub use self::colorize::RgbImage;
+pub use self::columns::Column;
+pub use self::columns::ColumnBuffers;
 pub use self::date_time::DateTime;
+pub use self::e57_editor::E57Editor;
 pub use self::e57_reader::E57Reader;
 pub use self::e57_writer::E57Writer;
 pub use self::error::Error;
 pub use self::error::Result;
 pub use self::extension::Extension;
+pub use self::extension::ExtensionData;
+pub use self::filter::CustomFilter;
+pub use self::filter::PointFilter;
+pub use self::grid::StructuredGrid;
+pub use self::grouping::LineGroup;
+pub use self::grouping::LineGrouping;
 pub use self::header::Header;
 pub use self::image_writer::ImageWriter;
 pub use self::images::CylindricalImage;
@@ -82,21 +115,33 @@ pub use self::images::VisualReferenceImage;
 pub use self::images::VisualReferenceImageProperties;
 pub use self::limits::ColorLimits;
 pub use self::limits::IntensityLimits;
+pub use self::line_group_reader::LineGroupReader;
+pub use self::pc_reader_parallel::PointCloudReaderParallel;
 pub use self::pc_reader_raw::PointCloudReaderRaw;
 pub use self::pc_reader_simple::PointCloudReaderSimple;
 pub use self::pc_writer::PointCloudWriter;
 pub use self::point::CartesianCoordinate;
 pub use self::point::Color;
+pub use self::point::Normal;
 pub use self::point::Point;
 pub use self::point::SphericalCoordinate;
 pub use self::pointcloud::PointCloud;
+pub use self::reader_options::CrcValidation;
+pub use self::reader_options::E57ReaderOptions;
 pub use self::record::Record;
 pub use self::record::RecordDataType;
 pub use self::record::RecordName;
 pub use self::record::RecordValue;
+pub use self::salvage::E57Salvager;
+pub use self::salvage::SalvageReport;
+pub use self::salvage::SalvagedPointCloud;
+pub use self::source::ReadAt;
+pub use self::source::SourceReader;
+pub use self::subsample::Subsampling;
 pub use self::transform::Quaternion;
 pub use self::transform::Transform;
 pub use self::transform::Translation;
+pub use self::xml_element::XmlElement;
 
 /// Storage container for low level point data.
 pub type RawValues = Vec<RecordValue>;
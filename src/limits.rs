@@ -1,4 +1,5 @@
 use crate::error::Converter;
+use crate::xml;
 use crate::Error;
 use crate::RecordDataType;
 use crate::RecordValue;
@@ -170,5 +171,6 @@ fn record_value_to_xml(tag_name: &str, value: &RecordValue) -> String {
             format!("<{tag_name} type=\"Float\" precision=\"single\">{value}</{tag_name}>\n")
         }
         RecordValue::Double(value) => format!("<{tag_name} type=\"Float\">{value}</{tag_name}>\n"),
+        RecordValue::String(value) => xml::gen_string(tag_name, value),
     }
 }
@@ -0,0 +1,65 @@
+use crate::grouping::{GroupRecord, LineGroup, LineGrouping};
+use crate::paged_reader::PagedReader;
+use crate::pc_reader_raw::PointCloudReaderRaw;
+use crate::{Error, PointCloud, Result};
+use std::io::{Read, Seek};
+
+/// Iterate over all line groups of a point cloud.
+pub struct LineGroupReader<'a, T: Read + Seek> {
+    raw_reader: PointCloudReaderRaw<'a, T>,
+    prototype: Vec<GroupRecord>,
+    failed: bool,
+}
+
+impl<'a, T: Read + Seek> LineGroupReader<'a, T> {
+    pub(crate) fn new(grouping: &LineGrouping, reader: &'a mut PagedReader<T>) -> Result<Self> {
+        // The groups are stored as compressed vector just like the points,
+        // so we can reuse the raw point reader with a fitting prototype.
+        let groups = PointCloud {
+            file_offset: grouping.file_offset,
+            records: grouping.group_count,
+            prototype: grouping
+                .prototype
+                .iter()
+                .map(GroupRecord::to_record)
+                .collect(),
+            ..Default::default()
+        };
+        Ok(Self {
+            raw_reader: PointCloudReaderRaw::new(&groups, reader)?,
+            prototype: grouping.prototype.clone(),
+            failed: false,
+        })
+    }
+}
+
+impl<T: Read + Seek> Iterator for LineGroupReader<'_, T> {
+    /// Each iterator item is a result for an extracted line group.
+    type Item = Result<LineGroup>;
+
+    /// Returns the next available line group or None if the end was reached.
+    /// Line groups cannot be recovered from corrupt pages,
+    /// the iteration ends with an error when such a page is found in recovery mode.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let values = self.raw_reader.next();
+        if !self.raw_reader.corrupt_pages().is_empty() {
+            self.failed = true;
+            return Some(Error::invalid(format!(
+                "Line groups contain pages with invalid checksums: {:?}",
+                self.raw_reader.corrupt_pages()
+            )));
+        }
+        let values = match values? {
+            Ok(values) => values,
+            Err(err) => return Some(Err(err)),
+        };
+        Some(LineGroup::from_values(&self.prototype, &values))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.raw_reader.size_hint()
+    }
+}
@@ -30,11 +30,18 @@ impl PacketHeader {
 
 pub struct IndexPacketHeader {
     pub packet_length: u64,
+    pub entry_count: u16,
+    pub index_level: u8,
 }
 
 impl IndexPacketHeader {
     pub const ID: u8 = 0;
 
+    pub const SIZE: usize = 16;
+
+    /// Maximum number of entries that fit into a single index packet.
+    pub const MAX_ENTRIES: usize = 2048;
+
     pub fn read(reader: &mut dyn Read) -> Result<Self> {
         let mut buffer = [0_u8; 15];
         reader
@@ -54,17 +61,72 @@ impl IndexPacketHeader {
         // Parse values
         let packet_length =
             u16::from_le_bytes(buffer[1..3].try_into().internal_err(WRONG_OFFSET)?) as u64 + 1;
+        let entry_count = u16::from_le_bytes(buffer[3..5].try_into().internal_err(WRONG_OFFSET)?);
+        let index_level = buffer[5];
 
-        // Currently unused header fields
-        let _entry_count = u16::from_le_bytes(buffer[3..5].try_into().internal_err(WRONG_OFFSET)?);
-        let _index_level = buffer[5];
-
-        // Validate length
+        // Validate values
         if !packet_length.is_multiple_of(4) {
             Error::invalid("Index packet length is not aligned and a multiple of four")?
         }
+        if entry_count == 0 || entry_count as usize > Self::MAX_ENTRIES {
+            Error::invalid(format!(
+                "Index packet entry count {entry_count} is not in the range 1..={}",
+                Self::MAX_ENTRIES
+            ))?
+        }
+        if (Self::SIZE + entry_count as usize * IndexPacketEntry::SIZE) as u64 > packet_length {
+            Error::invalid("Index packet entries do not fit into the index packet")?
+        }
 
-        Ok(Self { packet_length })
+        Ok(Self {
+            packet_length,
+            entry_count,
+            index_level,
+        })
+    }
+
+    pub fn write(&self, writer: &mut dyn Write) -> Result<()> {
+        let mut buffer = [0_u8; Self::SIZE];
+        buffer[0] = Self::ID;
+        let length = (self.packet_length - 1) as u16;
+        buffer[2..4].copy_from_slice(&length.to_le_bytes());
+        buffer[4..6].copy_from_slice(&self.entry_count.to_le_bytes());
+        buffer[6] = self.index_level;
+        writer
+            .write_all(&buffer)
+            .write_err("Failed to write index packet header")
+    }
+}
+
+/// Single entry of an index packet, pointing to a data packet or to another index packet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexPacketEntry {
+    /// Number of the first record contained in the referenced packet (or its children).
+    pub record: u64,
+    /// Physical offset of the referenced packet.
+    pub offset: u64,
+}
+
+impl IndexPacketEntry {
+    pub const SIZE: usize = 16;
+
+    pub fn read(reader: &mut dyn Read) -> Result<Self> {
+        let mut buffer = [0_u8; Self::SIZE];
+        reader
+            .read_exact(&mut buffer)
+            .read_err("Failed to read index packet entry")?;
+        let record = u64::from_le_bytes(buffer[0..8].try_into().internal_err(WRONG_OFFSET)?);
+        let offset = u64::from_le_bytes(buffer[8..16].try_into().internal_err(WRONG_OFFSET)?);
+        Ok(Self { record, offset })
+    }
+
+    pub fn write(&self, writer: &mut dyn Write) -> Result<()> {
+        let mut buffer = [0_u8; Self::SIZE];
+        buffer[0..8].copy_from_slice(&self.record.to_le_bytes());
+        buffer[8..16].copy_from_slice(&self.offset.to_le_bytes());
+        writer
+            .write_all(&buffer)
+            .write_err("Failed to write index packet entry")
     }
 }
 
@@ -128,6 +190,8 @@ pub struct IgnoredPacketHeader {
 impl IgnoredPacketHeader {
     pub const ID: u8 = 2;
 
+    pub const SIZE: usize = 4;
+
     pub fn read(reader: &mut dyn Read) -> Result<Self> {
         // Read Ignored Packet
         let mut buffer = [0_u8; 3];
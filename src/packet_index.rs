@@ -0,0 +1,643 @@
+use crate::cv_section::CompressedVectorSectionHeader;
+use crate::error::Converter;
+use crate::packet::{
+    DataPacketHeader, IgnoredPacketHeader, IndexPacketEntry, IndexPacketHeader, PacketHeader,
+};
+use crate::paged_reader::PagedReader;
+use crate::paged_writer::PagedWriter;
+use crate::{Error, PointCloud, RecordDataType, Result};
+use std::io::{Read, Seek, Write};
+
+/// Location of a single data packet inside a compressed vector section.
+#[derive(Clone, Debug)]
+pub struct DataPacketLocation {
+    /// Physical offset of the data packet header.
+    pub offset: u64,
+    /// Number of the first record in this data packet, if known.
+    pub record: Option<u64>,
+    /// Number of bits of each byte stream that are located before this data packet.
+    pub stream_bits: Vec<u64>,
+}
+
+/// Lookup table with the locations of the data packets of a compressed vector section.
+/// Used to find the data packet to start with when seeking to a specific point.
+pub struct PacketIndex {
+    bit_sizes: Vec<u64>,
+    has_strings: bool,
+    packets: Vec<DataPacketLocation>,
+    from_index: bool,
+}
+
+impl PacketIndex {
+    /// Creates the lookup table for the data packets of the section.
+    /// If the section has index packets, the table is built from their entries
+    /// without reading any data packet headers.
+    /// Use [`PacketIndex::verify`] to check a location before seeking to it.
+    /// Without index packets all packet headers of the section are scanned.
+    pub fn new<T: Read + Seek>(
+        reader: &mut PagedReader<T>,
+        pc: &PointCloud,
+        section_header: &CompressedVectorSectionHeader,
+    ) -> Result<Self> {
+        if section_header.index_offset == 0 {
+            return Self::scan(reader, pc, section_header);
+        }
+        let mut index = Self::empty(pc);
+        index.packets = Self::index_entries(reader, section_header)?
+            .into_iter()
+            .map(|entry| DataPacketLocation {
+                offset: entry.offset,
+                record: Some(entry.record),
+                stream_bits: index.record_bits(entry.record),
+            })
+            .collect();
+        index.from_index = true;
+        Ok(index)
+    }
+
+    /// Creates the lookup table by scanning all packet headers of the section.
+    /// Record numbers of the data packets are taken from the index packets, if available.
+    /// The index packets are ignored if any entry does not match the actual packet boundaries,
+    /// for example when packets do not start at a record boundary of every byte stream.
+    pub fn scan<T: Read + Seek>(
+        reader: &mut PagedReader<T>,
+        pc: &PointCloud,
+        section_header: &CompressedVectorSectionHeader,
+    ) -> Result<Self> {
+        let mut index = Self::empty(pc);
+        index.packets = Self::from_packet_headers(reader, pc, section_header, &index.bit_sizes)?;
+        if section_header.index_offset != 0 {
+            let entries = Self::index_entries(reader, section_header)?;
+            Self::apply_index_entries(&mut index.packets, &entries, &index.bit_sizes);
+        }
+        Ok(index)
+    }
+
+    /// Checks a location of this table before seeking to it.
+    /// For tables created from index packets, the data packet header at the location is read.
+    /// Its byte stream sizes and the sizes of the data packet in front of it must match
+    /// the record numbers of the index entries, if the entries point to directly adjacent packets.
+    /// Returns false if the index packets cannot be used and the packet headers need to be scanned instead.
+    /// Locations of scanned tables are always valid.
+    pub fn verify<T: Read + Seek>(
+        &self,
+        reader: &mut PagedReader<T>,
+        location: &DataPacketLocation,
+    ) -> Result<bool> {
+        if !self.from_index {
+            return Ok(true);
+        }
+        let Some(pos) = self
+            .packets
+            .iter()
+            .position(|p| p.offset == location.offset)
+        else {
+            return Ok(false);
+        };
+        if self.read_stream_sizes(reader, location.offset)?.is_none() {
+            return Ok(false);
+        }
+        let previous = pos
+            .checked_sub(1)
+            .map_or(Ok(true), |p| self.verify_sizes(reader, p))?;
+        Ok(previous && self.verify_sizes(reader, pos)?)
+    }
+
+    /// Returns the last data packet that starts before    /// Returns the last data packet that starts before or exactly at the given record.
+    /// Returns None if there is no such packet.
+    /// String values have no fixed bit size, so for prototypes with strings
+    /// only packets with a known first record number are considered.
+    pub fn find(&self, record: u64) -> Option<&DataPacketLocation> {
+        if self.has_strings {
+            return self
+                .packets
+                .iter()
+                .rev()
+                .find(|p| p.record.is_some_and(|r| r <= record));
+        }
+        let before = |p: &DataPacketLocation| {
+            p.stream_bits
+                .iter()
+                .zip(&self.bit_sizes)
+                .all(|(bits, size)| *size == 0 || *bits <= record * size)
+        };
+        let pos = self.packets.partition_point(before);
+        if pos > 0 {
+            self.packets.get(pos - 1)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the first known record number of a data packet that is not below the given record.
+    pub fn next_known_record(&self, record: u64) -> Option<u64> {
+        self.packets
+            .iter()
+            .filter_map(|p| p.record)
+            .find(|r| *r >= record)
+    }
+
+    /// Returns true if records can only be located at data packets with a known record number.
+    pub fn has_strings(&self) -> bool {
+        self.has_strings
+    }
+
+    /// Returns the number of bits of each byte stream that are located before the given record.
+    pub fn record_bits(&self, record: u64) -> Vec<u64> {
+        self.bit_sizes.iter().map(|size| record * size).collect()
+    }
+
+    fn empty(pc: &PointCloud) -> Self {
+        let bit_sizes = pc
+            .prototype
+            .iter()
+            .map(|r| r.data_type.bit_size() as u64)
+            .collect();
+        let has_strings = pc
+            .prototype
+            .iter()
+            .any(|r| matches!(r.data_type, RecordDataType::String));
+        Self {
+            bit_sizes,
+            has_strings,
+            packets: Vec::new(),
+            from_index: false,
+        }
+    }
+
+    /// Checks that the byte streams of the data packet at the given position contain exactly
+    /// the records up to the next entry, if the next entry points to the directly following packet.
+    fn verify_sizes<T: Read + Seek>(
+        &self,
+        reader: &mut PagedReader<T>,
+        pos: usize,
+    ) -> Result<bool> {
+        let (Some(packet), Some(next)) = (self.packets.get(pos), self.packets.get(pos + 1)) else {
+            return Ok(true);
+        };
+        let (Some(start), Some(end)) = (packet.record, next.record) else {
+            return Ok(true);
+        };
+        let Some((sizes, next_offset)) = self.read_stream_sizes(reader, packet.offset)? else {
+            return Ok(false);
+        };
+        if next_offset != next.offset {
+            // Entries that skip packets cannot be checked without reading the skipped packets
+            return Ok(true);
+        }
+        let records = end.saturating_sub(start);
+        Ok(sizes
+            .iter()
+            .zip(&self.bit_sizes)
+            .all(|(bits, size)| *size == 0 || *bits == records * size))
+    }
+
+    /// Reads the byte stream sizes in bits of the data packet at the given physical offset.
+    /// Also returns the physical offset behind the packet.
+    /// Returns None if there is no data packet for this point cloud at the offset.
+    fn read_stream_sizes<T: Read + Seek>(
+        &self,
+        reader: &mut PagedReader<T>,
+        offset: u64,
+    ) -> Result<Option<(Vec<u64>, u64)>> {
+        reader
+            .seek_physical(offset)
+            .read_err("Cannot seek to packet header")?;
+        let PacketHeader::Data(header) = PacketHeader::read(reader)? else {
+            return Ok(None);
+        };
+        if header.bytestream_count as usize != self.bit_sizes.len() {
+            return Ok(None);
+        }
+        let mut sizes = Vec::with_capacity(self.bit_sizes.len());
+        for _ in 0..self.bit_sizes.len() {
+            let mut buf = [0_u8; 2];
+            reader
+                .read_exact(&mut buf)
+                .read_err("Failed to read data packet buffer sizes")?;
+            sizes.push(u16::from_le_bytes(buf) as u64 * 8);
+        }
+        let header_size = DataPacketHeader::SIZE + self.bit_sizes.len() * 2;
+        let remaining = header
+            .packet_length
+            .checked_sub(header_size as u64)
+            .invalid_err("Packet length is smaller than the packet header")?;
+        reader
+            .skip(remaining)
+            .read_err("Failed to skip packet content")?;
+        Ok(Some((sizes, reader.physical_position())))
+    }
+
+    /// Reads all level 0 entries from the tree of index packets.
+    fn index_entries<T: Read + Seek>(
+        reader: &mut PagedReader<T>,
+        section_header: &CompressedVectorSectionHeader,
+    ) -> Result<Vec<IndexPacketEntry>> {
+        let mut entries = Vec::new();
+        Self::read_index_packet(reader, section_header.index_offset, None, &mut entries)?;
+        if entries.windows(2).any(|w| w[1].record < w[0].record) {
+            Error::invalid("Record numbers of index packet entries are not ascending")?
+        }
+        Ok(entries)
+    }
+
+    /// Sets the record numbers of the scanned data packets from the index entries.
+    /// Nothing is changed if any entry does not point to a data packet that starts
+    /// exactly at the bit offset of its record in every byte stream with a fixed bit size.
+    /// Returns true if the index entries were applied.
+    fn apply_index_entries(
+        packets: &mut [DataPacketLocation],
+        entries: &[IndexPacketEntry],
+        bit_sizes: &[u64],
+    ) -> bool {
+        let mut matches = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let Ok(pos) = packets.binary_search_by_key(&entry.offset, |p| p.offset) else {
+                return false;
+            };
+            let aligned = packets[pos]
+                .stream_bits
+                .iter()
+                .zip(bit_sizes)
+                .all(|(bits, size)| *size == 0 || *bits == entry.record * size);
+            if !aligned {
+                return false;
+            }
+            matches.push((pos, entry.record));
+        }
+        for (pos, record) in matches {
+            packets[pos].record = Some(record);
+        }
+        true
+    }
+
+    fn read_index_packet<T: Read + Seek>(
+        reader: &mut PagedReader<T>,
+        offset: u64,
+        parent_level: Option<u8>,
+        entries: &mut Vec<IndexPacketEntry>,
+    ) -> Result<()> {
+        reader
+            .seek_physical(offset)
+            .read_err("Cannot seek to index packet")?;
+        let header = match PacketHeader::read(reader)? {
+            PacketHeader::Index(header) => header,
+            _ => Error::invalid(format!("Expected index packet at offset {offset}"))?,
+        };
+        if let Some(parent_level) = parent_level {
+            // Child levels must be strictly decreasing, this also prevents endless loops
+            if header.index_level >= parent_level {
+                Error::invalid("Index level of child index packet is not below parent level")?
+            }
+        }
+
+        let mut children = Vec::with_capacity(header.entry_count as usize);
+        for _ in 0..header.entry_count {
+            children.push(IndexPacketEntry::read(reader)?);
+        }
+
+        if header.index_level == 0 {
+            entries.extend(children);
+        } else {
+            for child in children {
+                Self::read_index_packet(reader, child.offset, Some(header.index_level), entries)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn from_packet_headers<T: Read + Seek>(
+        reader: &mut PagedReader<T>,
+        pc: &PointCloud,
+        section_header: &CompressedVectorSectionHeader,
+        bit_sizes: &[u64],
+    ) -> Result<Vec<DataPacketLocation>> {
+        let section_start = reader
+            .seek_physical(pc.file_offset)
+            .read_err("Cannot seek to compressed vector header")?;
+        let section_end = section_start + section_header.section_length;
+
+        let mut packets = Vec::new();
+        let mut stream_bits = vec![0_u64; bit_sizes.len()];
+        let mut offset = section_header.data_offset;
+        loop {
+            // Stop as soon as the packets contain all records
+            let complete = bit_sizes
+                .iter()
+                .zip(&stream_bits)
+                .all(|(size, bits)| *bits >= size * pc.records);
+            if complete {
+                break;
+            }
+
+            let logical_offset = reader
+                .seek_physical(offset)
+                .read_err("Cannot seek to packet header")?;
+            if logical_offset >= section_end {
+                break;
+            }
+
+            let remaining = match PacketHeader::read(reader)? {
+                PacketHeader::Data(header) => {
+                    if header.bytestream_count as usize != bit_sizes.len() {
+                        Error::invalid("Bytestream count does not match prototype size")?
+                    }
+                    packets.push(DataPacketLocation {
+                        offset,
+                        record: if packets.is_empty() { Some(0) } else { None },
+                        stream_bits: stream_bits.clone(),
+                    });
+                    for bits in &mut stream_bits {
+                        let mut buf = [0_u8; 2];
+                        reader
+                            .read_exact(&mut buf)
+                            .read_err("Failed to read data packet buffer sizes")?;
+                        *bits += u16::from_le_bytes(buf) as u64 * 8;
+                    }
+                    let header_size = DataPacketHeader::SIZE + bit_sizes.len() * 2;
+                    header.packet_length.checked_sub(header_size as u64)
+                }
+                PacketHeader::Index(header) => header
+                    .packet_length
+                    .checked_sub(IndexPacketHeader::SIZE as u64),
+                PacketHeader::Ignored(header) => header
+                    .packet_length
+                    .checked_sub(IgnoredPacketHeader::SIZE as u64),
+            };
+            let remaining =
+                remaining.invalid_err("Packet length is smaller than the packet header")?;
+            reader
+                .skip(remaining)
+                .read_err("Failed to skip packet content")?;
+            offset = reader.physical_position();
+        }
+        Ok(packets)
+    }
+}
+
+/// Sums up the byte stream sizes in bits of all data packets from the given physical offset
+/// to the end of the compressed vector section.
+/// Returns None if there is no data packet at the offset or if any packet in this range
+/// has an invalid header or touches a corrupt page.
+pub fn stream_bits_behind<T: Read + Seek>(
+    reader: &mut PagedReader<T>,
+    pc: &PointCloud,
+    section_header: &CompressedVectorSectionHeader,
+    offset: u64,
+) -> Option<Vec<u64>> {
+    let section_start = reader.seek_physical(pc.file_offset).ok()?;
+    let section_end = section_start + section_header.section_length;
+    let corrupt_reads = reader.corrupt_reads();
+
+    let mut stream_bits = vec![0_u64; pc.prototype.len()];
+    let mut offset = offset;
+    let mut first = true;
+    loop {
+        let logical_offset = reader.seek_physical(offset).ok()?;
+        if logical_offset >= section_end {
+            break;
+        }
+        let (packet_length, header_size) = match PacketHeader::read(reader).ok()? {
+            PacketHeader::Data(header) => {
+                if header.bytestream_count as usize != stream_bits.len() {
+                    return None;
+                }
+                let mut used = DataPacketHeader::SIZE as u64 + stream_bits.len() as u64 * 2;
+                for bits in &mut stream_bits {
+                    let mut buf = [0_u8; 2];
+                    reader.read_exact(&mut buf).ok()?;
+                    let size = u16::from_le_bytes(buf) as u64;
+                    *bits += size * 8;
+                    used += size;
+                }
+                if used > header.packet_length {
+                    return None;
+                }
+                let header_size = DataPacketHeader::SIZE + stream_bits.len() * 2;
+                (header.packet_length, header_size as u64)
+            }
+            _ if first => return None,
+            PacketHeader::Index(header) => (header.packet_length, IndexPacketHeader::SIZE as u64),
+            PacketHeader::Ignored(header) => {
+                (header.packet_length, IgnoredPacketHeader::SIZE as u64)
+            }
+        };
+        if reader.corrupt_reads() != corrupt_reads || logical_offset + packet_length > section_end {
+            return None;
+        }
+        reader.skip(packet_length.checked_sub(header_size)?).ok()?;
+        offset = reader.physical_position();
+        first = false;
+    }
+    if reader.corrupt_reads() != corrupt_reads {
+        return None;
+    }
+    Some(stream_bits)
+}
+
+/// Writes a tree of index packets for the given level 0 entries pointing to data packets.
+/// Each index packet contains up to the given maximum number of entries.
+/// Additional levels are added until a single root index packet remains.
+/// Returns the physical offset of the root index packet and the combined length of all index packets.
+pub fn write_index_packets<T: Read + Write + Seek>(
+    writer: &mut PagedWriter<T>,
+    mut entries: Vec<IndexPacketEntry>,
+    max_entries: usize,
+) -> Result<(u64, u64)> {
+    if entries.is_empty() {
+        Error::internal("Cannot write index packets without entries")?
+    }
+    let max_entries = max_entries.clamp(1, IndexPacketHeader::MAX_ENTRIES);
+
+    let mut length = 0;
+    let mut level = 0_u8;
+    loop {
+        let mut parents = Vec::with_capacity(entries.len().div_ceil(max_entries));
+        for chunk in entries.chunks(max_entries) {
+            let offset = writer.physical_position()?;
+            let packet_length = IndexPacketHeader::SIZE + chunk.len() * IndexPacketEntry::SIZE;
+            IndexPacketHeader {
+                packet_length: packet_length as u64,
+                entry_count: chunk.len() as u16,
+                index_level: level,
+            }
+            .write(writer)?;
+            for entry in chunk {
+                entry.write(writer)?;
+            }
+            length += packet_length as u64;
+            parents.push(IndexPacketEntry {
+                record: chunk[0].record,
+                offset,
+            });
+        }
+
+        if parents.len() == 1 {
+            return Ok((parents[0].offset, length));
+        }
+        entries = parents;
+        level = level
+            .checked_add(1)
+            .internal_err("Too many levels of index packets")?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{E57Reader, E57Writer, RawValues, Record, RecordName, RecordValue};
+    use std::fs::{remove_file, File, OpenOptions};
+    use std::io::Cursor;
+    use std::path::Path;
+
+    #[test]
+    fn write_read_index_tree() {
+        let entries: Vec<IndexPacketEntry> = (0..7)
+            .map(|i| IndexPacketEntry {
+                record: i * 100,
+                offset: 1000 + i * 48,
+            })
+            .collect();
+
+        // Write two levels of index packets with only three entries per packet
+        let mut data = Vec::new();
+        let (root_offset, length) = {
+            let mut writer = PagedWriter::new(Cursor::new(&mut data)).unwrap();
+            writer.write_all(&[0_u8; 8]).unwrap();
+            write_index_packets(&mut writer, entries.clone(), 3).unwrap()
+        };
+        assert_eq!(root_offset, 8 + 3 * 16 + 7 * 16);
+        assert_eq!(length, 4 * 16 + 10 * 16);
+
+        let mut reader = PagedReader::new(Cursor::new(data), 1024).unwrap();
+        let mut section_header = CompressedVectorSectionHeader::default();
+        section_header.data_offset = 1000;
+        section_header.index_offset = root_offset;
+        let read = PacketIndex::index_entries(&mut reader, &section_header).unwrap();
+        assert_eq!(read.len(), entries.len());
+        for (read, entry) in read.iter().zip(&entries) {
+            assert_eq!(read.offset, entry.offset);
+            assert_eq!(read.record, entry.record);
+        }
+    }
+
+    #[test]
+    fn index_packets_replace_header_scan() {
+        let path = Path::new("index_packets_replace_header_scan.e57");
+        let count = 100_000;
+        let value = |i: u64| RecordValue::Single(i as f32);
+        {
+            let mut writer = E57Writer::from_file(path, "file_guid").unwrap();
+            let prototype = vec![
+                Record::CARTESIAN_X_F32,
+                Record::CARTESIAN_Y_F32,
+                Record::CARTESIAN_Z_F32,
+            ];
+            let mut pc_writer = writer.add_pointcloud("pc_guid", prototype).unwrap();
+            pc_writer.write_index_packets(true);
+            for i in 0..count {
+                pc_writer.add_point(vec![value(i); 3]).unwrap();
+            }
+            pc_writer.finalize().unwrap();
+            writer.finalize().unwrap();
+        }
+
+        // Break the page with the header of a data packet in the middle of the point cloud
+        let pc = E57Reader::from_file(path).unwrap().pointclouds().remove(0);
+        let mut data = std::fs::read(path).unwrap();
+        let mut reader = PagedReader::new(Cursor::new(&data), 1024).unwrap();
+        reader.seek_physical(pc.file_offset).unwrap();
+        let section_header = CompressedVectorSectionHeader::read(&mut reader).unwrap();
+        let scanned = PacketIndex::scan(&mut reader, &pc, &section_header).unwrap();
+        let broken = scanned.find(count / 2).unwrap().offset;
+        data[broken as usize] ^= 0xFF;
+
+        // Scanning reads all packet headers and fails, the index packets avoid this
+        let mut reader = PagedReader::new(Cursor::new(&data), 1024).unwrap();
+        assert!(PacketIndex::scan(&mut reader, &pc, &section_header).is_err());
+        let index = PacketIndex::new(&mut reader, &pc, &section_header).unwrap();
+        let location = index.find(count - 1).unwrap();
+        assert!(location.offset > broken);
+        assert!(index.verify(&mut reader, location).unwrap());
+
+        // Seeking behind the broken page works without touching it
+        let mut e57 = E57Reader::new(Cursor::new(&data)).unwrap();
+        let mut iter = e57.pointcloud_raw(&pc).unwrap();
+        iter.seek_to_point(count - 10).unwrap();
+        let points: Vec<RawValues> = iter.collect::<Result<_>>().unwrap();
+        assert_eq!(points.len(), 10);
+        assert_eq!(points[0], vec![value(count - 10); 3]);
+
+        remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn misaligned_index_entries() {
+        let path = Path::new("misaligned_index_entries.e57");
+        let data_type = RecordDataType::Integer { min: 0, max: 1000 };
+        let prototype: Vec<Record> = [
+            RecordName::CartesianX,
+            RecordName::CartesianY,
+            RecordName::CartesianZ,
+        ]
+        .into_iter()
+        .map(|name| Record {
+            name,
+            data_type: data_type.clone(),
+        })
+        .collect();
+        let count = 100_000;
+        let value = |i: u64| RecordValue::Integer((i % 1001) as i64);
+        {
+            let mut writer = E57Writer::from_file(path, "file_guid").unwrap();
+            let mut pc_writer = writer.add_pointcloud("pc_guid", prototype).unwrap();
+            for i in 0..count {
+                pc_writer.add_point(vec![value(i); 3]).unwrap();
+            }
+            pc_writer.finalize().unwrap();
+            writer.finalize().unwrap();
+        }
+
+        // Find the actual packets, the 10 bit records are cut at packet boundaries
+        let pc = E57Reader::from_file(path).unwrap().pointclouds().remove(0);
+        let mut reader = PagedReader::new(File::open(path).unwrap(), 1024).unwrap();
+        reader.seek_physical(pc.file_offset).unwrap();
+        let mut section_header = CompressedVectorSectionHeader::read(&mut reader).unwrap();
+        let packets =
+            PacketIndex::from_packet_headers(&mut reader, &pc, &section_header, &[10; 3]).unwrap();
+        assert!(packets.iter().any(|p| p.stream_bits[0] % 10 != 0));
+
+        // Add index packets that assume all packets start at a record boundary
+        let entries = packets
+            .iter()
+            .map(|p| IndexPacketEntry {
+                record: p.stream_bits[0] / 10,
+                offset: p.offset,
+            })
+            .collect();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .unwrap();
+        let mut writer = PagedWriter::from_existing(file).unwrap();
+        let end = writer.physical_size().unwrap();
+        writer.physical_seek(end).unwrap();
+        let (index_offset, _) = write_index_packets(&mut writer, entries, 16).unwrap();
+        section_header.index_offset = index_offset;
+        writer.physical_seek(pc.file_offset).unwrap();
+        section_header.write(&mut writer).unwrap();
+        drop(writer);
+
+        // Seeking must ignore the misaligned index entries
+        let mut e57 = E57Reader::from_file(path).unwrap();
+        let mut reader = e57.pointcloud_raw(&pc).unwrap();
+        for index in [count - 1, 12345, 1, 54321, 0] {
+            reader.seek_to_point(index).unwrap();
+            let values = reader.next().unwrap().unwrap();
+            assert_eq!(values, vec![value(index); 3]);
+        }
+
+        remove_file(path).unwrap();
+    }
+}
@@ -1,7 +1,9 @@
 use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom};
+use std::ops::{Deref, DerefMut};
 
 #[cfg(not(feature = "crc32c"))]
 use crate::crc32::Crc32;
+use crate::CrcValidation;
 
 const CHECKSUM_SIZE: u64 = 4;
 const ALIGNMENT_SIZE: u64 = 4;
@@ -17,6 +19,11 @@ pub struct PagedReader<T: Read + Seek> {
     page_num: Option<u64>,
     page_buffer: Vec<u8>,
     phy_pos: Option<u64>,
+    crc_validation: CrcValidation,
+    recovery: bool,
+    corrupt_pages: Vec<u64>,
+    corrupt_reads: u64,
+    page_corrupt: bool,
 
     #[cfg(not(feature = "crc32c"))]
     crc: Crc32,
@@ -24,11 +31,26 @@ pub struct PagedReader<T: Read + Seek> {
 
 impl<T: Read + Seek> PagedReader<T> {
     /// Create and initialize a paged reader that abstracts the E57 CRC scheme
-    pub fn new(mut reader: T, page_size: u64) -> Result<Self> {
-        if page_size > MAX_PAGE_SIZE {
+    pub fn new(reader: T, page_size: u64) -> Result<Self> {
+        Self::with_max_page_size(reader, page_size, MAX_PAGE_SIZE)
+    }
+
+    /// Same as `new()` but with a custom limit for the page size.
+    pub fn with_max_page_size(reader: T, page_size: u64, max_page_size: u64) -> Result<Self> {
+        Self::create(reader, page_size, max_page_size, false)
+    }
+
+    /// Same as `new()` but ignores an incomplete last page instead of returning an error.
+    /// Allows reading the intact pages of truncated files.
+    pub fn new_truncated(reader: T, page_size: u64) -> Result<Self> {
+        Self::create(reader, page_size, MAX_PAGE_SIZE, true)
+    }
+
+    fn create(mut reader: T, page_size: u64, max_page_size: u64, truncated: bool) -> Result<Self> {
+        if page_size > max_page_size {
             Err(Error::new(
                 ErrorKind::InvalidInput,
-                format!("Page size {page_size} is bigger than the allowed maximum page size of {MAX_PAGE_SIZE} bytes"),
+                format!("Page size {page_size} is bigger than the allowed maximum page size of {max_page_size} bytes"),
             ))?;
         }
         if page_size <= CHECKSUM_SIZE {
@@ -40,7 +62,10 @@ impl<T: Read + Seek> PagedReader<T> {
             Err(Error::new(ErrorKind::InvalidInput, msg))?;
         }
 
-        let phy_file_size = reader.seek(SeekFrom::End(0))?;
+        let mut phy_file_size = reader.seek(SeekFrom::End(0))?;
+        if truncated {
+            phy_file_size -= phy_file_size % page_size;
+        }
         if phy_file_size == 0 {
             let msg = "File is empty (0 bytes)";
             Err(Error::new(ErrorKind::InvalidData, msg))?;
@@ -62,6 +87,11 @@ impl<T: Read + Seek> PagedReader<T> {
             log_file_size: pages * (page_size - CHECKSUM_SIZE),
             page_buffer: vec![0_u8; page_size as usize],
             phy_pos: None,
+            crc_validation: CrcValidation::Always,
+            recovery: false,
+            corrupt_pages: Vec::new(),
+            corrupt_reads: 0,
+            page_corrupt: false,
             page_num: None,
             offset: 0,
 
@@ -85,6 +115,73 @@ impl<T: Read + Seek> PagedReader<T> {
         Ok(self.offset)
     }
 
+    /// Seeking to a logical offset without any checksum bytes relative to the start of the file.
+    pub fn seek_logical(&mut self, offset: u64) -> Result<()> {
+        if offset >= self.log_file_size {
+            Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Logical offset {offset} is behind end of file"),
+            ))?;
+        }
+        self.offset = offset;
+        Ok(())
+    }
+
+    /// Defines which pages are checked for CRC errors when reading them.
+    pub fn set_crc_validation(&mut self, value: CrcValidation) {
+        self.crc_validation = value;
+    }
+
+    /// If enabled, pages with invalid checksums are not reported as error.
+    /// Instead their content is replaced with zeros and the page is added to the list of corrupt pages.
+    pub fn set_recovery(&mut self, enable: bool) {
+        self.recovery = enable;
+    }
+
+    /// Returns the numbers of all pages with invalid checksums that were read in recovery mode.
+    pub fn corrupt_pages(&self) -> &[u64] {
+        &self.corrupt_pages
+    }
+
+    /// Returns the number of read operations that returned zeros instead of corrupt page data.
+    /// Can be used to detect if some read operation touched corrupt data.
+    pub fn corrupt_reads(&self) -> u64 {
+        self.corrupt_reads
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn inner(&self) -> &T {
+        &self.reader
+    }
+
+    /// Returns the physical size of a page including its checksum.
+    pub fn page_size(&self) -> u64 {
+        self.page_size
+    }
+
+    /// Returns the logical size of the file without any checksum bytes.
+    pub fn logical_size(&self) -> u64 {
+        self.log_file_size
+    }
+
+    /// Returns the physical file offset that corresponds to the current logical offset.
+    pub fn physical_position(&self) -> u64 {
+        let pages_before = self.offset / (self.page_size - CHECKSUM_SIZE);
+        self.offset + pages_before * CHECKSUM_SIZE
+    }
+
+    /// Skips the given number of logical bytes without reading them.
+    pub fn skip(&mut self, bytes: u64) -> Result<()> {
+        if self.offset + bytes > self.log_file_size {
+            Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Tried to skip behind end of the file",
+            ))?
+        }
+        self.offset += bytes;
+        Ok(())
+    }
+
     fn read_page(&mut self, page: u64) -> Result<()> {
         if page >= self.pages {
             let max = self.pages - 1;
@@ -113,6 +210,12 @@ impl<T: Read + Seek> PagedReader<T> {
             })?;
         self.phy_pos = Some(offset + self.page_size);
 
+        self.page_corrupt = false;
+        if !self.crc_validation.check_page(page) {
+            self.page_num = Some(page);
+            return Ok(());
+        }
+
         let data_size = self.page_size - CHECKSUM_SIZE;
         let expected_checksum = &self.page_buffer[data_size as usize..];
 
@@ -131,11 +234,20 @@ impl<T: Read + Seek> PagedReader<T> {
         let calculated_checksum = crc.to_be_bytes();
 
         if expected_checksum != calculated_checksum {
-            self.page_num = None;
-            return Err(Error::new(
-                ErrorKind::InvalidData,
-                format!("Detected invalid checksum (expected: {expected_checksum:?}, actual: {calculated_checksum:?}) for page {page}")
-            ));
+            if !self.recovery {
+                self.page_num = None;
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Detected invalid checksum (expected: {expected_checksum:?}, actual: {calculated_checksum:?}) for page {page}")
+                ));
+            }
+
+            // Replace corrupt data with zeros and remember the page
+            self.page_buffer.fill(0);
+            if !self.corrupt_pages.contains(&page) {
+                self.corrupt_pages.push(page);
+            }
+            self.page_corrupt = true;
         }
 
         self.page_num = Some(page);
@@ -159,6 +271,44 @@ impl<T: Read + Seek> PagedReader<T> {
     }
 }
 
+/// Paged reader that is either borrowed from an E57 reader or owned by a point cloud reader.
+pub enum PagedReaderRef<'a, T: Read + Seek> {
+    Borrowed(&'a mut PagedReader<T>),
+    Owned(Box<PagedReader<T>>),
+}
+
+impl<'a, T: Read + Seek> From<&'a mut PagedReader<T>> for PagedReaderRef<'a, T> {
+    fn from(reader: &'a mut PagedReader<T>) -> Self {
+        Self::Borrowed(reader)
+    }
+}
+
+impl<T: Read + Seek> From<PagedReader<T>> for PagedReaderRef<'_, T> {
+    fn from(reader: PagedReader<T>) -> Self {
+        Self::Owned(Box::new(reader))
+    }
+}
+
+impl<T: Read + Seek> Deref for PagedReaderRef<'_, T> {
+    type Target = PagedReader<T>;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Borrowed(reader) => reader,
+            Self::Owned(reader) => reader,
+        }
+    }
+}
+
+impl<T: Read + Seek> DerefMut for PagedReaderRef<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Self::Borrowed(reader) => reader,
+            Self::Owned(reader) => reader,
+        }
+    }
+}
+
 impl<T: Read + Seek> Read for PagedReader<T> {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
         let page = self.offset / (self.page_size - CHECKSUM_SIZE);
@@ -168,6 +318,9 @@ impl<T: Read + Seek> Read for PagedReader<T> {
         if self.page_num != Some(page) {
             self.read_page(page)?;
         }
+        if self.page_corrupt {
+            self.corrupt_reads += 1;
+        }
         let page_offset = self.offset % (self.page_size - CHECKSUM_SIZE);
         let page_readable = self.page_size - CHECKSUM_SIZE - page_offset;
         let read_size = usize::min(buf.len(), page_readable as usize);
@@ -230,6 +383,22 @@ mod tests {
         assert_eq!(buf.len(), 0);
     }
 
+    #[test]
+    fn corrupt_page_recovery() {
+        let mut data = std::fs::read("testdata/bunnyDouble.e57").unwrap();
+        data[2 * PAGE_SIZE as usize - 1] ^= 0xFF;
+        let mut reader = PagedReader::new(Cursor::new(data), PAGE_SIZE).unwrap();
+        reader.set_recovery(true);
+
+        let mut buf = vec![0_u8; 3 * (PAGE_SIZE - CHECKSUM_SIZE) as usize];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(reader.corrupt_pages(), &[1]);
+        assert_eq!(reader.corrupt_reads(), 1);
+        let page =
+            &buf[(PAGE_SIZE - CHECKSUM_SIZE) as usize..2 * (PAGE_SIZE - CHECKSUM_SIZE) as usize];
+        assert!(page.iter().all(|b| *b == 0));
+    }
+
     #[test]
     fn physical_seek() {
         let file = File::open("testdata/bunnyDouble.e57").unwrap();
@@ -246,6 +415,27 @@ mod tests {
         assert_eq!(String::from_utf8(buffer.to_vec()).unwrap(), "<?xml");
     }
 
+    #[test]
+    fn physical_position() {
+        let file = File::open("testdata/bunnyDouble.e57").unwrap();
+        let mut reader = PagedReader::new(file, PAGE_SIZE).unwrap();
+        assert_eq!(reader.physical_position(), 0);
+
+        let xml_physical_offset = 740736;
+        reader.seek_physical(xml_physical_offset).unwrap();
+        assert_eq!(reader.physical_position(), xml_physical_offset);
+
+        // Skipping to the end of a page continues on the next page
+        reader.seek_physical(1000).unwrap();
+        reader.skip(20).unwrap();
+        assert_eq!(reader.physical_position(), PAGE_SIZE);
+        reader.skip(10).unwrap();
+        assert_eq!(reader.physical_position(), PAGE_SIZE + 10);
+
+        // Skipping behind the end of the file is not allowed
+        assert!(reader.skip(u32::MAX as u64).is_err());
+    }
+
     #[test]
     fn read_end() {
         let file = File::open("testdata/bunnyDouble.e57").unwrap();
@@ -5,7 +5,7 @@ use std::io::{Read, Seek, SeekFrom, Write};
 #[cfg(not(feature = "crc32c"))]
 use crate::crc32::Crc32;
 
-const PAGE_SIZE: u64 = 1024;
+pub(crate) const PAGE_SIZE: u64 = 1024;
 const CRC_SIZE: u64 = 4;
 const PAGE_PAYLOAD_SIZE: usize = (PAGE_SIZE - CRC_SIZE) as usize;
 
@@ -27,14 +27,32 @@ impl<T: Write + Read + Seek> PagedWriter<T> {
         if end != 0 {
             Error::invalid("Supplied writer is not empty")?
         }
-        Ok(Self {
+        Ok(Self::from_writer(writer))
+    }
+
+    /// Create a paged writer for existing data that must consist of complete pages.
+    /// Use `physical_seek()` to move to the position where writing should start.
+    pub fn from_existing(mut writer: T) -> Result<Self> {
+        let end = writer
+            .seek(SeekFrom::End(0))
+            .read_err("Unable to seek length of writer")?;
+        if end % PAGE_SIZE != 0 {
+            Error::invalid(format!(
+                "Size of supplied writer is not a multiple of the page size {PAGE_SIZE}"
+            ))?
+        }
+        Ok(Self::from_writer(writer))
+    }
+
+    fn from_writer(writer: T) -> Self {
+        Self {
             writer,
             offset: 0,
             page_buffer: [0_u8; PAGE_SIZE as usize],
 
             #[cfg(not(feature = "crc32c"))]
             crc: Crc32::new(),
-        })
+        }
     }
 
     /// Get the current physical offset in the file.
@@ -189,6 +207,7 @@ impl<T: Write + Read + Seek> Drop for PagedWriter<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::paged_reader::PagedReader;
     use std::fs::{remove_file, File, OpenOptions};
     use std::path::Path;
 
@@ -520,4 +539,51 @@ mod tests {
 
         remove_file(path).unwrap();
     }
+
+    #[test]
+    fn existing_pages() {
+        let path = Path::new("existing.bin");
+        let file = open_file(path);
+        let mut writer = PagedWriter::new(file).unwrap();
+        writer.write_all(&[1; PAGE_PAYLOAD_SIZE + 1]).unwrap();
+        drop(writer);
+
+        // Append a third page and update the CRC of the first page
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .unwrap();
+        let mut writer = PagedWriter::from_existing(file).unwrap();
+        writer.physical_seek(2 * PAGE_SIZE).unwrap();
+        writer.write_all(&[3]).unwrap();
+        writer.physical_seek(0).unwrap();
+        writer.write_all(&[2]).unwrap();
+        drop(writer);
+
+        let content = std::fs::read(path).unwrap();
+        assert_eq!(content.len(), 3 * PAGE_SIZE as usize);
+        assert_eq!(content[0], 2);
+        assert_eq!(content[1], 1);
+        assert_eq!(content[PAGE_SIZE as usize], 1);
+        assert_eq!(content[2 * PAGE_SIZE as usize], 3);
+
+        // All checksums must be valid
+        let file = File::open(path).unwrap();
+        let mut reader = PagedReader::new(file, PAGE_SIZE).unwrap();
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+        assert_eq!(data.len(), 3 * PAGE_PAYLOAD_SIZE);
+
+        // Incomplete pages are rejected
+        let file = OpenOptions::new()
+            .append(true)
+            .read(true)
+            .open(path)
+            .unwrap();
+        (&file).write_all(&[0]).unwrap();
+        assert!(PagedWriter::from_existing(file).is_err());
+
+        remove_file(path).unwrap();
+    }
 }
@@ -0,0 +1,303 @@
+use crate::columns::ColumnBuffers;
+use crate::cv_section::CompressedVectorSectionHeader;
+use crate::error::Converter;
+use crate::packet_index::PacketIndex;
+use crate::paged_reader::PagedReader;
+use crate::queue_reader::QueueReader;
+use crate::Error;
+use crate::PointCloud;
+use crate::RawValues;
+use crate::Record;
+use crate::RecordValue;
+use crate::Result;
+use crate::{ReadAt, SourceReader};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{Read, Seek};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+const DEFAULT_CHUNK_SIZE: usize = 100_000;
+
+/// Range of points that is read and decoded by a worker thread.
+struct Job {
+    id: u64,
+    start: u64,
+    points: usize,
+}
+
+/// Decoded values of a chunk with one queue for each prototype record.
+type JobResult = (u64, Result<Vec<VecDeque<RecordValue>>>);
+
+/// Iterate over all raw points of a point cloud while reading and decoding them with multiple threads.
+///
+/// The data packets of the point cloud are located once when creating the reader.
+/// Each worker thread has its own independent reader for the file and reads the
+/// data packets of a contiguous range of points, which are then decoded by the same thread.
+/// The calling thread only reorders the decoded ranges, so the points are returned in their original order.
+/// The items and values are identical to the ones of [`PointCloudReaderRaw`](crate::PointCloudReaderRaw).
+pub struct PointCloudReaderParallel {
+    prototype: Vec<Record>,
+    index: Arc<PacketIndex>,
+    records: u64,
+    read: u64,
+    chunk_size: usize,
+    max_pending: usize,
+    dispatched: u64,
+    next_job: u64,
+    next_result: u64,
+    pending: usize,
+    finished: BTreeMap<u64, Result<Vec<VecDeque<RecordValue>>>>,
+    current: Vec<VecDeque<RecordValue>>,
+    current_len: usize,
+    jobs: Option<Sender<Job>>,
+    results: Receiver<JobResult>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl PointCloudReaderParallel {
+    /// Creates a reader with one worker thread for each of the given worker readers.
+    /// The supplied reader is only used to locate the data packets of the point cloud.
+    pub(crate) fn new<T: Read + Seek, R: ReadAt + Send + Sync + 'static>(
+        pc: &PointCloud,
+        reader: &mut PagedReader<T>,
+        worker_readers: Vec<PagedReader<SourceReader<R>>>,
+    ) -> Result<Self> {
+        if worker_readers.is_empty() {
+            Error::internal("Parallel point cloud reader requires at least one worker thread")?
+        }
+        reader
+            .seek_physical(pc.file_offset)
+            .read_err("Cannot seek to compressed vector header")?;
+        let section_header = CompressedVectorSectionHeader::read(reader)?;
+        let corrupt_reads = reader.corrupt_reads();
+        let index = Arc::new(PacketIndex::new(reader, pc, &section_header)?);
+        if reader.corrupt_reads() != corrupt_reads {
+            Error::not_implemented(
+                "Recovery of corrupt pages is not supported by the parallel point cloud reader",
+            )?
+        }
+
+        let (job_sender, job_receiver) = channel::<Job>();
+        let (result_sender, result_receiver) = channel::<JobResult>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let threads = worker_readers.len();
+        let mut workers = Vec::with_capacity(threads);
+        for worker_reader in worker_readers {
+            let mut queue_reader = QueueReader::new(pc, worker_reader)?;
+            queue_reader.set_packet_index(index.clone());
+            let jobs = job_receiver.clone();
+            let results = result_sender.clone();
+            workers.push(std::thread::spawn(move || {
+                worker(queue_reader, &jobs, &results)
+            }));
+        }
+
+        let len = pc.prototype.len();
+        Ok(Self {
+            prototype: pc.prototype.clone(),
+            index,
+            records: pc.records,
+            read: 0,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            max_pending: threads * 2,
+            dispatched: 0,
+            next_job: 0,
+            next_result: 0,
+            pending: 0,
+            finished: BTreeMap::new(),
+            current: vec![VecDeque::new(); len],
+            current_len: 0,
+            jobs: Some(job_sender),
+            results: result_receiver,
+            workers,
+        })
+    }
+
+    /// Sets the number of points that are read and decoded together by a single worker thread.
+    /// Bigger chunks reduce the synchronization overhead but need more memory.
+    /// Only affects chunks that were not yet handed to the worker threads.
+    /// Point clouds with string records can only be split at data packets referenced by index packets,
+    /// without index packets all points are read by a single worker thread.
+    /// Default setting is 100000 points.
+    pub fn chunk_size(&mut self, points: usize) {
+        self.chunk_size = points.max(1);
+    }
+
+    /// Reads up to the given number of points into the supplied column buffers.
+    /// Works like [`PointCloudReaderRaw::read_batch`](crate::PointCloudReaderRaw::read_batch).
+    pub fn read_batch(&mut self, buffers: &mut ColumnBuffers, max_points: usize) -> Result<usize> {
+        buffers.validate(&self.prototype)?;
+        buffers.clear();
+
+        let remaining = (self.records - self.read).min(max_points as u64) as usize;
+        let mut read = 0;
+        while read < remaining {
+            self.ensure_current()?;
+            let count = self.current_len.min(remaining - read);
+            buffers.reserve(count);
+            for (i, queue) in self.current.iter_mut().enumerate() {
+                let data_type = &self.prototype[i].data_type;
+                let column = buffers.column_mut(i)?;
+                for value in queue.drain(..count) {
+                    column.push(value, data_type)?;
+                }
+            }
+            self.current_len -= count;
+            self.read += count as u64;
+            read += count;
+        }
+        Ok(read)
+    }
+
+    /// Makes sure that the current chunk contains at least one decoded point.
+    fn ensure_current(&mut self) -> Result<()> {
+        if self.current_len > 0 {
+            return Ok(());
+        }
+        let result = self.receive_next();
+        if result.is_err() {
+            // Decoding cannot continue after an error
+            self.read = self.records;
+        }
+        let queues = result?;
+        self.current_len = queues.first().map(|q| q.len()).unwrap_or(0);
+        if self.current_len == 0 {
+            Error::internal("Received empty chunk of decoded points")?
+        }
+        self.current = queues;
+        Ok(())
+    }
+
+    /// Returns the decoded values of the next chunk in the original order of the points.
+    fn receive_next(&mut self) -> Result<Vec<VecDeque<RecordValue>>> {
+        self.dispatch_jobs()?;
+        loop {
+            if let Some(result) = self.finished.remove(&self.next_result) {
+                self.next_result += 1;
+                return result;
+            }
+            let (id, result) = self
+                .results
+                .recv()
+                .internal_err("Failed to receive decoded points from worker threads")?;
+            self.pending -= 1;
+            self.finished.insert(id, result);
+        }
+    }
+
+    /// Hands ranges of points to the workers until enough work is queued.
+    fn dispatch_jobs(&mut self) -> Result<()> {
+        while self.pending < self.max_pending && self.dispatched < self.records {
+            let end = self.chunk_end();
+            let job = Job {
+                id: self.next_job,
+                start: self.dispatched,
+                points: (end - self.dispatched) as usize,
+            };
+            self.jobs
+                .as_ref()
+                .internal_err("Worker threads are already stopped")?
+                .send(job)
+                .internal_err("Failed to send points to worker threads")?;
+            self.next_job += 1;
+            self.pending += 1;
+            self.dispatched = end;
+        }
+        Ok(())
+    }
+
+    /// Returns the end of the next range of points that is handed to a worker.
+    fn chunk_end(&self) -> u64 {
+        let end = self
+            .dispatched
+            .saturating_add(self.chunk_size as u64)
+            .min(self.records);
+        if self.index.has_strings() {
+            // Strings have no fixed size, so ranges can only start at data packets with known record numbers
+            self.index
+                .next_known_record(end)
+                .unwrap_or(self.records)
+                .min(self.records)
+        } else {
+            end
+        }
+    }
+}
+
+impl Iterator for PointCloudReaderParallel {
+    /// Each iterator item is a result for an extracted point.
+    type Item = Result<RawValues>;
+
+    /// Returns the next available point or None if the end was reached.
+    /// After an error the iteration ends.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.read >= self.records {
+            return None;
+        }
+        if let Err(err) = self.ensure_current() {
+            return Some(Err(err));
+        }
+
+        let mut point = RawValues::with_capacity(self.current.len());
+        for queue in &mut self.current {
+            match queue.pop_front() {
+                Some(value) => point.push(value),
+                None => return Some(Error::internal("Failed to pop value for next point")),
+            }
+        }
+        self.current_len -= 1;
+        self.read += 1;
+        Some(Ok(point))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.records - self.read;
+        (remaining as usize, Some(remaining as usize))
+    }
+}
+
+impl Drop for PointCloudReaderParallel {
+    fn drop(&mut self) {
+        // Closing the job channel stops the workers after their queued jobs
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn worker<R: ReadAt>(
+    mut reader: QueueReader<'_, SourceReader<R>>,
+    jobs: &Mutex<Receiver<Job>>,
+    results: &Sender<JobResult>,
+) {
+    loop {
+        let job = match jobs.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+        let Ok(job) = job else {
+            return;
+        };
+        let result = decode(&mut reader, &job);
+        if results.send((job.id, result)).is_err() {
+            return;
+        }
+    }
+}
+
+/// Reads the data packets of a range of points and decodes them into queues of values.
+fn decode<R: ReadAt>(
+    reader: &mut QueueReader<'_, SourceReader<R>>,
+    job: &Job,
+) -> Result<Vec<VecDeque<RecordValue>>> {
+    // Consecutive ranges can be read without seeking
+    if reader.next_record() != job.start {
+        reader.seek(job.start)?;
+    }
+    while reader.available() < job.points {
+        reader.advance()?;
+    }
+    reader.pop_queues(job.points)
+}
@@ -1,8 +1,16 @@
-use crate::paged_reader::PagedReader;
+use crate::columns::ColumnBuffers;
+use crate::filter::PointFilters;
+use crate::paged_reader::PagedReaderRef;
 use crate::queue_reader::QueueReader;
+use crate::subsample::Subsampler;
+use crate::CartesianBounds;
 use crate::PointCloud;
+use crate::PointFilter;
 use crate::RawValues;
-use crate::Result;
+use crate::Record;
+use crate::RecordName;
+use crate::Subsampling;
+use crate::{Error, Result};
 use std::io::{Read, Seek};
 
 /// Iterate over all raw points of a point cloud for reading.
@@ -11,55 +19,242 @@ pub struct PointCloudReaderRaw<'a, T: Read + Seek> {
     prototype_len: usize,
     records: u64,
     read: u64,
+    filters: PointFilters,
+    subsampler: Subsampler,
+    bounds: Option<CartesianBounds>,
+    outside: bool,
 }
 
 impl<'a, T: Read + Seek> PointCloudReaderRaw<'a, T> {
-    pub(crate) fn new(pc: &PointCloud, reader: &'a mut PagedReader<T>) -> Result<Self> {
+    pub(crate) fn new(pc: &PointCloud, reader: impl Into<PagedReaderRef<'a, T>>) -> Result<Self> {
         let queue_reader = QueueReader::new(pc, reader)?;
-        let prototype_len = pc.prototype.len();
+        Ok(Self::from_queue_reader(pc, queue_reader))
+    }
+
+    pub(crate) fn new_projected(
+        pc: &PointCloud,
+        reader: impl Into<PagedReaderRef<'a, T>>,
+        records: &[RecordName],
+    ) -> Result<Self> {
+        let queue_reader = QueueReader::new_projected(pc, reader, records)?;
+        Ok(Self::from_queue_reader(pc, queue_reader))
+    }
+
+    pub(crate) fn from_queue_reader(pc: &PointCloud, queue_reader: QueueReader<'a, T>) -> Self {
+        let prototype_len = queue_reader.prototype().len();
         let records = pc.records;
-        Ok(Self {
+        let filters = PointFilters::new(queue_reader.prototype());
+        let subsampler = Subsampler::new(queue_reader.prototype());
+        Self {
             queue_reader,
             prototype_len,
             records,
             read: 0,
-        })
+            filters,
+            subsampler,
+            bounds: pc.get_cartesian_bounds(),
+            outside: false,
+        }
     }
-}
 
-impl<T: Read + Seek> Iterator for PointCloudReaderRaw<'_, T> {
-    /// Each iterator item is a result for an extracted point.
-    type Item = Result<RawValues>;
+    /// Returns the prototype records of the values returned by this reader.
+    /// This is the full prototype of the point cloud, unless the reader was created with a projection.
+    pub fn prototype(&self) -> &[Record] {
+        self.queue_reader.prototype()
+    }
 
-    /// Returns the next available point or None if the end was reached.
-    fn next(&mut self) -> Option<Self::Item> {
-        // Already read all points?
-        if self.read >= self.records {
-            return None;
+    /// Moves the reader to the point with the given index.
+    /// The next point returned by the iterator will be the requested point.
+    /// Seeking to the number of records of the point cloud is allowed and ends the iteration.
+    ///
+    /// If the point cloud has index packets, they are used to find the data packet containing the point.
+    /// Otherwise, or if the index packets do not match the data packets,
+    /// all packet headers of the point cloud are scanned once without decoding any points.
+    /// The resulting lookup table is kept, so subsequent seeks on the same reader are cheap.
+    pub fn seek_to_point(&mut self, index: u64) -> Result<()> {
+        if index > self.records {
+            Error::invalid(format!(
+                "Cannot seek to point {index}, the point cloud has only {} points",
+                self.records
+            ))?
+        }
+        if index < self.records {
+            self.queue_reader.seek(index)?;
+        }
+        self.read = index;
+        Ok(())
+    }
+
+    /// Adds a filter to skip points while reading.
+    /// Points are only returned if they pass all added filters.
+    /// All records used by the filter must be part of the prototype of this reader.
+    ///
+    /// If the stored bounds of the point cloud cannot intersect with a spatial filter,
+    /// the point cloud is skipped completely without reading any data.
+    pub fn add_filter(&mut self, filter: PointFilter) -> Result<()> {
+        self.filters.add(filter, self.queue_reader.prototype())?;
+        if !self.filters.intersects(&self.bounds) {
+            self.outside = true;
+        }
+        Ok(())
+    }
+
+    /// Enables subsampling to read only a part of the points.
+    /// Replaces any previously set subsampling mode.
+    /// All records required by the mode must be part of the prototype of this reader.
+    ///
+    /// Subsampling is applied after all filters, which is relevant for the voxel mode.
+    /// The selected voxels are kept when seeking, so each voxel is returned only once.
+    pub fn subsample(&mut self, mode: Subsampling) -> Result<()> {
+        self.subsampler.set(mode)
+    }
+
+    /// Reads up to the given number of points into the supplied column buffers.
+    /// The buffers are cleared before reading and their allocated memory is reused.
+    /// Returns the number of points read, which is zero after the last point was read.
+    ///
+    /// Compared to iterating over the reader, this avoids allocating memory for each individual point.
+    /// Scaled integer values are converted to floating point values with their scale and offset applied.
+    pub fn read_batch(&mut self, buffers: &mut ColumnBuffers, max_points: usize) -> Result<usize> {
+        buffers.validate(self.queue_reader.prototype())?;
+        buffers.clear();
+        if self.is_selective() {
+            return self.read_batch_filtered(buffers, max_points);
+        }
+
+        let mut read = 0;
+        while read < max_points {
+            while self.queue_reader.available() < 1 && self.remaining() > 0 {
+                self.queue_reader.advance()?;
+            }
+            let remaining = self.remaining().min((max_points - read) as u64) as usize;
+            if remaining == 0 {
+                break;
+            }
+            let count = self.queue_reader.available().min(remaining);
+            self.queue_reader.pop_columns(count, buffers)?;
+            self.read += count as u64;
+            read += count;
+        }
+        Ok(read)
+    }
+
+    /// Returns the number of points that were skipped because of corrupt pages.
+    /// Always zero, unless the recovery mode was enabled in the reader options,
+    /// see [`E57ReaderOptions::recover_corrupt_pages`](crate::E57ReaderOptions::recover_corrupt_pages).
+    pub fn lost_points(&self) -> u64 {
+        self.queue_reader.lost_points()
+    }
+
+    /// Returns the numbers of all pages with invalid checksums found by this reader in recovery mode.
+    pub fn corrupt_pages(&self) -> &[u64] {
+        self.queue_reader.corrupt_pages()
+    }
+
+    /// Returns true if filters or subsampling might skip points.
+    fn is_selective(&self) -> bool {
+        !self.filters.is_empty() || self.subsampler.is_active()
+    }
+
+    /// Same as `read_batch()` but with filters or subsampling, which requires checking each point individually.
+    fn read_batch_filtered(
+        &mut self,
+        buffers: &mut ColumnBuffers,
+        max_points: usize,
+    ) -> Result<usize> {
+        let mut read = 0;
+        while read < max_points {
+            let Some(point) = self.next_point()? else {
+                break;
+            };
+            for (i, (value, record)) in point.into_iter().zip(self.prototype()).enumerate() {
+                buffers.column_mut(i)?.push(value, &record.data_type)?;
+            }
+            read += 1;
         }
+        Ok(read)
+    }
+
+    /// Returns the next point that passes all filters or None if the end was reached.
+    fn next_point(&mut self) -> Result<Option<RawValues>> {
+        loop {
+            // Already read all points?
+            if self.remaining() == 0 {
+                return Ok(None);
+            }
+
+            // Skip data packets without selected points
+            if self.queue_reader.available() < 1 {
+                self.skip_unselected()?;
+                if self.remaining() == 0 {
+                    return Ok(None);
+                }
+            }
+
+            // Refill property queues if required
+            // (in some corner cases more than one advance is required)
+            while self.queue_reader.available() < 1 {
+                // Remaining points might have been lost because of corrupt pages
+                if self.remaining() == 0 {
+                    return Ok(None);
+                }
+                self.queue_reader.advance()?;
+            }
 
-        // Refill property queues if required
-        // (in some corner cases more than one advance is required)
-        while self.queue_reader.available() < 1 {
-            if let Err(err) = self.queue_reader.advance() {
-                return Some(Err(err));
+            // Extract next point
+            let index = self.queue_reader.next_record();
+            let mut point = RawValues::with_capacity(self.prototype_len);
+            self.queue_reader.pop_point(&mut point)?;
+            self.read += 1;
+            let prototype = self.queue_reader.prototype();
+            if (self.filters.is_empty() || self.filters.matches(prototype, &point)?)
+                && self.subsampler.keep(index, prototype, &point)?
+            {
+                return Ok(Some(point));
             }
         }
+    }
 
-        // Extract next point
-        let mut point = RawValues::with_capacity(self.prototype_len);
-        match self.queue_reader.pop_point(&mut point) {
-            Ok(()) => {
-                self.read += 1;
-                Some(Ok(point))
+    /// Skips the points in front of the next selected point without decoding them,
+    /// if the subsampling mode allows it.
+    fn skip_unselected(&mut self) -> Result<()> {
+        let current = self.queue_reader.next_record();
+        if let Some(next) = self.subsampler.next_selected(current) {
+            let next = next.min(self.records);
+            if next > current {
+                self.queue_reader.skip_to(next)?;
+                self.read += next - current;
             }
-            Err(err) => Some(Err(err)),
         }
+        Ok(())
+    }
+
+    /// Returns the number of points that are left to read.
+    fn remaining(&self) -> u64 {
+        if self.outside {
+            return 0;
+        }
+        self.records
+            .saturating_sub(self.read)
+            .saturating_sub(self.queue_reader.lost_points_since_seek())
+    }
+}
+
+impl<T: Read + Seek> Iterator for PointCloudReaderRaw<'_, T> {
+    /// Each iterator item is a result for an extracted point.
+    type Item = Result<RawValues>;
+
+    /// Returns the next available point or None if the end was reached.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_point().transpose()
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let overall = self.records;
-        let remaining = overall - self.read;
-        (remaining as usize, Some(remaining as usize))
+        let remaining = self.remaining() as usize;
+        if !self.is_selective() {
+            (remaining, Some(remaining))
+        } else {
+            (0, Some(remaining))
+        }
     }
 }
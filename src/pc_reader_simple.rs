@@ -1,8 +1,13 @@
-use crate::paged_reader::PagedReader;
+use crate::error::Converter;
+use crate::extension::surface_normal_names;
+use crate::filter::PointFilters;
+use crate::paged_reader::PagedReaderRef;
 use crate::queue_reader::QueueReader;
+use crate::subsample::Subsampler;
 use crate::{
-    CartesianCoordinate, Color, ColorLimits, Error, Point, PointCloud, RecordDataType, RecordName,
-    RecordValue, Result, SphericalCoordinate, Transform, Translation,
+    CartesianCoordinate, Color, ColorLimits, Error, Extension, Normal, Point, PointCloud,
+    PointFilter, RecordDataType, RecordName, RecordValue, Result, SphericalCoordinate, Subsampling,
+    Transform, Translation,
 };
 use std::collections::VecDeque;
 use std::io::{Read, Seek};
@@ -18,6 +23,12 @@ struct Indices {
     intensity_invalid: Option<usize>,
     row: Option<usize>,
     column: Option<usize>,
+    timestamp: Option<usize>,
+    timestamp_invalid: Option<usize>,
+    return_index: Option<usize>,
+    return_count: Option<usize>,
+    normal: Option<(usize, usize, usize)>,
+    extensions: Vec<usize>,
 }
 
 /// Simple iterator over all normalized points of a point cloud for reading.
@@ -44,16 +55,23 @@ pub struct PointCloudReaderSimple<'a, T: Read + Seek> {
     red_range: Option<Range>,       // Red color range for normalization
     green_range: Option<Range>,     // Green color range for normalization
     blue_range: Option<Range>,      // Blue color range for normalization
+    filters: PointFilters,          // Filters to be applied to the raw values of all points
+    subsampler: Subsampler,         // Subsampling to be applied after the filters
+    outside: bool, // Filters cannot match any points inside the point cloud bounds?
 }
 
 impl<'a, T: Read + Seek> PointCloudReaderSimple<'a, T> {
-    pub(crate) fn new(pc: &PointCloud, reader: &'a mut PagedReader<T>) -> Result<Self> {
+    pub(crate) fn new(
+        pc: &PointCloud,
+        extensions: &[Extension],
+        reader: impl Into<PagedReaderRef<'a, T>>,
+    ) -> Result<Self> {
         let (rotation, translation) = Self::prepare_transform(pc);
         Ok(Self {
             rotation,
             translation,
             pc: pc.clone(),
-            indices: Self::prepare_indices(pc),
+            indices: Self::prepare_indices(pc, extensions),
             queue_reader: QueueReader::new(pc, reader)?,
             transform: true,
             s2c: true,
@@ -69,9 +87,103 @@ impl<'a, T: Read + Seek> PointCloudReaderSimple<'a, T> {
             red_range: Range::red_from_pointcloud(pc)?,
             green_range: Range::green_from_pointcloud(pc)?,
             blue_range: Range::blue_from_pointcloud(pc)?,
+            filters: PointFilters::new(&pc.prototype),
+            subsampler: Subsampler::new(&pc.prototype),
+            outside: false,
         })
     }
 
+    /// Moves the reader to the point with the given index.
+    /// The next point returned by the iterator will be the requested point.
+    /// Seeking to the number of records of the point cloud is allowed and ends the iteration.
+    ///
+    /// If the point cloud has index packets, they are used to find the data packet containing the point.
+    /// Otherwise, or if the index packets do not match the data packets,
+    /// all packet headers of the point cloud are scanned once without decoding any points.
+    /// The resulting lookup table is kept, so subsequent seeks on the same reader are cheap.
+    pub fn seek_to_point(&mut self, index: u64) -> Result<()> {
+        if index > self.pc.records {
+            Error::invalid(format!(
+                "Cannot seek to point {index}, the point cloud has only {} points",
+                self.pc.records
+            ))?
+        }
+        self.points.clear();
+        if index < self.pc.records {
+            self.queue_reader.seek(index)?;
+        }
+        self.read = index;
+        Ok(())
+    }
+
+    /// Returns the number of points that were skipped because of corrupt pages.
+    /// Always zero, unless the recovery mode was enabled in the reader options,
+    /// see [`E57ReaderOptions::recover_corrupt_pages`](crate::E57ReaderOptions::recover_corrupt_pages).
+    pub fn lost_points(&self) -> u64 {
+        self.queue_reader.lost_points()
+    }
+
+    /// Returns the numbers of all pages with invalid checksums found by this reader in recovery mode.
+    pub fn corrupt_pages(&self) -> &[u64] {
+        self.queue_reader.corrupt_pages()
+    }
+
+    /// Adds a filter to skip points while reading.
+    /// Points are only returned if they pass all added filters.
+    /// Filters are evaluated on the raw values before any post-processing,
+    /// which means before applying the pose or normalizing intensity and color values.
+    ///
+    /// If the stored bounds of the point cloud cannot intersect with a spatial filter,
+    /// the point cloud is skipped completely without reading any data.
+    /// Should be called before reading any points, since already buffered points are not filtered.
+    pub fn add_filter(&mut self, filter: PointFilter) -> Result<()> {
+        self.filters.add(filter, &self.pc.prototype)?;
+        if !self.filters.intersects(&self.pc.get_cartesian_bounds()) {
+            self.outside = true;
+        }
+        Ok(())
+    }
+
+    /// Enables subsampling to read only a part of the points.
+    /// Replaces any previously set subsampling mode.
+    /// Should be called before reading any points, since already buffered points are not subsampled.
+    ///
+    /// Subsampling is applied after all filters, which is relevant for the voxel mode.
+    /// The selected voxels are kept when seeking, so each voxel is returned only once.
+    pub fn subsample(&mut self, mode: Subsampling) -> Result<()> {
+        self.subsampler.set(mode)
+    }
+
+    /// Returns true if filters or subsampling might skip points.
+    fn is_selective(&self) -> bool {
+        !self.filters.is_empty() || self.subsampler.is_active()
+    }
+
+    /// Skips the points in front of the next selected point without decoding them,
+    /// if the subsampling mode allows it.
+    fn skip_unselected(&mut self) -> Result<()> {
+        let current = self.queue_reader.next_record();
+        if let Some(next) = self.subsampler.next_selected(current) {
+            let next = next.min(self.pc.records);
+            if next > current {
+                self.queue_reader.skip_to(next)?;
+                self.read += next - current;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of points that are left to read.
+    fn remaining(&self) -> u64 {
+        if self.outside {
+            return 0;
+        }
+        self.pc
+            .records
+            .saturating_sub(self.read)
+            .saturating_sub(self.queue_reader.lost_points_since_seek())
+    }
+
     /// If enabled, the iterator will automatically convert spherical to Cartesian coordinates.
     /// Will only replace fully invalid Cartesian coordinates and do nothing otherwise.
     /// Default setting is enabled.
@@ -108,35 +220,56 @@ impl<'a, T: Read + Seek> PointCloudReaderSimple<'a, T> {
     }
 
     /// If enabled, the iterator will apply the point cloud pose to the Cartesian coordinates.
+    /// Surface normals are rotated with the pose, but not translated.
     /// Default setting is enabled.
     pub fn apply_pose(&mut self, enable: bool) {
         self.transform = enable;
     }
 
+    /// Registers additional point attributes that will be extracted into the extension values of each point.
+    /// This allows reading attributes that are not part of the E57 standard, like custom attributes of proprietary extensions.
+    /// The values are returned as floating point values in the same order as the given names,
+    /// scaled integers have their scale and offset applied.
+    /// Only records with numeric data types are supported.
+    /// Should be called before reading any points, since already buffered points are not updated.
+    /// See also [`Point::extensions`](crate::Point::extensions).
+    pub fn extension_attributes(&mut self, names: &[RecordName]) -> Result<()> {
+        let mut extensions = Vec::with_capacity(names.len());
+        for name in names {
+            let index = self
+                .pc
+                .prototype
+                .iter()
+                .position(|r| r.name == *name)
+                .invalid_err(format!("Point cloud has no record with the name {name:?}"))?;
+            if matches!(self.pc.prototype[index].data_type, RecordDataType::String) {
+                Error::invalid(format!(
+                    "Record {name:?} contains strings and cannot be read as extension attribute"
+                ))?
+            }
+            extensions.push(index);
+        }
+        self.indices.extensions = extensions;
+        Ok(())
+    }
+
     fn prepare_transform(pc: &PointCloud) -> ([f64; 9], Translation) {
         let t = if let Some(t) = &pc.transform {
             t.clone()
         } else {
             Transform::default()
         };
-        let q = &t.rotation;
+        // Flatten the matrix in column-major order
+        let m = t.rotation.to_matrix();
         (
             [
-                q.w * q.w + q.x * q.x - q.y * q.y - q.z * q.z,
-                2.0 * (q.x * q.y + q.w * q.z),
-                2.0 * (q.x * q.z - q.w * q.y),
-                2.0 * (q.x * q.y - q.w * q.z),
-                q.w * q.w + q.y * q.y - q.x * q.x - q.z * q.z,
-                2.0 * (q.y * q.z + q.w * q.x),
-                2.0 * (q.x * q.z + q.w * q.y),
-                2.0 * (q.y * q.z - q.w * q.x),
-                q.w * q.w + q.z * q.z - q.x * q.x - q.y * q.y,
+                m[0][0], m[1][0], m[2][0], m[0][1], m[1][1], m[2][1], m[0][2], m[1][2], m[2][2],
             ],
             t.translation,
         )
     }
 
-    fn prepare_indices(pc: &PointCloud) -> Indices {
+    fn prepare_indices(pc: &PointCloud, extensions: &[Extension]) -> Indices {
         let fi = |name: RecordName| -> Option<usize> {
             pc.prototype.iter().position(|r| r.name == name)
         };
@@ -161,6 +294,11 @@ impl<'a, T: Read + Seek> PointCloudReaderSimple<'a, T> {
             (Some(red), Some(green), Some(blue)) => Some((red, green, blue)),
             _ => None,
         };
+        let normals = surface_normal_names(extensions).map(|names| names.map(&fi));
+        let normal = match normals {
+            Some([Some(nx), Some(ny), Some(nz)]) => Some((nx, ny, nz)),
+            _ => None,
+        };
         Indices {
             cartesian,
             cartesian_invalid: fi(RecordName::CartesianInvalidState),
@@ -172,6 +310,12 @@ impl<'a, T: Read + Seek> PointCloudReaderSimple<'a, T> {
             intensity_invalid: fi(RecordName::IsIntensityInvalid),
             row: fi(RecordName::RowIndex),
             column: fi(RecordName::ColumnIndex),
+            timestamp: fi(RecordName::TimeStamp),
+            timestamp_invalid: fi(RecordName::IsTimeStampInvalid),
+            return_index: fi(RecordName::ReturnIndex),
+            return_count: fi(RecordName::ReturnCount),
+            normal,
+            extensions: Vec::new(),
         }
     }
 
@@ -189,10 +333,20 @@ impl<'a, T: Read + Seek> PointCloudReaderSimple<'a, T> {
         }
     }
 
-    fn pop_point(&mut self) -> Result<Point> {
+    fn pop_point(&mut self) -> Result<Option<Point>> {
         // Read raw values of the point from queue
+        let index = self.queue_reader.next_record();
         self.queue_reader.pop_point(&mut self.values)?;
 
+        // Rejected points count as consumed
+        let proto = &self.pc.prototype;
+        if (!self.filters.is_empty() && !self.filters.matches(proto, &self.values)?)
+            || !self.subsampler.keep(index, proto, &self.values)?
+        {
+            self.read += 1;
+            return Ok(None);
+        }
+
         // Some shortcuts for better readability
         let proto = &self.pc.prototype;
         let values = &self.values;
@@ -336,48 +490,97 @@ impl<'a, T: Read + Seek> PointCloudReaderSimple<'a, T> {
             -1
         };
 
-        Ok(Point {
+        Ok(Some(Point {
             cartesian,
             spherical,
             color,
             intensity,
             row,
             column,
-        })
+            timestamp: self.timestamp()?,
+            return_index: self.integer_or_default(self.indices.return_index)?,
+            return_count: self.integer_or_default(self.indices.return_count)?,
+            normal: self.normal()?,
+            extensions: self
+                .indices
+                .extensions
+                .iter()
+                .map(|i| self.values[*i].to_f64(&self.pc.prototype[*i].data_type))
+                .collect::<Result<_>>()?,
+        }))
     }
-}
 
-impl<T: Read + Seek> Iterator for PointCloudReaderSimple<'_, T> {
-    /// Each iterator item is a result for an extracted point.
-    type Item = Result<Point>;
+    /// Extracts the time stamp from the raw values of the last popped point.
+    fn timestamp(&self) -> Result<Option<f64>> {
+        let proto = &self.pc.prototype;
+        let values = &self.values;
+        let Some(ind) = self.indices.timestamp else {
+            return Ok(None);
+        };
+        let timestamp_invalid = if let Some(ind) = self.indices.timestamp_invalid {
+            values[ind].to_i64(&proto[ind].data_type)?
+        } else {
+            0
+        };
+        if timestamp_invalid == 0 {
+            Ok(Some(values[ind].to_f64(&proto[ind].data_type)?))
+        } else if timestamp_invalid == 1 {
+            Ok(None)
+        } else {
+            Error::invalid(format!(
+                "Time stamp invalid state contains invalid value: {timestamp_invalid}"
+            ))
+        }
+    }
 
-    /// Returns the next available point or None if the end was reached.
-    fn next(&mut self) -> Option<Self::Item> {
-        // Already read all points?
-        if self.read >= self.pc.records {
-            return None;
+    /// Extracts the surface normal from the raw values of the last popped point.
+    fn normal(&self) -> Result<Option<Normal>> {
+        let proto = &self.pc.prototype;
+        let values = &self.values;
+        let Some(ind) = self.indices.normal else {
+            return Ok(None);
+        };
+        Ok(Some(Normal {
+            x: values[ind.0].to_f64(&proto[ind.0].data_type)?,
+            y: values[ind.1].to_f64(&proto[ind.1].data_type)?,
+            z: values[ind.2].to_f64(&proto[ind.2].data_type)?,
+        }))
+    }
+
+    /// Extracts an integer value from the raw values of the last popped point or returns -1 if there is none.
+    fn integer_or_default(&self, index: Option<usize>) -> Result<i64> {
+        if let Some(ind) = index {
+            self.values[ind].to_i64(&self.pc.prototype[ind].data_type)
+        } else {
+            Ok(-1)
         }
+    }
 
-        // Is there a point available in the output queue?
-        if let Some(point) = self.points.pop_front() {
-            self.read += 1;
-            return Some(Ok(point));
+    /// Extracts and post-processes the next batch of points into the output queue.
+    /// Returns false if there are no more points to read.
+    fn refill(&mut self) -> Result<bool> {
+        // Skip data packets without selected points
+        if self.queue_reader.available() < 1 {
+            self.skip_unselected()?;
         }
 
         // Refill queues with raw point values
-        if let Err(err) = self.queue_reader.advance() {
-            return Some(Err(err));
+        // (in some corner cases more than one advance is required)
+        while self.queue_reader.available() < 1 {
+            // Remaining points might have been lost because of corrupt pages
+            if self.remaining() == 0 {
+                return Ok(false);
+            }
+            self.queue_reader.advance()?;
         }
 
         // Read raw point values as simple point, add to buffer
         let available = self.queue_reader.available();
         self.buffer.reserve(available);
         for _ in 0..available {
-            let p = match self.pop_point() {
-                Ok(p) => p,
-                Err(err) => return Some(Err(err)),
-            };
-            self.buffer.push(p);
+            if let Some(p) = self.pop_point()? {
+                self.buffer.push(p);
+            }
         }
 
         // Post-processing of the points in the buffer
@@ -403,26 +606,45 @@ impl<T: Read + Seek> Iterator for PointCloudReaderSimple<'_, T> {
         }
 
         // Move points from buffer to output queue
-        self.points.reserve(available);
-        for p in self.buffer.drain(..) {
-            self.points.push_back(p);
-        }
+        self.points.extend(self.buffer.drain(..));
+        Ok(true)
+    }
+}
 
-        // Get and return one of the new points
-        if let Some(point) = self.points.pop_front() {
-            self.read += 1;
-            Some(Ok(point))
-        } else {
-            Some(Error::internal(
-                "Cannot read next point because of logic error",
-            ))
+impl<T: Read + Seek> Iterator for PointCloudReaderSimple<'_, T> {
+    /// Each iterator item is a result for an extracted point.
+    type Item = Result<Point>;
+
+    /// Returns the next available point or None if the end was reached.
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Already read all points?
+            if self.remaining() == 0 {
+                return None;
+            }
+
+            // Is there a point available in the output queue?
+            if let Some(point) = self.points.pop_front() {
+                self.read += 1;
+                return Some(Ok(point));
+            }
+
+            // Refill output queue, might add no points if all of them were filtered
+            match self.refill() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(err) => return Some(Err(err)),
+            }
         }
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let overall = self.pc.records;
-        let remaining = overall - self.read;
-        (remaining as usize, Some(remaining as usize))
+        let remaining = self.remaining() as usize;
+        if !self.is_selective() {
+            (remaining, Some(remaining))
+        } else {
+            (0, Some(remaining))
+        }
     }
 }
 
@@ -437,6 +659,13 @@ fn transform_point(p: &mut Point, rotation: &[f64; 9], translation: &Translation
             z: nz + translation.z,
         };
     }
+    if let Some(n) = &p.normal {
+        p.normal = Some(Normal {
+            x: rotation[0] * n.x + rotation[3] * n.y + rotation[6] * n.z,
+            y: rotation[1] * n.x + rotation[4] * n.y + rotation[7] * n.z,
+            z: rotation[2] * n.x + rotation[5] * n.y + rotation[8] * n.z,
+        });
+    }
 }
 
 fn convert_to_cartesian(p: &mut Point) {
@@ -555,6 +784,7 @@ impl Range {
                 *max as f64 * *scale + *offset,
             ),
             RecordDataType::Integer { min, max } => Self::from_min_max(*min as f64, *max as f64),
+            RecordDataType::String => Error::invalid("Cannot normalize values of type string"),
         }
     }
 
@@ -688,6 +918,11 @@ mod tests {
             intensity: None,
             row: -1,
             column: -1,
+            timestamp: None,
+            return_index: -1,
+            return_count: -1,
+            normal: None,
+            extensions: Vec::new(),
         };
         convert_to_spherical(&mut p);
         assert_eq!(
@@ -713,6 +948,11 @@ mod tests {
             intensity: None,
             row: -1,
             column: -1,
+            timestamp: None,
+            return_index: -1,
+            return_count: -1,
+            normal: None,
+            extensions: Vec::new(),
         };
         convert_to_cartesian(&mut p);
         if let CartesianCoordinate::Valid { x, y, z } = p.cartesian {
@@ -738,6 +978,11 @@ mod tests {
             intensity: None,
             row: -1,
             column: -1,
+            timestamp: None,
+            return_index: -1,
+            return_count: -1,
+            normal: None,
+            extensions: Vec::new(),
         };
         convert_to_spherical(&mut point);
         point.cartesian = CartesianCoordinate::Invalid;
@@ -1,14 +1,21 @@
 use crate::bs_write::ByteStreamWriteBuffer;
 use crate::cv_section::CompressedVectorSectionHeader;
 use crate::error::Converter;
-use crate::packet::DataPacketHeader;
+use crate::extension::{set_extension_data, surface_normal_names};
+use crate::grouping::{GroupRecord, LineGroupCollector};
+use crate::packet::{DataPacketHeader, IndexPacketEntry, IndexPacketHeader};
+use crate::packet_index::write_index_packets;
 use crate::paged_writer::PagedWriter;
+use crate::record::serialized_string_size;
 use crate::CartesianBounds;
 use crate::ColorLimits;
 use crate::DateTime;
 use crate::Error;
+use crate::Extension;
+use crate::ExtensionData;
 use crate::IndexBounds;
 use crate::IntensityLimits;
+use crate::LineGrouping;
 use crate::PointCloud;
 use crate::RawValues;
 use crate::Record;
@@ -18,6 +25,7 @@ use crate::RecordValue;
 use crate::Result;
 use crate::SphericalBounds;
 use crate::Transform;
+use crate::XmlElement;
 use std::collections::VecDeque;
 use std::io::{Read, Seek, Write};
 
@@ -28,6 +36,7 @@ use std::io::{Read, Seek, Write};
 pub struct PointCloudWriter<'a, T: Read + Write + Seek> {
     writer: &'a mut PagedWriter<T>,
     pointclouds: &'a mut Vec<PointCloud>,
+    extensions: &'a mut Vec<Extension>,
     guid: String,
     section_offset: u64,
     section_header: CompressedVectorSectionHeader,
@@ -39,6 +48,8 @@ pub struct PointCloudWriter<'a, T: Read + Write + Seek> {
     buffer: VecDeque<RawValues>,
     max_points_per_packet: usize,
     byte_streams: Vec<ByteStreamWriteBuffer>,
+    index_entries: Option<Vec<IndexPacketEntry>>,
+    line_groups: Option<LineGroupCollector>,
     cartesian_bounds: Option<CartesianBounds>,
     spherical_bounds: Option<SphericalBounds>,
     index_bounds: Option<IndexBounds>,
@@ -58,12 +69,14 @@ pub struct PointCloudWriter<'a, T: Read + Write + Seek> {
     temperature: Option<f64>,
     humidity: Option<f64>,
     atmospheric_pressure: Option<f64>,
+    additional_xml: Vec<XmlElement>,
 }
 
 impl<'a, T: Read + Write + Seek> PointCloudWriter<'a, T> {
     pub(crate) fn new(
         writer: &'a mut PagedWriter<T>,
         pointclouds: &'a mut Vec<PointCloud>,
+        extensions: &'a mut Vec<Extension>,
         guid: &str,
         prototype: Vec<Record>,
     ) -> Result<Self> {
@@ -153,6 +166,7 @@ impl<'a, T: Read + Write + Seek> PointCloudWriter<'a, T> {
         Ok(PointCloudWriter {
             writer,
             pointclouds,
+            extensions,
             guid: guid.to_owned(),
             section_offset,
             section_header,
@@ -164,6 +178,8 @@ impl<'a, T: Read + Write + Seek> PointCloudWriter<'a, T> {
             buffer: VecDeque::new(),
             byte_streams,
             max_points_per_packet,
+            index_entries: None,
+            line_groups: None,
             cartesian_bounds,
             spherical_bounds,
             index_bounds,
@@ -183,6 +199,7 @@ impl<'a, T: Read + Write + Seek> PointCloudWriter<'a, T> {
             temperature: None,
             humidity: None,
             atmospheric_pressure: None,
+            additional_xml: Vec::new(),
         })
     }
 
@@ -273,6 +290,20 @@ impl<'a, T: Read + Write + Seek> PointCloudWriter<'a, T> {
         self.atmospheric_pressure = value;
     }
 
+    /// Attaches typed extension data to the point cloud.
+    /// Replaces any previously set data of the same type.
+    /// The extension of the data is registered automatically, if required.
+    pub fn set_extension_data<D: ExtensionData>(&mut self, data: &D) -> Result<()> {
+        set_extension_data(&mut self.additional_xml, self.extensions, data)
+    }
+
+    /// Sets additional XML elements for the point cloud descriptor, for example from an existing file.
+    /// Replaces all previously set elements, including typed extension data.
+    /// Namespaces used by the elements must be registered as extensions.
+    pub fn set_additional_xml(&mut self, elements: Vec<XmlElement>) {
+        self.additional_xml = elements;
+    }
+
     /// Sets the intensity limits of the point cloud.
     /// Only required if the point cloud contains intensity data.
     /// This must represent the full range of the sensor that captured the point cloud data.
@@ -326,6 +357,45 @@ impl<'a, T: Read + Write + Seek> PointCloudWriter<'a, T> {
         self.spherical_bounds = value;
     }
 
+    /// If enabled, index packets are written when the point cloud is finalized (disabled by default).
+    /// Index packets allow readers to find the data packet of a specific point
+    /// without decoding or scanning all previous data packets.
+    /// To allow this, data packets are written so that they start at record boundaries.
+    /// Should be set before adding the first point to make sure all data packets are indexed.
+    pub fn write_index_packets(&mut self, enable: bool) {
+        let max_points = get_max_packet_points(&self.prototype);
+        if enable {
+            // A multiple of eight points per packet ensures that each packet
+            // ends on full bytes and the next packet starts with a complete record.
+            if max_points >= 8 {
+                self.max_points_per_packet = max_points - max_points % 8;
+            }
+            if self.index_entries.is_none() {
+                self.index_entries = Some(Vec::new());
+            }
+        } else {
+            self.max_points_per_packet = max_points;
+            self.index_entries = None;
+        }
+    }
+
+    /// Enables grouping of the points into lines (disabled by default).
+    /// The lines are identified by the given row or column index record of the prototype.
+    /// All points of a line must be added contiguously.
+    /// For each line the start index, the number of points and the Cartesian bounds are collected
+    /// and written into the E57 file when the point cloud is finalized.
+    /// This needs to be configured before adding the first point.
+    pub fn set_line_grouping(&mut self, value: Option<RecordName>) -> Result<()> {
+        if self.point_count > 0 {
+            Error::invalid("Line grouping must be configured before adding points")?
+        }
+        self.line_groups = match value {
+            Some(name) => Some(LineGroupCollector::new(&self.prototype, name)?),
+            None => None,
+        };
+        Ok(())
+    }
+
     fn validate_prototype(prototype: &[Record]) -> Result<()> {
         // Helpers to check and look up records
         let contains = |n: RecordName| prototype.iter().any(|p| p.name == n);
@@ -380,10 +450,42 @@ impl<'a, T: Read + Write + Seek> PointCloudWriter<'a, T> {
         Ok(())
     }
 
+    /// Returns the number of buffered points for the next data packet.
+    /// Strings have a variable size and limit the number of points that fit into a packet.
+    fn get_packet_points(&self) -> Result<usize> {
+        let max_points = self.max_points_per_packet.min(self.buffer.len());
+        let has_strings = self
+            .prototype
+            .iter()
+            .any(|r| matches!(r.data_type, RecordDataType::String));
+        if !has_strings {
+            return Ok(max_points);
+        }
+
+        let max_bits = get_max_packet_bytes(&self.prototype) * 8;
+        let mut packet_bits = 0;
+        let mut packet_points = 0;
+        for p in self.buffer.iter().take(max_points) {
+            let point_bits = get_point_bits(&self.prototype, p);
+            if packet_bits + point_bits > max_bits {
+                break;
+            }
+            packet_bits += point_bits;
+            packet_points += 1;
+        }
+        if packet_points == 0 && max_points > 0 {
+            Error::invalid("Point is too big to fit into a single data packet")?
+        }
+        Ok(packet_points)
+    }
+
     fn write_buffer_to_disk(&mut self, last_flush: bool) -> Result<()> {
+        // Packets can only be indexed if they start with a complete record
+        let first_record = self.point_count - self.buffer.len() as u64;
+        let record_aligned = self.byte_streams.iter().all(|bs| bs.all_bytes() == 0);
+
         // Add points from buffer into byte streams
-        let packet_points = self.max_points_per_packet.min(self.buffer.len());
-        let proto_len = self.prototype.len();
+        let packet_points = self.get_packet_points()?;
         for _ in 0..packet_points {
             let p = self
                 .buffer
@@ -399,68 +501,19 @@ impl<'a, T: Read + Write + Seek> PointCloudWriter<'a, T> {
             }
         }
 
-        // Check and prepare buffer sizes
-        let mut sum_bs_sizes = 0;
-        let mut bs_sizes = Vec::with_capacity(proto_len);
-        for bs in &self.byte_streams {
-            let bs_size = if last_flush {
-                bs.all_bytes()
-            } else {
-                bs.full_bytes()
-            };
-            sum_bs_sizes += bs_size;
-            bs_sizes.push(bs_size as u16);
-        }
-
-        // Only write packet if there is actual data!
-        if sum_bs_sizes > 0 {
-            // Calculate packet length for header, must be aligned to four bytes.
-            // If the length exceeds 2^16 this library has somewhere a logic bug!
-            let mut packet_length = DataPacketHeader::SIZE + proto_len * 2 + sum_bs_sizes;
-            if !packet_length.is_multiple_of(4) {
-                let missing = 4 - (packet_length % 4);
-                packet_length += missing;
-            }
-            if packet_length > u16::MAX as usize {
-                Error::internal("Invalid data packet length detected")?
-            }
-
-            // Add data packet length to section length for later
-            self.section_header.section_length += packet_length as u64;
-
-            // Write data packet header
-            DataPacketHeader {
-                comp_restart_flag: false,
-                packet_length: packet_length as u64,
-                bytestream_count: proto_len as u16,
-            }
-            .write(&mut self.writer)?;
-
-            // Write bytestream sizes as u16 values
-            for size in bs_sizes {
-                let bytes = size.to_le_bytes();
-                self.writer
-                    .write_all(&bytes)
-                    .write_err("Cannot write data packet buffer size")?;
-            }
-
-            // Write actual bytestream buffers with data
-            for bs in &mut self.byte_streams {
-                let data = if last_flush {
-                    bs.get_all_bytes()
-                } else {
-                    bs.get_full_bytes()
-                };
-                self.writer
-                    .write_all(&data)
-                    .write_err("Cannot write bytestream buffer into data packet")?;
+        // Write data packet and remember its location for the index packets
+        let packet_offset = self.writer.physical_position()?;
+        let packet_length = write_data_packet(self.writer, &mut self.byte_streams, last_flush)?;
+        self.section_header.section_length += packet_length;
+        if let Some(entries) = &mut self.index_entries {
+            if record_aligned && packet_points > 0 && packet_length > 0 {
+                entries.push(IndexPacketEntry {
+                    record: first_record,
+                    offset: packet_offset,
+                });
             }
         }
 
-        self.writer
-            .align()
-            .write_err("Failed to align writer on next 4-byte offset after writing data packet")?;
-
         Ok(())
     }
 
@@ -471,6 +524,18 @@ impl<'a, T: Read + Write + Seek> PointCloudWriter<'a, T> {
             Error::invalid("Number of values does not match prototype length")?
         }
 
+        // Points cannot be split over multiple data packets, which limits the size of strings
+        if values.iter().any(|v| matches!(v, RecordValue::String(..))) {
+            let point_bytes = get_point_bits(&self.prototype, &values).div_ceil(8);
+            let max_bytes = get_max_packet_bytes(&self.prototype);
+            if point_bytes > max_bytes {
+                Error::invalid(format!(
+                    "Point with {point_bytes} bytes is too big to fit into a single data packet, \
+                     the values of a point including all strings must not exceed {max_bytes} bytes"
+                ))?
+            }
+        }
+
         // Go over all values to validate and extract min/max values
         for (i, p) in self.prototype.iter().enumerate() {
             let value = &values[i];
@@ -483,6 +548,7 @@ impl<'a, T: Read + Write + Seek> PointCloudWriter<'a, T> {
                     matches!(value, RecordValue::ScaledInteger(..))
                 }
                 RecordDataType::Integer { .. } => matches!(value, RecordValue::Integer(..)),
+                RecordDataType::String => matches!(value, RecordValue::String(..)),
             } {
                 Error::invalid(format!(
                     "Type mismatch at index {i}: value type does not match prototype"
@@ -585,6 +651,11 @@ impl<'a, T: Read + Write + Seek> PointCloudWriter<'a, T> {
             }
         }
 
+        // Collect line groups
+        if let Some(line_groups) = &mut self.line_groups {
+            line_groups.add_point(self.point_count, &values, &self.prototype)?;
+        }
+
         // Add new point to output buffer
         self.buffer.push_back(values);
         self.point_count += 1;
@@ -607,19 +678,34 @@ impl<'a, T: Read + Write + Seek> PointCloudWriter<'a, T> {
         // Flush last partial bytes from byte streams
         self.write_buffer_to_disk(true)?;
 
+        // Write index packets after all data packets
+        if let Some(entries) = self.index_entries.take() {
+            if !entries.is_empty() {
+                let max_entries = IndexPacketHeader::MAX_ENTRIES;
+                let (offset, length) = write_index_packets(self.writer, entries, max_entries)?;
+                self.section_header.index_offset = offset;
+                self.section_header.section_length += length;
+            }
+        }
+
         // We need to write the section header again with the final length
         // which was previously unknown and is now available.
-        let end_offset = self
-            .writer
-            .physical_position()
-            .write_err("Failed to get section end offset")?;
-        self.writer
-            .physical_seek(self.section_offset)
-            .write_err("Failed to seek to section start for final update")?;
-        self.section_header.write(&mut self.writer)?;
-        self.writer
-            .physical_seek(end_offset)
-            .write_err("Failed to seek behind finalized section")?;
+        update_section_header(self.writer, self.section_offset, &self.section_header)?;
+
+        // Line groups are written into their own section behind the points
+        let line_grouping = if let Some(line_groups) = self.line_groups.take() {
+            let (id_element_name, prototype, groups) = line_groups.finish();
+            let records: Vec<Record> = prototype.iter().map(GroupRecord::to_record).collect();
+            let file_offset = write_section(self.writer, &records, &groups)?;
+            Some(LineGrouping {
+                id_element_name,
+                group_count: groups.len() as u64,
+                file_offset,
+                prototype,
+            })
+        } else {
+            None
+        };
 
         // prepare point cloud metadata
         let pc = PointCloud {
@@ -647,6 +733,9 @@ impl<'a, T: Read + Write + Seek> PointCloudWriter<'a, T> {
             temperature: self.temperature.take(),
             humidity: self.humidity.take(),
             atmospheric_pressure: self.atmospheric_pressure.take(),
+            line_grouping,
+            additional_xml: std::mem::take(&mut self.additional_xml),
+            normal_names: surface_normal_names(self.extensions),
         };
 
         // Add metadata for XML generation later, when the file is completed.
@@ -656,6 +745,127 @@ impl<'a, T: Read + Write + Seek> PointCloudWriter<'a, T> {
     }
 }
 
+/// Writes a data packet with the content of the byte streams.
+/// Only full bytes are written, unless its the last flush, which will write all remaining bits.
+/// Returns the length of the written packet, which is zero if there was no data to write.
+fn write_data_packet<T: Read + Write + Seek>(
+    writer: &mut PagedWriter<T>,
+    byte_streams: &mut [ByteStreamWriteBuffer],
+    last_flush: bool,
+) -> Result<u64> {
+    // Check and prepare buffer sizes
+    let mut sum_bs_sizes = 0;
+    let mut bs_sizes = Vec::with_capacity(byte_streams.len());
+    for bs in byte_streams.iter() {
+        let bs_size = if last_flush {
+            bs.all_bytes()
+        } else {
+            bs.full_bytes()
+        };
+        sum_bs_sizes += bs_size;
+        bs_sizes.push(bs_size as u16);
+    }
+
+    // Only write packet if there is actual data!
+    if sum_bs_sizes == 0 {
+        return Ok(0);
+    }
+
+    // Calculate packet length for header, must be aligned to four bytes.
+    // If the length exceeds 2^16 this library has somewhere a logic bug!
+    let mut packet_length = DataPacketHeader::SIZE + byte_streams.len() * 2 + sum_bs_sizes;
+    if !packet_length.is_multiple_of(4) {
+        let missing = 4 - (packet_length % 4);
+        packet_length += missing;
+    }
+    if packet_length > u16::MAX as usize {
+        Error::internal("Invalid data packet length detected")?
+    }
+
+    // Write data packet header
+    DataPacketHeader {
+        comp_restart_flag: false,
+        packet_length: packet_length as u64,
+        bytestream_count: byte_streams.len() as u16,
+    }
+    .write(writer)?;
+
+    // Write bytestream sizes as u16 values
+    for size in bs_sizes {
+        let bytes = size.to_le_bytes();
+        writer
+            .write_all(&bytes)
+            .write_err("Cannot write data packet buffer size")?;
+    }
+
+    // Write actual bytestream buffers with data
+    for bs in byte_streams.iter_mut() {
+        let data = if last_flush {
+            bs.get_all_bytes()
+        } else {
+            bs.get_full_bytes()
+        };
+        writer
+            .write_all(&data)
+            .write_err("Cannot write bytestream buffer into data packet")?;
+    }
+
+    writer
+        .align()
+        .write_err("Failed to align writer on next 4-byte offset after writing data packet")?;
+
+    Ok(packet_length as u64)
+}
+
+/// Writes a complete compressed vector section with the given records.
+/// Returns the physical offset of the start of the section.
+fn write_section<T: Read + Write + Seek>(
+    writer: &mut PagedWriter<T>,
+    prototype: &[Record],
+    records: &[RawValues],
+) -> Result<u64> {
+    let section_offset = writer.physical_position()?;
+    let mut section_header = CompressedVectorSectionHeader::default();
+    section_header.section_length = CompressedVectorSectionHeader::SIZE;
+    section_header.write(writer)?;
+    section_header.data_offset = writer.physical_position()?;
+
+    let mut byte_streams = vec![ByteStreamWriteBuffer::new(); prototype.len()];
+    for chunk in records.chunks(get_max_packet_points(prototype).max(1)) {
+        for values in chunk {
+            for (i, record) in prototype.iter().enumerate() {
+                let value = values
+                    .get(i)
+                    .internal_err("Prototype is bigger than number of values")?;
+                record.data_type.write(value, &mut byte_streams[i])?;
+            }
+        }
+        section_header.section_length += write_data_packet(writer, &mut byte_streams, false)?;
+    }
+    section_header.section_length += write_data_packet(writer, &mut byte_streams, true)?;
+
+    update_section_header(writer, section_offset, &section_header)?;
+    Ok(section_offset)
+}
+
+/// Overwrites an existing section header and moves the writer back to the previous position.
+fn update_section_header<T: Read + Write + Seek>(
+    writer: &mut PagedWriter<T>,
+    section_offset: u64,
+    section_header: &CompressedVectorSectionHeader,
+) -> Result<()> {
+    let end_offset = writer
+        .physical_position()
+        .write_err("Failed to get section end offset")?;
+    writer
+        .physical_seek(section_offset)
+        .write_err("Failed to seek to section start for final update")?;
+    section_header.write(writer)?;
+    writer
+        .physical_seek(end_offset)
+        .write_err("Failed to seek behind finalized section")
+}
+
 fn update_min<T: PartialOrd>(value: T, min: &mut Option<T>) {
     if let Some(current) = min {
         if *current > value {
@@ -804,12 +1014,31 @@ fn validate_return(prototype: &[Record]) -> Result<()> {
 /// Each data packet can contain up to 2^16 bytes, but we need some reserved
 /// space for header data. We also need to consider some "incomplete" bytes
 /// from record value sizes that are not a multiple of 8 bits.
+/// Strings have no fixed size and are not considered here.
 fn get_max_packet_points(prototype: &[Record]) -> usize {
-    const SAFETY_MARGIN: usize = 500;
     let point_size_bits: usize = prototype.iter().map(|p| p.data_type.bit_size()).sum();
+    (get_max_packet_bytes(prototype) * 8) / point_size_bits.max(1)
+}
+
+/// Returns the number of bits required to store the values of a single point.
+fn get_point_bits(prototype: &[Record], values: &[RecordValue]) -> usize {
+    let fixed_bits: usize = prototype.iter().map(|r| r.data_type.bit_size()).sum();
+    let string_bytes: usize = values
+        .iter()
+        .map(|v| match v {
+            RecordValue::String(s) => serialized_string_size(s),
+            _ => 0,
+        })
+        .sum();
+    fixed_bits + string_bytes * 8
+}
+
+/// Calculate the maximum number of bytes available for point data in a single packet.
+fn get_max_packet_bytes(prototype: &[Record]) -> usize {
+    const SAFETY_MARGIN: usize = 500;
     let bs_size_headers = prototype.len() * 2; // u16 for each byte stream header
     let headers_size = DataPacketHeader::SIZE + bs_size_headers;
     let max_incomplete_bytes = prototype.len();
     let u16_max = u16::MAX as usize;
-    ((u16_max - headers_size - max_incomplete_bytes - SAFETY_MARGIN) * 8) / point_size_bits
+    u16_max - headers_size - max_incomplete_bytes - SAFETY_MARGIN
 }
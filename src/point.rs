@@ -39,8 +39,23 @@ pub struct Color {
     pub blue: f32,
 }
 
+/// Surface normal vector of a point.
+///
+/// The values are returned as stored in the file, the vector might not be normalized.
+/// If the pose of the point cloud is applied while reading, the normal is rotated accordingly.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Normal {
+    /// X component of the normal vector, read from the `normalX` record.
+    pub x: f64,
+    /// Y component of the normal vector, read from the `normalY` record.
+    pub y: f64,
+    /// Z component of the normal vector, read from the `normalZ` record.
+    pub z: f64,
+}
+
 /// Represents a high level point with its different attributes.
 #[derive(Clone, Debug)]
+#[non_exhaustive]
 pub struct Point {
     /// Cartesian coordinates.
     /// Might be always invalid if the point cloud does only contain spherical coordinates and the automatic conversion from spherical to Cartesian is disabled.
@@ -87,4 +102,32 @@ pub struct Point {
     /// have a column index or not.
     /// See also [`PointCloud::has_row_column`](crate::PointCloud::has_row_column).
     pub column: i64,
+
+    /// Time stamp of the point in seconds, relative to the acquisition start of the point cloud.
+    /// None means the whole point cloud has no time stamps or the time stamp of this individual point is invalid.
+    /// Please check the point cloud properties to understand whether the point cloud in general has time stamps or not.
+    /// See also [`PointCloud::has_timestamp`](crate::PointCloud::has_timestamp) and
+    /// [`PointCloud::gps_time`](crate::PointCloud::gps_time) to get absolute GPS times.
+    pub timestamp: Option<f64>,
+
+    /// Zero-based index of this return for multi-return sensors.
+    /// Default value for point clouds without return index will be -1.
+    /// See also [`PointCloud::has_return`](crate::PointCloud::has_return).
+    pub return_index: i64,
+
+    /// Total number of returns of the pulse that this point belongs to for multi-return sensors.
+    /// Default value for point clouds without return count will be -1.
+    /// See also [`PointCloud::has_return`](crate::PointCloud::has_return).
+    pub return_count: i64,
+
+    /// Surface normal vector from the surface normals extension.
+    /// None means the point cloud has no normal records of the surface normals extension.
+    /// See also [`PointCloud::has_normals`](crate::PointCloud::has_normals) and [Normal].
+    pub normal: Option<Normal>,
+
+    /// Values of additional attributes that were registered with
+    /// [`PointCloudReaderSimple::extension_attributes`](crate::PointCloudReaderSimple::extension_attributes).
+    /// The values are in the same order as the registered attribute names.
+    /// Empty if no additional attributes were registered.
+    pub extensions: Vec<f64>,
 }
@@ -1,8 +1,10 @@
 use crate::error::Converter;
+use crate::extension::{get_extension_data, surface_normal_names};
 use crate::xml;
 use crate::{
-    CartesianBounds, ColorLimits, DateTime, IndexBounds, IntensityLimits, Record, RecordDataType,
-    RecordName, Result, SphericalBounds, Transform,
+    CartesianBounds, ColorLimits, DateTime, Extension, ExtensionData, IndexBounds, IntensityLimits,
+    LineGrouping, Record, RecordDataType, RecordName, Result, SphericalBounds, Transform,
+    XmlElement,
 };
 use roxmltree::{Document, Node};
 
@@ -63,15 +65,56 @@ pub struct PointCloud {
     pub humidity: Option<f64>,
     /// Optional atmospheric pressure in Pascals, measured at the sensor at the time of capturing.
     pub atmospheric_pressure: Option<f64>,
+    /// Optional grouping of the points into lines, for example scan lines of a mobile mapping system.
+    pub line_grouping: Option<LineGrouping>,
+    /// Additional XML elements of the point cloud descriptor that are not defined by the E57 standard,
+    /// for example extension data or vendor specific metadata.
+    /// They are written back unchanged, which means elements referring to binary sections
+    /// of the original file, like blobs, will no longer be valid in a new file.
+    /// See [`PointCloud::extension_data`] for typed access.
+    pub additional_xml: Vec<XmlElement>,
+
+    /// Record names of the surface normals, resolved by the extension URL when reading a file.
+    pub(crate) normal_names: Option<[RecordName; 3]>,
 }
 
+/// Tag names of all point cloud descriptor elements defined by the E57 standard.
+const KNOWN_TAGS: &[&str] = &[
+    "guid",
+    "originalGuids",
+    "name",
+    "description",
+    "cartesianBounds",
+    "sphericalBounds",
+    "indexBounds",
+    "intensityLimits",
+    "colorLimits",
+    "pose",
+    "acquisitionStart",
+    "acquisitionEnd",
+    "sensorVendor",
+    "sensorModel",
+    "sensorSerialNumber",
+    "sensorHardwareVersion",
+    "sensorSoftwareVersion",
+    "sensorFirmwareVersion",
+    "temperature",
+    "relativeHumidity",
+    "atmosphericPressure",
+    "pointGroupingSchemes",
+    "points",
+];
+
 impl PointCloud {
-    pub(crate) fn vec_from_document(document: &Document) -> Result<Vec<Self>> {
+    pub(crate) fn vec_from_document(
+        document: &Document,
+        extensions: &[Extension],
+    ) -> Result<Vec<Self>> {
         let mut pointclouds = Vec::new();
         if let Some(data3d_node) = document.descendants().find(|n| n.has_tag_name("data3D")) {
             for n in data3d_node.children() {
                 if n.has_tag_name("vectorChild") && n.attribute("type") == Some("Structure") {
-                    let pointcloud = Self::from_node(&n)?;
+                    let pointcloud = Self::from_node(&n, extensions)?;
                     pointclouds.push(pointcloud);
                 }
             }
@@ -79,7 +122,7 @@ impl PointCloud {
         Ok(pointclouds)
     }
 
-    pub(crate) fn from_node(node: &Node) -> Result<Self> {
+    pub(crate) fn from_node(node: &Node, extensions: &[Extension]) -> Result<Self> {
         let guid = xml::opt_string(node, "guid")?;
         let name = xml::opt_string(node, "name")?;
         let description = xml::opt_string(node, "description")?;
@@ -100,6 +143,9 @@ impl PointCloud {
         let index_bounds = node.children().find(|n| n.has_tag_name("indexBounds"));
         let intensity_limits = node.children().find(|n| n.has_tag_name("intensityLimits"));
         let color_limits = node.children().find(|n| n.has_tag_name("colorLimits"));
+        let grouping = node
+            .children()
+            .find(|n| n.has_tag_name("pointGroupingSchemes"));
 
         // Read optional vector of original GUIDs
         let original_guids = if let Some(original_guids_node) =
@@ -151,6 +197,7 @@ impl PointCloud {
             let data_type = RecordDataType::from_node(&n)?;
             prototype.push(Record { name, data_type });
         }
+        let normal_names = surface_normal_names(extensions);
 
         Ok(Self {
             guid,
@@ -197,6 +244,13 @@ impl PointCloud {
             temperature,
             humidity,
             atmospheric_pressure,
+            line_grouping: if let Some(node) = grouping {
+                LineGrouping::from_node(&node)?
+            } else {
+                None
+            },
+            additional_xml: XmlElement::unknown_children(node, KNOWN_TAGS),
+            normal_names,
         })
     }
 
@@ -291,6 +345,13 @@ impl PointCloud {
             xml += &xml::gen_float("atmosphericPressure", pressure);
         }
 
+        if let Some(grouping) = &self.line_grouping {
+            xml += &grouping.xml_string();
+        }
+        for element in &self.additional_xml {
+            xml += &element.xml_string();
+        }
+
         xml += &format!(
             "<points type=\"CompressedVector\" fileOffset=\"{}\" recordCount=\"{}\">\n",
             self.file_offset, self.records
@@ -312,6 +373,12 @@ impl PointCloud {
             .all(|searched| self.prototype.iter().any(|actual| &actual.name == searched))
     }
 
+    /// Parses the typed extension data from the additional XML elements of the point cloud.
+    /// Returns None if the point cloud has no element for this extension data.
+    pub fn extension_data<D: ExtensionData>(&self) -> Result<Option<D>> {
+        get_extension_data(&self.additional_xml)
+    }
+
     /// Returns true if the point prototype contains X, Y and Z records for Cartesian coordinates.
     pub fn has_cartesian(&self) -> bool {
         self.contains(&[
@@ -339,6 +406,15 @@ impl PointCloud {
         ])
     }
 
+    /// Returns true if the point prototype contains X, Y and Z records for surface normals.
+    /// The records are identified by the URL of the libE57 surface normals extension,
+    /// so this is only true for point clouds read from a file that defines the extension.
+    pub fn has_normals(&self) -> bool {
+        self.normal_names
+            .as_ref()
+            .is_some_and(|names| self.contains(names))
+    }
+
     /// Returns true if the point prototype contains a intensity record.
     pub fn has_intensity(&self) -> bool {
         self.contains(&[RecordName::Intensity])
@@ -359,6 +435,15 @@ impl PointCloud {
         self.contains(&[RecordName::TimeStamp])
     }
 
+    /// Converts a point time stamp into an absolute GPS time in seconds.
+    /// Point time stamps are relative to the acquisition start of the point cloud.
+    /// Returns None if the point cloud has no acquisition start.
+    pub fn gps_time(&self, timestamp: f64) -> Option<f64> {
+        self.acquisition_start
+            .as_ref()
+            .map(|start| start.gps_time + timestamp)
+    }
+
     /// Tries to returns the Cartesian bounds of the point cloud, if possible.
     /// The bounds will be in local coordinates without the point clouds transformation pose applied.
     /// If existing, it will return the Cartesian bounds provided in the E57 file.
@@ -1,34 +1,112 @@
 use crate::bitpack::BitPack;
 use crate::bs_read::ByteStreamReadBuffer;
+use crate::columns::ColumnBuffers;
 use crate::cv_section::CompressedVectorSectionHeader;
 use crate::error::Converter;
-use crate::packet::PacketHeader;
-use crate::paged_reader::PagedReader;
+use crate::packet::{IgnoredPacketHeader, IndexPacketHeader, PacketHeader};
+use crate::packet_index::{stream_bits_behind, PacketIndex};
+use crate::paged_reader::PagedReaderRef;
 use crate::Error;
 use crate::PointCloud;
 use crate::RawValues;
+use crate::Record;
 use crate::RecordDataType;
+use crate::RecordName;
 use crate::RecordValue;
 use crate::Result;
 use std::collections::VecDeque;
 use std::io::{Read, Seek};
+use std::sync::Arc;
 
 /// Read compressed vector sections into queues of raw values.
 pub struct QueueReader<'a, T: Read + Seek> {
     pc: PointCloud,
-    reader: &'a mut PagedReader<T>,
+    reader: PagedReaderRef<'a, T>,
+    section_header: CompressedVectorSectionHeader,
+    packet_index: Option<Arc<PacketIndex>>,
     buffer: Vec<u8>,
     buffer_sizes: Vec<usize>,
     byte_streams: Vec<ByteStreamReadBuffer>,
+    skip_bits: Vec<u64>,
+    skip_values: Vec<u64>,
+    stream_bits: Vec<u64>,
+    record: u64,
+    lost: u64,
+    lost_since_seek: u64,
+    corrupt_pages_start: usize,
     queues: Vec<VecDeque<RecordValue>>,
+    decode: Vec<bool>,
+    output: Vec<bool>,
+    prototype: Vec<Record>,
 }
 
 impl<'a, T: Read + Seek> QueueReader<'a, T> {
-    pub fn new(pc: &PointCloud, reader: &'a mut PagedReader<T>) -> Result<Self> {
+    pub fn new(pc: &PointCloud, reader: impl Into<PagedReaderRef<'a, T>>) -> Result<Self> {
+        let names: Vec<RecordName> = pc.prototype.iter().map(|r| r.name.clone()).collect();
+        Self::new_projected(pc, reader, &names)
+    }
+
+    /// Creates a reader that only decodes the byte streams of the given records.
+    /// The byte streams of all other records are skipped without unpacking them.
+    pub fn new_projected(
+        pc: &PointCloud,
+        reader: impl Into<PagedReaderRef<'a, T>>,
+        names: &[RecordName],
+    ) -> Result<Self> {
+        let mut reader = reader.into();
         reader
             .seek_physical(pc.file_offset)
             .read_err("Cannot seek to compressed vector header")?;
-        let section_header = CompressedVectorSectionHeader::read(reader)?;
+        let section_header = CompressedVectorSectionHeader::read(&mut *reader)?;
+        Self::from_section_header(pc, reader, names, section_header)
+    }
+
+    /// Creates a reader for a compressed vector section with an already known section header.
+    /// Allows reading sections with incomplete headers, for example when salvaging damaged files.
+    pub fn from_section_header(
+        pc: &PointCloud,
+        mut reader: PagedReaderRef<'a, T>,
+        names: &[RecordName],
+        section_header: CompressedVectorSectionHeader,
+    ) -> Result<Self> {
+        let mut output = vec![false; pc.prototype.len()];
+        for name in names {
+            let index = pc
+                .prototype
+                .iter()
+                .position(|r| r.name == *name)
+                .invalid_err(format!("Point cloud has no record with the name {name:?}"))?;
+            if output[index] {
+                Error::invalid(format!("Record {name:?} was selected more than once"))?
+            }
+            output[index] = true;
+        }
+        let prototype = pc
+            .prototype
+            .iter()
+            .zip(&output)
+            .filter(|(_, o)| **o)
+            .map(|(r, _)| r.clone())
+            .collect();
+
+        // Records with a bit size of zero cannot tell how many points are in a packet.
+        // If only such records are selected, one additional record is decoded for counting.
+        let countable = |r: &Record| {
+            r.data_type.bit_size() > 0 || matches!(r.data_type, RecordDataType::String)
+        };
+        let mut decode = output.clone();
+        let selected_countable = pc
+            .prototype
+            .iter()
+            .zip(&output)
+            .any(|(r, o)| *o && countable(r));
+        if !selected_countable {
+            if let Some(index) = pc.prototype.iter().position(countable) {
+                decode[index] = true;
+            }
+        }
+
+        let corrupt_pages_start = reader.corrupt_pages().len();
         reader
             .seek_physical(section_header.data_offset)
             .read_err("Cannot seek to packet header")?;
@@ -36,13 +114,163 @@ impl<'a, T: Read + Seek> QueueReader<'a, T> {
         Ok(Self {
             pc: pc.clone(),
             reader,
+            section_header,
+            packet_index: None,
             buffer: Vec::new(),
             buffer_sizes: vec![0; pc.prototype.len()],
             byte_streams: vec![ByteStreamReadBuffer::new(); pc.prototype.len()],
+            skip_bits: vec![0; pc.prototype.len()],
+            skip_values: vec![0; pc.prototype.len()],
+            stream_bits: vec![0; pc.prototype.len()],
+            record: 0,
+            lost: 0,
+            lost_since_seek: 0,
+            corrupt_pages_start,
             queues: vec![VecDeque::new(); pc.prototype.len()],
+            decode,
+            output,
+            prototype,
         })
     }
 
+    /// Moves the reader to the data packet containing the given record.
+    /// All queued values are discarded and the next popped point will be the requested record.
+    /// The packet lookup table is created on the first call and reused afterwards.
+    pub fn seek(&mut self, record: u64) -> Result<()> {
+        if self.packet_index.is_none() {
+            let index = PacketIndex::new(&mut self.reader, &self.pc, &self.section_header)?;
+            self.packet_index = Some(Arc::new(index));
+        }
+        let mut index = self
+            .packet_index
+            .clone()
+            .internal_err("Packet index is not initialized")?;
+        let valid = match index.find(record) {
+            Some(location) => index.verify(&mut self.reader, location)?,
+            None => true,
+        };
+        if !valid {
+            // Index packets do not match the data packets, fall back to scanning all packet headers
+            index = Arc::new(PacketIndex::scan(
+                &mut self.reader,
+                &self.pc,
+                &self.section_header,
+            )?);
+            self.packet_index = Some(index.clone());
+        }
+
+        let target_bits = index.record_bits(record);
+        let (offset, packet_bits, packet_record) = match index.find(record) {
+            Some(location) => (
+                location.offset,
+                location.stream_bits.as_slice(),
+                location.record.unwrap_or(0),
+            ),
+            None => (self.section_header.data_offset, &[][..], 0),
+        };
+        for (i, target) in target_bits.iter().enumerate() {
+            let before = packet_bits.get(i).copied().unwrap_or(0);
+            self.stream_bits[i] = before;
+            // Strings have no fixed bit size and are skipped value by value instead
+            (self.skip_bits[i], self.skip_values[i]) = match self.pc.prototype[i].data_type {
+                RecordDataType::String => (0, record - packet_record),
+                _ => (target - before, 0),
+            };
+            self.byte_streams[i] = ByteStreamReadBuffer::new();
+            self.queues[i].clear();
+        }
+        self.record = record;
+        self.lost_since_seek = 0;
+
+        self.reader
+            .seek_physical(offset)
+            .read_err("Cannot seek to packet header")?;
+        Ok(())
+    }
+
+    /// Skips all points in front of the given record without decoding them.
+    /// With index packets the reader seeks directly to the data packet containing the record,
+    /// if it is located behind the next data packet.
+    /// Otherwise the values of the skipped points are dropped from the queues and byte streams.
+    pub fn skip_to(&mut self, record: u64) -> Result<()> {
+        if record <= self.record {
+            return Ok(());
+        }
+        if self.has_index_packets() && record < self.pc.records {
+            let next_packet = self.reader.physical_position();
+            if self.packet_index.is_none() {
+                let index = PacketIndex::new(&mut self.reader, &self.pc, &self.section_header)?;
+                self.packet_index = Some(Arc::new(index));
+                self.reader
+                    .seek_physical(next_packet)
+                    .read_err("Cannot seek to packet header")?;
+            }
+            let behind_next_packet = self
+                .packet_index
+                .as_ref()
+                .and_then(|index| index.find(record))
+                .is_some_and(|location| location.offset > next_packet);
+            if behind_next_packet {
+                return self.seek(record);
+            }
+        }
+
+        let count = record - self.record;
+        for (i, r) in self.pc.prototype.iter().enumerate() {
+            if !self.decode[i] {
+                continue;
+            }
+            let queued = count.min(self.queues[i].len() as u64);
+            self.queues[i].drain(..queued as usize);
+            let missing = count - queued;
+            match r.data_type {
+                RecordDataType::String => self.skip_values[i] += missing,
+                _ => {
+                    let bits = missing * r.data_type.bit_size() as u64;
+                    let skipped = self.byte_streams[i].skip(bits);
+                    self.skip_bits[i] += bits - skipped;
+                }
+            }
+        }
+        self.record = record;
+        Ok(())
+    }
+
+    /// Uses an existing packet lookup table for seeking instead of creating a new one.
+    /// The lookup table must belong to the compressed vector section of this reader.
+    pub fn set_packet_index(&mut self, index: Arc<PacketIndex>) {
+        self.packet_index = Some(index);
+    }
+
+    /// Returns the number of points that were lost because of corrupt pages.
+    pub fn lost_points(&self) -> u64 {
+        self.lost
+    }
+
+    /// Returns the number of points that were lost because of corrupt pages since the last seek.
+    pub fn lost_points_since_seek(&self) -> u64 {
+        self.lost_since_seek
+    }
+
+    /// Returns the numbers of all pages with invalid checksums that were found while reading.
+    /// Only pages found in recovery mode are reported, otherwise invalid pages are an error.
+    pub fn corrupt_pages(&self) -> &[u64] {
+        self.reader
+            .corrupt_pages()
+            .get(self.corrupt_pages_start..)
+            .unwrap_or_default()
+    }
+
+    /// Returns the index of the point that will be returned by the next pop.
+    pub fn next_record(&self) -> u64 {
+        self.record
+    }
+
+    /// Returns true if the point cloud has index packets to locate data packets.
+    pub fn has_index_packets(&self) -> bool {
+        self.section_header.index_offset != 0
+    }
+
     /// Returns the number of complete and available points across all queues.
     pub fn available(&self) -> usize {
         if self.queues.is_empty() {
@@ -50,7 +278,7 @@ impl<'a, T: Read + Seek> QueueReader<'a, T> {
         }
 
         let mut av = usize::MAX;
-        for q in &self.queues {
+        for (q, _) in self.queues.iter().zip(&self.decode).filter(|(_, d)| **d) {
             let len = q.len();
             if len < av {
                 av = len;
@@ -60,35 +288,104 @@ impl<'a, T: Read + Seek> QueueReader<'a, T> {
     }
 
     /// Return values for the next point by popping one value from each queue.
+    /// Only values of selected records are returned, values of additionally decoded records are dropped.
     /// Use an existing vector with enough capacity to avoid frequent reallocations!
     pub fn pop_point(&mut self, output: &mut RawValues) -> Result<()> {
         output.clear();
         for i in 0..self.pc.prototype.len() {
+            if !self.decode[i] {
+                continue;
+            }
             let value = self.queues[i]
                 .pop_front()
                 .internal_err("Failed to pop value for next point")?;
-            output.push(value);
+            if self.output[i] {
+                output.push(value);
+            }
+        }
+        self.record += 1;
+        Ok(())
+    }
+
+    /// Moves the values of the given number of points from the queues into the column buffers.
+    pub fn pop_columns(&mut self, count: usize, output: &mut ColumnBuffers) -> Result<()> {
+        output.reserve(count);
+        let mut column_index = 0;
+        for (i, queue) in self.queues.iter_mut().enumerate() {
+            if !self.decode[i] {
+                continue;
+            }
+            if queue.len() < count {
+                Error::internal("Not enough values in queue for requested number of points")?
+            }
+            if !self.output[i] {
+                queue.drain(..count);
+                continue;
+            }
+            let data_type = &self.pc.prototype[i].data_type;
+            let column = output.column_mut(column_index)?;
+            for value in queue.drain(..count) {
+                column.push(value, data_type)?;
+            }
+            column_index += 1;
         }
+        self.record += count as u64;
         Ok(())
     }
 
+    /// Moves the values of the given number of points from the queues into a new set of queues.
+    /// Returns one queue for each record selected for output.
+    pub fn pop_queues(&mut self, count: usize) -> Result<Vec<VecDeque<RecordValue>>> {
+        let mut output = Vec::with_capacity(self.prototype.len());
+        for (i, queue) in self.queues.iter_mut().enumerate() {
+            if !self.decode[i] {
+                continue;
+            }
+            if queue.len() < count {
+                Error::internal("Not enough values in queue for requested number of points")?
+            }
+            let values: VecDeque<RecordValue> = queue.drain(..count).collect();
+            if self.output[i] {
+                output.push(values);
+            }
+        }
+        self.record += count as u64;
+        Ok(output)
+    }
+
+    /// Returns the prototype records of the values returned by this reader.
+    pub fn prototype(&self) -> &[Record] {
+        &self.prototype
+    }
+
     /// Reads the next packet from the compressed vector and decodes it into the queues.
     pub fn advance(&mut self) -> Result<()> {
-        let packet_header = PacketHeader::read(self.reader)?;
-        match packet_header {
+        let packet_offset = self.reader.physical_position();
+        let corrupt_reads = self.reader.corrupt_reads();
+        let packet_header = PacketHeader::read(&mut *self.reader);
+        if self.reader.corrupt_reads() != corrupt_reads {
+            return self.recover_from_corrupt_header(packet_offset);
+        }
+        match packet_header? {
             PacketHeader::Index(header) => {
                 // Just skip over index packets
-                let mut buffer = vec![0; header.packet_length as usize];
+                let remaining = header
+                    .packet_length
+                    .checked_sub(IndexPacketHeader::SIZE as u64)
+                    .invalid_err("Index packet length is smaller than its header")?;
                 self.reader
-                    .read_exact(&mut buffer)
-                    .read_err("Failed to read data of index packet")?
+                    .skip(remaining)
+                    .read_err("Failed to skip data of index packet")?
             }
             PacketHeader::Ignored(header) => {
                 // Just skip over ignored packets
-                let mut buffer = vec![0; header.packet_length as usize];
+                let remaining = header
+                    .packet_length
+                    .checked_sub(IgnoredPacketHeader::SIZE as u64)
+                    .invalid_err("Ignored packet length is smaller than its header")?;
                 self.reader
-                    .read_exact(&mut buffer)
-                    .read_err("Failed to read data of ignored packet")?
+                    .skip(remaining)
+                    .read_err("Failed to skip data of ignored packet")?
             }
             PacketHeader::Data(header) => {
                 if header.bytestream_count as usize != self.byte_streams.len() {
@@ -104,14 +401,49 @@ impl<'a, T: Read + Seek> QueueReader<'a, T> {
                     let len = u16::from_le_bytes(buf) as usize;
                     self.buffer_sizes[i] = len;
                 }
+                if self.reader.corrupt_reads() != corrupt_reads {
+                    return self.recover_from_corrupt_header(packet_offset);
+                }
 
                 // Read byte streams into memory
                 for (i, bs) in self.buffer_sizes.iter().enumerate() {
+                    self.stream_bits[i] += *bs as u64 * 8;
+
+                    // Skip streams of records that are not decoded
+                    if !self.decode[i] {
+                        self.reader
+                            .skip(*bs as u64)
+                            .read_err("Failed to skip data packet buffer")?;
+                        continue;
+                    }
+
                     self.buffer.resize(*bs, 0_u8);
                     self.reader
                         .read_exact(&mut self.buffer)
                         .read_err("Failed to read data packet buffers")?;
                     self.byte_streams[i].append(&self.buffer);
+
+                    // Drop bits that belong to records before a seek target
+                    if self.skip_bits[i] > 0 {
+                        self.skip_bits[i] -= self.byte_streams[i].skip(self.skip_bits[i]);
+                    }
+
+                    // Strings are unpacked right away because their number
+                    // cannot be derived from the available bits in the stream
+                    if matches!(self.pc.prototype[i].data_type, RecordDataType::String) {
+                        BitPack::unpack_strings(&mut self.byte_streams[i], &mut self.queues[i])?;
+                        while self.skip_values[i] > 0 && self.queues[i].pop_front().is_some() {
+                            self.skip_values[i] -= 1;
+                        }
+                    }
+                }
+
+                if self.reader.corrupt_reads() != corrupt_reads {
+                    self.recover_from_corrupt_data()?;
+                    return self
+                        .reader
+                        .align()
+                        .read_err("Failed to align reader after corrupt data packet");
                 }
 
                 // Find smallest number of expected items in any queue after stream unpacking.
@@ -120,9 +452,20 @@ impl<'a, T: Read + Seek> QueueReader<'a, T> {
                 // This happens for example with integer values where min=max, because all values are equal.
                 let mut min_queue_size = usize::MAX;
                 for (i, bs) in self.byte_streams.iter().enumerate() {
-                    let bit_size = self.pc.prototype[i].data_type.bit_size();
+                    if !self.decode[i] {
+                        continue;
+                    }
+                    let data_type = &self.pc.prototype[i].data_type;
+                    if matches!(data_type, RecordDataType::String) {
+                        // Strings were already unpacked into the queue
+                        let items = self.queues[i].len();
+                        if items < min_queue_size {
+                            min_queue_size = items;
+                        }
+                        continue;
+                    }
                     // We can only check records with a non-zero bit size
-                    if let Some(bs_items) = bs.available().checked_div(bit_size) {
+                    if let Some(bs_items) = bs.available().checked_div(data_type.bit_size()) {
                         let queue_items = self.queues[i].len();
                         let items = bs_items + queue_items;
                         if items < min_queue_size {
@@ -140,9 +483,117 @@ impl<'a, T: Read + Seek> QueueReader<'a, T> {
             .read_err("Failed to align reader on next 4-byte offset after reading packet")
     }
 
+    /// Drops the content of a data packet that contains corrupt data.
+    /// Reading continues with the first record that is completely located behind the packet.
+    fn recover_from_corrupt_data(&mut self) -> Result<()> {
+        let stream_bits = self.stream_bits.clone();
+        let resume = self.first_complete_record(&stream_bits)?;
+        self.resume_at(resume, &stream_bits)
+    }
+
+    /// Continues reading at the next intact data packet behind a corrupt packet header.
+    /// The packet boundaries are unknown, so the following pages are scanned for a valid data packet header
+    /// that starts a chain of valid packets up to the end of the section.
+    /// Since the byte streams of all records end with the last data packet, the stream positions
+    /// of the found packet can be calculated from the sizes of the byte streams behind it.
+    fn recover_from_corrupt_header(&mut self, packet_offset: u64) -> Result<()> {
+        let section_end = self
+            .reader
+            .seek_physical(self.pc.file_offset)
+            .read_err("Cannot seek to compressed vector header")?
+            + self.section_header.section_length;
+        let total_bits: Vec<u64> = self
+            .pc
+            .prototype
+            .iter()
+            .map(|r| (r.data_type.bit_size() as u64 * self.pc.records).div_ceil(8) * 8)
+            .collect();
+
+        let page_size = self.reader.page_size();
+        let mut offset = (packet_offset / page_size + 1) * page_size;
+        while let Ok(logical_offset) = self.reader.seek_physical(offset) {
+            if logical_offset >= section_end {
+                break;
+            }
+            let behind =
+                stream_bits_behind(&mut self.reader, &self.pc, &self.section_header, offset);
+            let before: Option<Vec<u64>> = behind.and_then(|behind| {
+                total_bits
+                    .iter()
+                    .zip(behind)
+                    .zip(&self.stream_bits)
+                    .map(|((total, behind), current)| {
+                        total.checked_sub(behind).filter(|bits| bits >= current)
+                    })
+                    .collect()
+            });
+            if let Some(before) = before {
+                let resume = self.first_complete_record(&before)?;
+                self.reader
+                    .seek_physical(offset)
+                    .read_err("Cannot seek to packet header")?;
+                return self.resume_at(resume, &before);
+            }
+
+            // Packets start at 4-byte aligned offsets and never inside the checksum of a page
+            offset += 4;
+            if offset % page_size == page_size - 4 {
+                offset += 4;
+            }
+        }
+
+        // All remaining points are lost
+        let stream_bits = self.stream_bits.clone();
+        self.resume_at(self.pc.records, &stream_bits)
+    }
+
+    /// Returns the first record that is completely located behind the given stream positions.
+    fn first_complete_record(&self, stream_bits: &[u64]) -> Result<u64> {
+        self.pc
+            .prototype
+            .iter()
+            .enumerate()
+            .filter(|(i, r)| self.decode[*i] && r.data_type.bit_size() > 0)
+            .map(|(i, r)| stream_bits[i].div_ceil(r.data_type.bit_size() as u64))
+            .max()
+            .invalid_err("Cannot recover from corrupt data without records of fixed size")
+    }
+
+    /// Discards all queued values of incomplete points and continues reading at the given record.
+    /// The stream bits define the stream positions of the next packet that will be read.
+    fn resume_at(&mut self, record: u64, stream_bits: &[u64]) -> Result<()> {
+        if self
+            .pc
+            .prototype
+            .iter()
+            .any(|r| matches!(r.data_type, RecordDataType::String))
+        {
+            Error::invalid("Cannot recover from corrupt data with string records")?
+        }
+
+        // Queued values belong to the records directly behind all previously lost points
+        let available = self.available();
+        let first_lost = self.record + self.lost_since_seek + available as u64;
+        let resume = record.max(first_lost).min(self.pc.records);
+        for (i, r) in self.pc.prototype.iter().enumerate() {
+            let bit_size = r.data_type.bit_size() as u64;
+            self.queues[i].truncate(available);
+            self.byte_streams[i] = ByteStreamReadBuffer::new();
+            self.skip_bits[i] = (resume * bit_size).saturating_sub(stream_bits[i]);
+            self.stream_bits[i] = stream_bits[i];
+        }
+        let lost = resume.saturating_sub(first_lost);
+        self.lost += lost;
+        self.lost_since_seek += lost;
+        Ok(())
+    }
+
     /// Extracts raw values from byte streams into queues.
     fn parse_byte_streams(&mut self, min_queue_size: usize) -> Result<()> {
         for (i, r) in self.pc.prototype.iter().enumerate() {
+            if !self.decode[i] {
+                continue;
+            }
             match r.data_type {
                 RecordDataType::Single { .. } => {
                     BitPack::unpack_singles(&mut self.byte_streams[i], &mut self.queues[i])?
@@ -184,9 +635,85 @@ impl<'a, T: Read + Seek> QueueReader<'a, T> {
                         )?
                     }
                 }
+                RecordDataType::String => {
+                    // Strings are already unpacked when reading the data packet
+                }
             };
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paged_reader::PagedReader;
+    use crate::{E57Reader, E57ReaderOptions, E57Writer};
+    use std::fs::{read, remove_file};
+    use std::io::Cursor;
+    use std::path::Path;
+
+    #[test]
+    fn recover_from_corrupt_packet_header() {
+        let path = Path::new("recover_from_corrupt_packet_header.e57");
+        let data_type = RecordDataType::Integer { min: 0, max: 1000 };
+        let prototype: Vec<Record> = [
+            RecordName::CartesianX,
+            RecordName::CartesianY,
+            RecordName::CartesianZ,
+        ]
+        .into_iter()
+        .map(|name| Record {
+            name,
+            data_type: data_type.clone(),
+        })
+        .collect();
+        let count = 100_000;
+        let value = |i: u64| RecordValue::Integer((i % 1001) as i64);
+
+        for index_packets in [false, true] {
+            {
+                let mut writer = E57Writer::from_file(path, "file_guid").unwrap();
+                let mut pc_writer = writer.add_pointcloud("pc_guid", prototype.clone()).unwrap();
+                pc_writer.write_index_packets(index_packets);
+                for i in 0..count {
+                    pc_writer.add_point(vec![value(i); 3]).unwrap();
+                }
+                pc_writer.finalize().unwrap();
+                writer.finalize().unwrap();
+            }
+
+            // Break the header of a data packet in the middle of the point cloud
+            let pc = E57Reader::from_file(path).unwrap().pointclouds().remove(0);
+            let mut data = read(path).unwrap();
+            let mut reader = PagedReader::new(Cursor::new(&data), 1024).unwrap();
+            reader.seek_physical(pc.file_offset).unwrap();
+            let section_header = CompressedVectorSectionHeader::read(&mut reader).unwrap();
+            let index = PacketIndex::new(&mut reader, &pc, &section_header).unwrap();
+            let offset = index.find(count / 2).unwrap().offset;
+            data[offset as usize] ^= 0xFF;
+
+            let options = E57ReaderOptions::new().recover_corrupt_pages(true);
+            let mut e57 = E57Reader::with_options(Cursor::new(&data), options).unwrap();
+            let mut iter = e57.pointcloud_raw(&pc).unwrap();
+            let points: Vec<RawValues> = iter.by_ref().collect::<Result<_>>().unwrap();
+            let lost = iter.lost_points();
+            assert_eq!(iter.corrupt_pages(), &[offset / 1024]);
+            assert!(lost > 0);
+            assert_eq!(points.len() as u64 + lost, count);
+
+            // All points before and after the lost points are intact
+            let first_lost = (0..points.len())
+                .find(|i| points[*i] != vec![value(*i as u64); 3])
+                .unwrap() as u64;
+            for (i, point) in points.iter().enumerate() {
+                let i = i as u64;
+                let expected = if i < first_lost { i } else { i + lost };
+                assert_eq!(*point, vec![value(expected); 3]);
+            }
+        }
+
+        remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,103 @@
+/// Defines which pages of an E57 file are checked for CRC errors when reading.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrcValidation {
+    /// Checks the CRC of every page that is read.
+    Always,
+    /// Does not check any CRCs, corrupt data might be returned without error.
+    Never,
+    /// Checks only every n-th page, starting with the first page.
+    /// Zero is treated like one.
+    Sampled(u64),
+}
+
+impl CrcValidation {
+    pub(crate) fn check_page(&self, page: u64) -> bool {
+        match self {
+            CrcValidation::Always => true,
+            CrcValidation::Never => false,
+            CrcValidation::Sampled(n) => page.is_multiple_of((*n).max(1)),
+        }
+    }
+}
+
+/// Options to configure how E57 files are read.
+///
+/// The default options are the same as used by [`E57Reader::new`](crate::E57Reader::new).
+/// Use [`E57Reader::with_options`](crate::E57Reader::with_options) or
+/// [`E57Reader::from_file_with_options`](crate::E57Reader::from_file_with_options)
+/// to read files with custom options.
+#[derive(Clone, Debug)]
+pub struct E57ReaderOptions {
+    pub(crate) crc_validation: CrcValidation,
+    pub(crate) max_xml_size: usize,
+    pub(crate) buffer_size: usize,
+    pub(crate) max_page_size: u64,
+    pub(crate) recover_corrupt_pages: bool,
+}
+
+impl E57ReaderOptions {
+    /// Creates a new set of options with the default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets which pages are checked for CRC errors.
+    /// Default setting is to check all pages.
+    pub fn crc_validation(mut self, value: CrcValidation) -> Self {
+        self.crc_validation = value;
+        self
+    }
+
+    /// Sets the maximum size of the XML section in bytes.
+    /// Files with a bigger XML section are rejected to avoid excessive memory usage.
+    /// Default setting is 50 MB.
+    pub fn max_xml_size(mut self, value: usize) -> Self {
+        self.max_xml_size = value;
+        self
+    }
+
+    /// Sets the size of the I/O buffer in bytes when reading from files.
+    /// Only used when opening files with a path.
+    /// Default setting is 256 KB.
+    pub fn buffer_size(mut self, value: usize) -> Self {
+        self.buffer_size = value;
+        self
+    }
+
+    /// Sets the maximum page size in bytes.
+    /// Files with a bigger page size in their header are rejected.
+    /// Readers allocate a page buffer with the page size of the file,
+    /// so this limits the memory used by such buffers for untrusted files.
+    /// Default setting is 1 MB.
+    pub fn max_page_size(mut self, value: u64) -> Self {
+        self.max_page_size = value;
+        self
+    }
+
+    /// Enables the recovery mode for pages with invalid checksums.
+    /// Instead of failing with an error, point cloud readers skip all points
+    /// that are affected by corrupt pages and continue with the next intact data.
+    /// Use [`PointCloudReaderRaw::lost_points`](crate::PointCloudReaderRaw::lost_points)
+    /// and [`PointCloudReaderRaw::corrupt_pages`](crate::PointCloudReaderRaw::corrupt_pages)
+    /// to find out what was skipped.
+    /// The XML section and other metadata must still be intact.
+    /// Point clouds with string records cannot be recovered,
+    /// because the number of lost strings is unknown. Reading them fails at the first corrupt page.
+    /// Default setting is disabled.
+    pub fn recover_corrupt_pages(mut self, value: bool) -> Self {
+        self.recover_corrupt_pages = value;
+        self
+    }
+}
+
+impl Default for E57ReaderOptions {
+    fn default() -> Self {
+        Self {
+            crc_validation: CrcValidation::Always,
+            max_xml_size: 1024 * 1024 * 50,
+            buffer_size: 256 * 1024,
+            max_page_size: 1024 * 1024,
+            recover_corrupt_pages: false,
+        }
+    }
+}
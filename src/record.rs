@@ -1,6 +1,6 @@
 use crate::bs_write::ByteStreamWriteBuffer;
 use crate::error::Converter;
-use crate::{Error, Result};
+use crate::{Error, Extension, Result};
 use roxmltree::Node;
 use std::error::Error as StdError;
 use std::fmt::{Debug, Display};
@@ -29,6 +29,10 @@ pub enum RecordDataType {
     },
     /// Signed 64-bit integer value.
     Integer { min: i64, max: i64 },
+    /// UTF-8 encoded string with variable length.
+    /// Points are never split over multiple data packets when writing,
+    /// so all values of a point including its strings must fit into a single packet of about 64 KB.
+    String,
 }
 
 /// Used to describe the prototype records with all attributes that exist in the point cloud.
@@ -108,6 +112,7 @@ pub enum RecordValue {
     Double(f64),
     ScaledInteger(i64),
     Integer(i64),
+    String(String),
 }
 
 impl Record {
@@ -118,8 +123,7 @@ impl Record {
             .map(|n| n.to_owned() + ":")
             .unwrap_or("".to_owned());
         let tag_name = self.name.tag_name();
-        let (attrs, value) = serialize_record_type(&self.data_type);
-        format!("<{namespace}{tag_name} {attrs}>{value}</{namespace}{tag_name}>\n")
+        self.data_type.xml_string(&format!("{namespace}{tag_name}"))
     }
 }
 
@@ -157,6 +161,14 @@ impl RecordName {
         }
     }
 
+    /// Creates the name of a surface normal record with the default namespace of the extension.
+    pub(crate) fn surface_normal(name: &str) -> Self {
+        RecordName::Unknown {
+            namespace: Extension::SURFACE_NORMALS_NAMESPACE.to_owned(),
+            name: name.to_owned(),
+        }
+    }
+
     pub(crate) fn from_namespace_and_tag_name(
         namespace: Option<&str>,
         tag_name: &str,
@@ -245,6 +257,7 @@ impl RecordDataType {
                     offset,
                 }
             }
+            "String" => RecordDataType::String,
             _ => Error::not_implemented(format!(
                 "Unsupported type '{type_name}' in XML tag '{tag_name}' detected"
             ))?,
@@ -257,6 +270,8 @@ impl RecordDataType {
             RecordDataType::Double { .. } => std::mem::size_of::<f64>() * 8,
             RecordDataType::ScaledInteger { min, max, .. } => integer_bits(*min, *max),
             RecordDataType::Integer { min, max } => integer_bits(*min, *max),
+            // Strings have a variable size and are always a multiple of full bytes
+            RecordDataType::String => 0,
         }
     }
 
@@ -296,10 +311,22 @@ impl RecordDataType {
                     Error::invalid("Data type integer only supports integer values")?
                 }
             }
+            RecordDataType::String => {
+                if let RecordValue::String(string) = value {
+                    serialize_string(string, buffer);
+                } else {
+                    Error::invalid("Data type string only supports string values")?
+                }
+            }
         };
         Ok(())
     }
 
+    pub(crate) fn xml_string(&self, tag_name: &str) -> String {
+        let (attrs, value) = serialize_record_type(self);
+        format!("<{tag_name} {attrs}>{value}</{tag_name}>\n")
+    }
+
     pub(crate) fn limits(&self) -> (Option<RecordValue>, Option<RecordValue>) {
         match self {
             RecordDataType::Single { min, max } => {
@@ -316,6 +343,7 @@ impl RecordDataType {
                 Some(RecordValue::Integer(*min)),
                 Some(RecordValue::Integer(*max)),
             ),
+            RecordDataType::String => (None, None),
         }
     }
 }
@@ -333,6 +361,9 @@ impl RecordValue {
                 }
             }
             RecordValue::Integer(i) => Ok(*i as f64),
+            RecordValue::String(_) => {
+                Error::internal("Tried to convert string value to floating point number")
+            }
         }
     }
 
@@ -378,6 +409,7 @@ impl Display for RecordValue {
             RecordValue::Double(v) => write!(f, "{v}"),
             RecordValue::ScaledInteger(v) => write!(f, "{v}"),
             RecordValue::Integer(v) => write!(f, "{v}"),
+            RecordValue::String(v) => write!(f, "{v}"),
         }
     }
 }
@@ -390,6 +422,30 @@ fn serialize_integer(value: i64, min: i64, max: i64, buffer: &mut ByteStreamWrit
     buffer.add_bits(&data, bits);
 }
 
+/// Strings are stored as UTF-8 bytes with a length prefix.
+/// Short strings with less than 128 bytes have a single byte prefix with the length shifted by one bit.
+/// Longer strings have an eight byte prefix with the shifted length and the lowest bit set.
+pub(crate) fn serialize_string(value: &str, buffer: &mut ByteStreamWriteBuffer) {
+    let bytes = value.as_bytes();
+    if bytes.len() < 128 {
+        buffer.add_bytes(&[(bytes.len() as u8) << 1]);
+    } else {
+        let prefix = ((bytes.len() as u64) << 1) | 1;
+        buffer.add_bytes(&prefix.to_le_bytes());
+    }
+    buffer.add_bytes(bytes);
+}
+
+/// Returns the number of bytes required to serialize a string value.
+pub(crate) fn serialized_string_size(value: &str) -> usize {
+    let len = value.len();
+    if len < 128 {
+        len + 1
+    } else {
+        len + 8
+    }
+}
+
 #[inline]
 fn integer_bits(min: i64, max: i64) -> usize {
     let range = max as i128 - min as i128;
@@ -454,6 +510,7 @@ fn serialize_record_type(rt: &RecordDataType) -> (String, String) {
             format!("type=\"Integer\" minimum=\"{min}\" maximum=\"{max}\""),
             min.to_string(),
         ),
+        RecordDataType::String => (String::from("type=\"String\""), String::new()),
     }
 }
 
@@ -482,6 +539,12 @@ impl RecordDataType {
         min: 0,
         max: u16::MAX as i64,
     };
+
+    /// Single precision floating point type for the components of unit surface normals.
+    pub const NORMAL_F32: RecordDataType = RecordDataType::Single {
+        min: Some(-1.0),
+        max: Some(1.0),
+    };
 }
 
 impl Record {
@@ -594,4 +657,29 @@ impl Record {
         name: RecordName::Intensity,
         data_type: RecordDataType::UNIT_F32,
     };
+
+    /// Returns a record for the X component of surface normals.
+    /// The record uses the namespace of the surface normals extension, see [`Extension::surface_normals`].
+    pub fn normal_x_f32() -> Record {
+        Self::surface_normal("normalX")
+    }
+
+    /// Returns a record for the Y component of surface normals.
+    /// The record uses the namespace of the surface normals extension, see [`Extension::surface_normals`].
+    pub fn normal_y_f32() -> Record {
+        Self::surface_normal("normalY")
+    }
+
+    /// Returns a record for the Z component of surface normals.
+    /// The record uses the namespace of the surface normals extension, see [`Extension::surface_normals`].
+    pub fn normal_z_f32() -> Record {
+        Self::surface_normal("normalZ")
+    }
+
+    fn surface_normal(name: &str) -> Record {
+        Record {
+            name: RecordName::surface_normal(name),
+            data_type: RecordDataType::NORMAL_F32,
+        }
+    }
 }
@@ -1,6 +1,6 @@
 use crate::error::Converter;
 use crate::Extension;
-use crate::{xml, DateTime, Error, Image, PointCloud, Result};
+use crate::{xml, DateTime, Error, Image, PointCloud, Result, XmlElement};
 use roxmltree::Document;
 
 /// E57 XML Root structure with information shared by all elements in the file.
@@ -14,6 +14,7 @@ pub struct Root {
     pub library_version: Option<String>,
     pub creation: Option<DateTime>,
     pub coordinate_metadata: Option<String>,
+    pub additional_xml: Vec<XmlElement>,
 }
 
 impl Default for Root {
@@ -26,10 +27,24 @@ impl Default for Root {
             creation: None,
             coordinate_metadata: None,
             library_version: None,
+            additional_xml: Vec::new(),
         }
     }
 }
 
+/// Tag names of all root elements defined by the E57 standard.
+const KNOWN_TAGS: &[&str] = &[
+    "formatName",
+    "guid",
+    "versionMajor",
+    "versionMinor",
+    "e57LibraryVersion",
+    "creationDateTime",
+    "coordinateMetadata",
+    "data3D",
+    "images2D",
+];
+
 pub fn root_from_document(document: &Document) -> Result<Root> {
     let root = document
         .descendants()
@@ -55,6 +70,7 @@ pub fn root_from_document(document: &Document) -> Result<Root> {
         minor_version,
         coordinate_metadata,
         library_version,
+        additional_xml: XmlElement::unknown_children(&root, KNOWN_TAGS),
     })
 }
 
@@ -64,6 +80,8 @@ pub fn serialize_root(
     images: &[Image],
     extensions: &[Extension],
 ) -> Result<String> {
+    check_namespaces(root, pointclouds, images, extensions)?;
+
     let mut xml = String::new();
     xml += "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
     xml += "<e57Root type=\"Structure\" ";
@@ -105,6 +123,27 @@ pub fn serialize_root(
         xml += &img.xml_string();
     }
     xml += "</images2D>\n";
+    for element in &root.additional_xml {
+        xml += &element.xml_string();
+    }
     xml += "</e57Root>\n";
     Ok(xml)
 }
+
+/// Makes sure that all namespace prefixes of the additional XML elements are declared.
+fn check_namespaces(
+    root: &Root,
+    pointclouds: &[PointCloud],
+    images: &[Image],
+    extensions: &[Extension],
+) -> Result<()> {
+    let elements = root
+        .additional_xml
+        .iter()
+        .chain(pointclouds.iter().flat_map(|pc| &pc.additional_xml))
+        .chain(images.iter().flat_map(|img| &img.additional_xml));
+    for element in elements {
+        element.check_namespaces(extensions)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,597 @@
+use crate::cv_section::CompressedVectorSectionHeader;
+use crate::error::Converter;
+use crate::packet::PacketHeader;
+use crate::paged_reader::{PagedReader, PagedReaderRef};
+use crate::queue_reader::QueueReader;
+use crate::{
+    Blob, E57Writer, Error, ImageFormat, PointCloud, PointCloudReaderRaw, Record, RecordName,
+    Result, VisualReferenceImageProperties,
+};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, Write};
+use std::path::Path;
+
+const DEFAULT_PAGE_SIZE: u64 = 1024;
+const MAX_PAGE_SIZE: u64 = 1024 * 1024;
+const FILE_HEADER_SIZE: u64 = 48;
+const BLOB_HEADER_SIZE: u64 = 16;
+const SAMPLE_SIZE: usize = 64;
+
+/// Compressed vector section with point data that was found in a damaged E57 file.
+///
+/// Since the XML section is missing, the prototype of the points is unknown.
+/// Only the number of byte streams and their sizes can be derived from the data packets.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct SalvagedPointCloud {
+    /// Physical file offset of the compressed vector section.
+    pub offset: u64,
+    /// Physical file offset of the first data packet.
+    pub data_offset: u64,
+    /// Logical length of the section up to the end of the last intact packet.
+    pub length: u64,
+    /// Number of intact data packets.
+    pub packets: u64,
+    /// Number of bytes found in each byte stream.
+    pub stream_sizes: Vec<u64>,
+    /// First bytes of the first byte stream used for guessing the prototype.
+    sample: Vec<u8>,
+}
+
+impl SalvagedPointCloud {
+    /// Returns the number of complete points stored in the byte streams for the given prototype.
+    pub fn records(&self, prototype: &[Record]) -> Result<u64> {
+        if prototype.len() != self.stream_sizes.len() {
+            Error::invalid(format!(
+                "Prototype has {} records, but the section contains {} byte streams",
+                prototype.len(),
+                self.stream_sizes.len()
+            ))?
+        }
+        prototype
+            .iter()
+            .zip(&self.stream_sizes)
+            .filter(|(r, _)| r.data_type.bit_size() > 0)
+            .map(|(r, size)| size * 8 / r.data_type.bit_size() as u64)
+            .min()
+            .invalid_err("Cannot count points of a prototype without any records of fixed size")
+    }
+
+    /// Tries to guess the prototype from the structure of the section.
+    ///
+    /// Only sections with exactly three byte streams of equal size are recognized.
+    /// They are assumed to contain Cartesian coordinates with double or single precision,
+    /// which is checked by decoding the first few values of the first byte stream.
+    /// Values are considered plausible if they are finite and their magnitude is below 10 million.
+    ///
+    /// All other prototypes cannot be guessed, because the data types, value ranges and bit sizes
+    /// of the records are only stored in the XML section.
+    /// This includes integer or scaled integer coordinates, spherical coordinates
+    /// and any additional records like colors, intensities or row and column indices.
+    /// A guessed prototype might also be wrong, for example three single precision streams
+    /// can contain spherical instead of Cartesian coordinates.
+    /// Returns None if the prototype could not be guessed.
+    pub fn infer_prototype(&self) -> Option<Vec<Record>> {
+        let size = *self.stream_sizes.first()?;
+        if self.stream_sizes.len() != 3 || size == 0 || self.stream_sizes.iter().any(|s| *s != size)
+        {
+            return None;
+        }
+        let plausible = |v: f64| v.is_finite() && v.abs() < 1e7;
+        let doubles = size.is_multiple_of(8)
+            && self
+                .sample
+                .chunks_exact(8)
+                .filter_map(|c| c.try_into().ok())
+                .all(|b| plausible(f64::from_le_bytes(b)));
+        let singles = size.is_multiple_of(4)
+            && self
+                .sample
+                .chunks_exact(4)
+                .filter_map(|c| c.try_into().ok())
+                .all(|b| plausible(f32::from_le_bytes(b) as f64));
+        if doubles {
+            Some(vec![
+                Record::CARTESIAN_X_F64,
+                Record::CARTESIAN_Y_F64,
+                Record::CARTESIAN_Z_F64,
+            ])
+        } else if singles {
+            Some(vec![
+                Record::CARTESIAN_X_F32,
+                Record::CARTESIAN_Y_F32,
+                Record::CARTESIAN_Z_F32,
+            ])
+        } else {
+            None
+        }
+    }
+
+    /// Creates a point cloud descriptor for the section with the given prototype.
+    /// The number of records is derived from the byte stream sizes, all other metadata is empty.
+    pub fn pointcloud(&self, prototype: Vec<Record>) -> Result<PointCloud> {
+        let records = self.records(&prototype)?;
+        Ok(PointCloud {
+            file_offset: self.offset,
+            records,
+            prototype,
+            ..Default::default()
+        })
+    }
+
+    fn section_header(&self) -> CompressedVectorSectionHeader {
+        let mut header = CompressedVectorSectionHeader::default();
+        header.section_length = self.length;
+        header.data_offset = self.data_offset;
+        header
+    }
+}
+
+/// Summary of the data written by [`E57Salvager::write`].
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct SalvageReport {
+    /// Number of point clouds written into the new file.
+    pub pointclouds: usize,
+    /// Overall number of points written into the new file.
+    pub points: u64,
+    /// Number of point cloud sections that were skipped because of an unknown prototype.
+    pub skipped_pointclouds: usize,
+    /// Number of blobs that were recognized as PNG or JPEG and added as images.
+    pub images: usize,
+    /// All other blobs that were copied into the new file.
+    /// They are not referenced in the XML section and need to be linked manually if required.
+    pub blobs: Vec<Blob>,
+}
+
+/// Recovers binary data from E57 files with a missing or damaged XML section.
+///
+/// The file is scanned page by page for compressed vector sections with point data and for blob sections.
+/// Pages with invalid checksums are treated as zeros instead of failing.
+/// Found data can be read directly or written into a new and valid E57 file.
+pub struct E57Salvager<T: Read + Seek> {
+    reader: PagedReader<T>,
+    pointclouds: Vec<SalvagedPointCloud>,
+    blobs: Vec<Blob>,
+}
+
+impl<T: Read + Seek> E57Salvager<T> {
+    /// Scans the binary sections of a damaged E57 file.
+    ///
+    /// If the file header is intact and points to an XML section, the scan stops there.
+    /// Truncated files are supported, an incomplete last page is ignored.
+    pub fn new(mut reader: T) -> Result<Self> {
+        let (xml_offset, page_size) = header_values(&mut reader);
+        let mut reader = PagedReader::new_truncated(reader, page_size)
+            .read_err("Failed creating paged CRC reader")?;
+        reader.set_recovery(true);
+
+        let end = match xml_offset {
+            Some(offset) => reader
+                .seek_physical(offset)
+                .unwrap_or(reader.logical_size()),
+            None => reader.logical_size(),
+        };
+        let mut salvager = Self {
+            reader,
+            pointclouds: Vec::new(),
+            blobs: Vec::new(),
+        };
+        salvager.scan(end);
+        Ok(salvager)
+    }
+
+    /// Returns all point cloud sections found in the file.
+    pub fn pointclouds(&self) -> &[SalvagedPointCloud] {
+        &self.pointclouds
+    }
+
+    /// Returns all blob sections found in the file.
+    pub fn blobs(&self) -> &[Blob] {
+        &self.blobs
+    }
+
+    /// Returns the numbers of all pages with invalid checksums that were found so far.
+    pub fn corrupt_pages(&self) -> &[u64] {
+        self.reader.corrupt_pages()
+    }
+
+    /// Returns an iterator for the raw points of a salvaged point cloud.
+    /// The point cloud descriptor must be created with [`SalvagedPointCloud::pointcloud`].
+    pub fn pointcloud_raw(
+        &mut self,
+        salvaged: &SalvagedPointCloud,
+        pc: &PointCloud,
+    ) -> Result<PointCloudReaderRaw<'_, T>> {
+        let names: Vec<RecordName> = pc.prototype.iter().map(|r| r.name.clone()).collect();
+        let reader = PagedReaderRef::Borrowed(&mut self.reader);
+        let queue_reader =
+            QueueReader::from_section_header(pc, reader, &names, salvaged.section_header())?;
+        Ok(PointCloudReaderRaw::from_queue_reader(pc, queue_reader))
+    }
+
+    /// Reads the content of a salvaged blob and writes it into the supplied writer.
+    /// Returns the number of bytes written.
+    pub fn blob(&mut self, blob: &Blob, writer: &mut dyn Write) -> Result<u64> {
+        blob.read(&mut self.reader, writer)
+    }
+
+    /// Writes all salvaged data into a new E57 file.
+    ///
+    /// The given prototype is used for all point cloud sections with a matching number of byte streams.
+    /// For other sections the prototype is guessed, if that is not possible they are skipped.
+    /// Blobs containing PNG or JPEG images are added as visual reference images,
+    /// all other blobs are copied without being referenced.
+    /// New point clouds and images get GUIDs that consist of the given prefix and a running number.
+    /// The writer is not finalized, this is left to the caller.
+    pub fn write<W: Read + Write + Seek>(
+        &mut self,
+        writer: &mut E57Writer<W>,
+        prototype: Option<&[Record]>,
+        guid_prefix: &str,
+    ) -> Result<SalvageReport> {
+        let mut report = SalvageReport::default();
+        for salvaged in self.pointclouds.clone() {
+            let prototype = match prototype {
+                Some(p) if p.len() == salvaged.stream_sizes.len() => Some(p.to_vec()),
+                _ => salvaged.infer_prototype(),
+            };
+            let Some(prototype) = prototype else {
+                report.skipped_pointclouds += 1;
+                continue;
+            };
+            let pc = salvaged.pointcloud(prototype)?;
+            let guid = format!("{guid_prefix}_pointcloud_{}", report.pointclouds);
+            let mut pc_writer = writer.add_pointcloud(&guid, pc.prototype.clone())?;
+            for point in self.pointcloud_raw(&salvaged, &pc)? {
+                pc_writer.add_point(point?)?;
+                report.points += 1;
+            }
+            pc_writer.finalize()?;
+            report.pointclouds += 1;
+        }
+
+        for blob in self.blobs.clone() {
+            let mut data = Vec::new();
+            self.blob(&blob, &mut data)?;
+            if let Some((format, width, height)) = image_properties(&data) {
+                let guid = format!("{guid_prefix}_image_{}", report.images);
+                let mut image_writer = writer.add_image(&guid)?;
+                let properties = VisualReferenceImageProperties { width, height };
+                image_writer.add_visual_reference(
+                    format,
+                    &mut data.as_slice(),
+                    properties,
+                    None,
+                )?;
+                image_writer.finalize()?;
+                report.images += 1;
+            } else {
+                report.blobs.push(writer.add_blob(&mut data.as_slice())?);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Walks over the logical file content and looks for section headers at each 4-byte-aligned offset.
+    fn scan(&mut self, end: u64) {
+        let mut offset = FILE_HEADER_SIZE;
+        while offset + BLOB_HEADER_SIZE <= end {
+            let mut buffer = [0_u8; CompressedVectorSectionHeader::SIZE as usize];
+            let size = (end - offset).min(buffer.len() as u64) as usize;
+            if self.read_at(offset, &mut buffer[..size]).is_err() {
+                break;
+            }
+            let reserved_zero = buffer[1..8].iter().all(|b| *b == 0);
+            let section_length = u64::from_le_bytes([
+                buffer[8], buffer[9], buffer[10], buffer[11], buffer[12], buffer[13], buffer[14],
+                buffer[15],
+            ]);
+            if !reserved_zero || section_length == 0 {
+                offset += 4;
+                continue;
+            }
+
+            if buffer[0] == 1 && size == buffer.len() {
+                let data_offset = u64::from_le_bytes([
+                    buffer[16], buffer[17], buffer[18], buffer[19], buffer[20], buffer[21],
+                    buffer[22], buffer[23],
+                ]);
+                if let Some(pc) = self.scan_pointcloud(offset, data_offset, end) {
+                    self.truncate_last_blob(offset);
+                    offset = align(pc.length + offset);
+                    self.pointclouds.push(pc);
+                    continue;
+                }
+            } else if buffer[0] == 0 {
+                self.truncate_last_blob(offset);
+                let start = offset + BLOB_HEADER_SIZE;
+                // Some writers like libE57 include the header in the section length and some do not.
+                // The header is included if the next section only follows the shorter interpretation.
+                let includes_header = section_length >= BLOB_HEADER_SIZE
+                    && !self.is_section_start(align(start + section_length), end)
+                    && self.is_section_start(align(offset + section_length), end);
+                let length = if includes_header {
+                    section_length - BLOB_HEADER_SIZE
+                } else {
+                    section_length
+                };
+                let length = length.min(end - start);
+                if let Ok(physical) = self.physical_offset(offset) {
+                    self.blobs.push(Blob::new(physical, length));
+                }
+                // The smaller length is used to continue, the next header will be found anyway
+                offset = align(offset + section_length.max(BLOB_HEADER_SIZE));
+                continue;
+            }
+            offset += 4;
+        }
+    }
+
+    /// Checks if a blob or compressed vector section header is located at the given logical offset.
+    /// The end of the scanned range also counts as start of the next section.
+    fn is_section_start(&mut self, offset: u64, end: u64) -> bool {
+        if offset == end {
+            return true;
+        }
+        let mut buffer = [0_u8; BLOB_HEADER_SIZE as usize];
+        if offset > end || self.read_at(offset, &mut buffer).is_err() {
+            return false;
+        }
+        let section_length = u64::from_le_bytes([
+            buffer[8], buffer[9], buffer[10], buffer[11], buffer[12], buffer[13], buffer[14],
+            buffer[15],
+        ]);
+        buffer[0] <= 1 && buffer[1..8].iter().all(|b| *b == 0) && section_length > 0
+    }
+
+    /// Walks over all intact packets of a potential compressed vector section.
+    /// Returns None if no valid data packet was found.
+    fn scan_pointcloud(
+        &mut self,
+        offset: u64,
+        data_offset: u64,
+        end: u64,
+    ) -> Option<SalvagedPointCloud> {
+        // Incomplete sections have no valid data offset, assume data is following the header
+        let header_end = offset + CompressedVectorSectionHeader::SIZE;
+        let data_start = self
+            .reader
+            .seek_physical(data_offset)
+            .ok()
+            .filter(|o| *o >= header_end && *o < end)
+            .unwrap_or(header_end);
+
+        let mut position = data_start;
+        let mut packets = 0;
+        let mut stream_sizes: Vec<u64> = Vec::new();
+        let mut sample = Vec::new();
+        while position < end {
+            let Some((length, sizes)) = self.read_packet(position) else {
+                break;
+            };
+            if position + length > end {
+                break;
+            }
+            if !sizes.is_empty() {
+                if packets == 0 {
+                    stream_sizes = vec![0; sizes.len()];
+                    sample = self.read_sample(position, &sizes).unwrap_or_default();
+                } else if sizes.len() != stream_sizes.len() {
+                    break;
+                }
+                stream_sizes
+                    .iter_mut()
+                    .zip(&sizes)
+                    .for_each(|(s, n)| *s += n);
+                packets += 1;
+            }
+            position += length;
+        }
+
+        if packets == 0 {
+            return None;
+        }
+        Some(SalvagedPointCloud {
+            offset: self.physical_offset(offset).ok()?,
+            data_offset: self.physical_offset(data_start).ok()?,
+            length: position - offset,
+            packets,
+            stream_sizes,
+            sample,
+        })
+    }
+
+    /// Reads a packet header at the given logical offset.
+    /// Returns the packet length and the byte stream sizes, which are empty for non-data packets.
+    fn read_packet(&mut self, offset: u64) -> Option<(u64, Vec<u64>)> {
+        self.reader.seek_logical(offset).ok()?;
+        match PacketHeader::read(&mut self.reader).ok()? {
+            PacketHeader::Index(header) => Some((header.packet_length, Vec::new())),
+            PacketHeader::Ignored(header) => Some((header.packet_length, Vec::new())),
+            PacketHeader::Data(header) => {
+                let mut sizes = Vec::with_capacity(header.bytestream_count as usize);
+                for _ in 0..header.bytestream_count {
+                    let mut buf = [0_u8; 2];
+                    self.reader.read_exact(&mut buf).ok()?;
+                    sizes.push(u16::from_le_bytes(buf) as u64);
+                }
+                let used = 6 + 2 * sizes.len() as u64 + sizes.iter().sum::<u64>();
+                if used > header.packet_length {
+                    return None;
+                }
+                Some((header.packet_length, sizes))
+            }
+        }
+    }
+
+    /// Reads the first bytes of the first byte stream of a data packet.
+    fn read_sample(&mut self, offset: u64, sizes: &[u64]) -> Result<Vec<u8>> {
+        let first = *sizes
+            .first()
+            .invalid_err("Data packet has no byte streams")?;
+        let mut sample = vec![0_u8; (first as usize).min(SAMPLE_SIZE)];
+        self.read_at(offset + 6 + 2 * sizes.len() as u64, &mut sample)?;
+        Ok(sample)
+    }
+
+    fn read_at(&mut self, offset: u64, buffer: &mut [u8]) -> Result<()> {
+        self.reader
+            .seek_logical(offset)
+            .read_err("Failed to seek in salvaged file")?;
+        self.reader
+            .read_exact(buffer)
+            .read_err("Failed to read from salvaged file")
+    }
+
+    fn physical_offset(&mut self, offset: u64) -> Result<u64> {
+        self.reader
+            .seek_logical(offset)
+            .read_err("Failed to seek in salvaged file")?;
+        Ok(self.reader.physical_position())
+    }
+
+    /// Makes sure the last found blob does not overlap with a section starting at the given logical offset.
+    fn truncate_last_blob(&mut self, offset: u64) {
+        let Some(blob) = self.blobs.last_mut() else {
+            return;
+        };
+        let Ok(start) = self.reader.seek_physical(blob.offset) else {
+            return;
+        };
+        let max_length = offset.saturating_sub(start + BLOB_HEADER_SIZE);
+        if blob.length > max_length {
+            blob.length = max_length;
+        }
+    }
+}
+
+impl E57Salvager<BufReader<File>> {
+    /// Scans the binary sections of a damaged E57 file at the given path.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path).read_err("Unable to open file")?;
+        let reader = BufReader::new(file);
+        Self::new(reader)
+    }
+}
+
+/// Reads the XML offset and page size from the file header.
+/// The default page size is used if the header is damaged or contains an implausible page size.
+fn header_values(reader: &mut dyn Read) -> (Option<u64>, u64) {
+    let mut data = [0_u8; FILE_HEADER_SIZE as usize];
+    if reader.read_exact(&mut data).is_err() || !data.starts_with(b"ASTM-E57") {
+        return (None, DEFAULT_PAGE_SIZE);
+    }
+    let value = |offset: usize| {
+        u64::from_le_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+            data[offset + 4],
+            data[offset + 5],
+            data[offset + 6],
+            data[offset + 7],
+        ])
+    };
+    let xml_offset = Some(value(24)).filter(|o| *o > 0);
+    let page_size = Some(value(40))
+        .filter(|s| s.is_power_of_two() && (64..=MAX_PAGE_SIZE).contains(s))
+        .unwrap_or(DEFAULT_PAGE_SIZE);
+    (xml_offset, page_size)
+}
+
+fn align(offset: u64) -> u64 {
+    offset.div_ceil(4) * 4
+}
+
+/// Detects PNG and JPEG images and extracts their size.
+fn image_properties(data: &[u8]) -> Option<(ImageFormat, u32, u32)> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let width = u32::from_be_bytes(data.get(16..20)?.try_into().ok()?);
+        let height = u32::from_be_bytes(data.get(20..24)?.try_into().ok()?);
+        return Some((ImageFormat::Png, width, height));
+    }
+    if data.starts_with(&[0xFF, 0xD8]) {
+        let (width, height) = jpeg_size(data)?;
+        return Some((ImageFormat::Jpeg, width, height));
+    }
+    None
+}
+
+/// Walks over the JPEG segments until a start of frame segment with the image size is found.
+fn jpeg_size(data: &[u8]) -> Option<(u32, u32)> {
+    let mut pos = 2;
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+        let standalone = marker == 0x01 || (0xD0..=0xD8).contains(&marker) || marker == 0xFF;
+        if standalone {
+            pos += if marker == 0xFF { 1 } else { 2 };
+            continue;
+        }
+        let is_sof = (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker);
+        if is_sof {
+            let height = u16::from_be_bytes(data.get(pos + 5..pos + 7)?.try_into().ok()?);
+            let width = u16::from_be_bytes(data.get(pos + 7..pos + 9)?.try_into().ok()?);
+            return Some((width as u32, height as u32));
+        }
+        let length = u16::from_be_bytes(data.get(pos + 2..pos + 4)?.try_into().ok()?);
+        pos += 2 + length as usize;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paged_writer::PagedWriter;
+    use crate::RecordValue;
+    use std::io::Cursor;
+
+    #[test]
+    fn blob_length_with_header() {
+        let blob_data = vec![42_u8; 5001];
+        let mut cursor = Cursor::new(Vec::new());
+        let mut writer = E57Writer::new(&mut cursor, "guid_file").unwrap();
+        let blob = writer.add_blob(&mut blob_data.as_slice()).unwrap();
+        let mut pc_writer = writer
+            .add_pointcloud(
+                "guid_pc",
+                vec![
+                    Record::CARTESIAN_X_F64,
+                    Record::CARTESIAN_Y_F64,
+                    Record::CARTESIAN_Z_F64,
+                ],
+            )
+            .unwrap();
+        pc_writer
+            .add_point(vec![RecordValue::Double(1.0); 3])
+            .unwrap();
+        pc_writer.finalize().unwrap();
+        writer.finalize().unwrap();
+        drop(writer);
+        let mut data = cursor.into_inner();
+
+        let salvager = E57Salvager::new(Cursor::new(&data)).unwrap();
+        assert_eq!(salvager.blobs().len(), 1);
+        assert_eq!(salvager.blobs()[0].length, 5001);
+
+        // Include the header in the section length like libE57 does
+        let mut writer = PagedWriter::from_existing(Cursor::new(&mut data)).unwrap();
+        writer.physical_seek(blob.offset + 8).unwrap();
+        writer.write_all(&5017_u64.to_le_bytes()).unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        let mut salvager = E57Salvager::new(Cursor::new(&data)).unwrap();
+        assert_eq!(salvager.blobs().len(), 1);
+        assert_eq!(salvager.blobs()[0].length, 5001);
+        assert_eq!(salvager.pointclouds().len(), 1);
+        let mut read = Vec::new();
+        let blob = salvager.blobs()[0].clone();
+        salvager.blob(&blob, &mut read).unwrap();
+        assert_eq!(read, blob_data);
+    }
+}
@@ -0,0 +1,155 @@
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom};
+use std::sync::Arc;
+
+/// Data source that supports reading from arbitrary positions without any mutable state.
+///
+/// Sources implementing this trait can be shared between multiple threads.
+/// This allows reading several point clouds of the same E57 file concurrently,
+/// see also [`E57Reader::from_source`](crate::E57Reader::from_source).
+pub trait ReadAt {
+    /// Reads bytes starting at the given offset into the buffer.
+    /// Returns the number of bytes read, which is zero at the end of the source.
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize>;
+
+    /// Returns the overall size of the source in bytes.
+    fn size(&self) -> Result<u64>;
+}
+
+#[cfg(unix)]
+impl ReadAt for File {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        std::os::unix::fs::FileExt::read_at(self, buf, offset)
+    }
+
+    fn size(&self) -> Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+}
+
+#[cfg(windows)]
+impl ReadAt for File {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        std::os::windows::fs::FileExt::seek_read(self, buf, offset)
+    }
+
+    fn size(&self) -> Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+}
+
+impl ReadAt for [u8] {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        let start = usize::try_from(offset)
+            .unwrap_or(usize::MAX)
+            .min(self.len());
+        let len = buf.len().min(self.len() - start);
+        buf[..len].copy_from_slice(&self[start..start + len]);
+        Ok(len)
+    }
+
+    fn size(&self) -> Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+impl ReadAt for Vec<u8> {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        self.as_slice().read_at(offset, buf)
+    }
+
+    fn size(&self) -> Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+impl<R: ReadAt + ?Sized> ReadAt for Arc<R> {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        self.as_ref().read_at(offset, buf)
+    }
+
+    fn size(&self) -> Result<u64> {
+        self.as_ref().size()
+    }
+}
+
+/// Reader with its own position on top of a shared source.
+///
+/// Implements [`Read`] and [`Seek`] so it can be used like any other reader.
+/// Cloning creates an independent reader for the same source.
+pub struct SourceReader<R: ReadAt> {
+    source: Arc<R>,
+    position: u64,
+}
+
+impl<R: ReadAt> SourceReader<R> {
+    /// Creates a new reader for the shared source starting at the beginning.
+    pub fn new(source: Arc<R>) -> Self {
+        Self {
+            source,
+            position: 0,
+        }
+    }
+
+    /// Returns the shared source of the reader.
+    pub fn source(&self) -> &Arc<R> {
+        &self.source
+    }
+}
+
+impl<R: ReadAt> Clone for SourceReader<R> {
+    fn clone(&self) -> Self {
+        Self {
+            source: self.source.clone(),
+            position: self.position,
+        }
+    }
+}
+
+impl<R: ReadAt> Read for SourceReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let read = self.source.read_at(self.position, buf)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: ReadAt> Seek for SourceReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.source.size()?.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position.ok_or(Error::new(
+            ErrorKind::InvalidInput,
+            "Tried to seek to a negative or overflowing position",
+        ))?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_and_seek() {
+        let source = Arc::new(vec![1_u8, 2, 3, 4, 5]);
+        let mut reader = SourceReader::new(source);
+        let mut other = reader.clone();
+
+        let mut buf = [0_u8; 2];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [1, 2]);
+        assert_eq!(reader.seek(SeekFrom::End(-1)).unwrap(), 4);
+        assert_eq!(reader.read(&mut buf).unwrap(), 1);
+        assert_eq!(buf[0], 5);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        assert!(reader.seek(SeekFrom::Current(-10)).is_err());
+
+        // Clones have their own position
+        other.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [1, 2]);
+    }
+}
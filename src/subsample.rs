@@ -0,0 +1,182 @@
+use crate::filter::Coordinates;
+use crate::{Error, Record, RecordName, RecordValue, Result};
+use std::collections::HashSet;
+
+/// Defines how points are subsampled while reading a point cloud.
+///
+/// Subsampling is evaluated directly after decoding the raw values of a point,
+/// skipped points are never converted or returned by the readers.
+/// See also [`PointCloudReaderSimple::subsample`](crate::PointCloudReaderSimple::subsample)
+/// and [`PointCloudReaderRaw::subsample`](crate::PointCloudReaderRaw::subsample).
+#[derive(Clone, Debug, PartialEq)]
+pub enum Subsampling {
+    /// Keeps every Nth point, starting with the first point of the point cloud.
+    /// The values of skipped points are not decoded.
+    /// If the point cloud has index packets, data packets without any selected points are not read at all.
+    EveryNth(u64),
+    /// Keeps a random fraction of the points, with a value between 0 and 1.
+    /// The selection only depends on the seed and the index of the point,
+    /// which means the same seed always selects the same points.
+    Random { fraction: f64, seed: u64 },
+    /// Keeps points where the row index is a multiple of the row stride
+    /// and the column index is a multiple of the column stride.
+    /// Requires a point cloud with row and column indices.
+    Grid { row_stride: u64, column_stride: u64 },
+    /// Keeps only the first point inside each cell of a regular voxel grid with the given cell size.
+    /// The grid is aligned to the origin of the coordinate system of the point cloud,
+    /// which means the pose of the point cloud is not applied.
+    Voxel { size: f64 },
+}
+
+/// Evaluates a subsampling mode on the raw values of points.
+pub(crate) struct Subsampler {
+    mode: Option<Subsampling>,
+    row: Option<usize>,
+    column: Option<usize>,
+    coordinates: Coordinates,
+    voxels: HashSet<[i64; 3]>,
+}
+
+impl Subsampler {
+    pub fn new(prototype: &[Record]) -> Self {
+        let fi = |name: RecordName| prototype.iter().position(|r| r.name == name);
+        Self {
+            mode: None,
+            row: fi(RecordName::RowIndex),
+            column: fi(RecordName::ColumnIndex),
+            coordinates: Coordinates::new(prototype),
+            voxels: HashSet::new(),
+        }
+    }
+
+    /// Sets a new mode after checking its parameters and the required records.
+    pub fn set(&mut self, mode: Subsampling) -> Result<()> {
+        match &mode {
+            Subsampling::EveryNth(n) => {
+                if *n == 0 {
+                    Error::invalid("Subsampling step size must be at least one")?
+                }
+            }
+            Subsampling::Random { fraction, .. } => {
+                if !(0.0..=1.0).contains(fraction) {
+                    Error::invalid(format!(
+                        "Subsampling fraction must be between 0 and 1, got {fraction}"
+                    ))?
+                }
+            }
+            Subsampling::Grid {
+                row_stride,
+                column_stride,
+            } => {
+                if *row_stride == 0 || *column_stride == 0 {
+                    Error::invalid("Subsampling strides must be at least one")?
+                }
+                if self.row.is_none() || self.column.is_none() {
+                    Error::invalid("Grid subsampling requires row and column index records")?
+                }
+            }
+            Subsampling::Voxel { size } => {
+                if !size.is_finite() || *size <= 0.0 {
+                    Error::invalid(format!(
+                        "Subsampling voxel size must be a positive number, got {size}"
+                    ))?
+                }
+                if self.coordinates.is_empty() {
+                    Error::invalid("Voxel subsampling requires Cartesian or spherical coordinates")?
+                }
+            }
+        }
+        self.mode = Some(mode);
+        self.voxels.clear();
+        Ok(())
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.mode.is_some()
+    }
+
+    /// Returns the index of the next selected point after the given index,
+    /// if this can be determined without looking at the point values.
+    pub fn next_selected(&self, index: u64) -> Option<u64> {
+        match &self.mode {
+            Some(Subsampling::EveryNth(n)) => Some(index.div_ceil(*n) * n),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the point with the given index and raw values is selected.
+    pub fn keep(
+        &mut self,
+        index: u64,
+        prototype: &[Record],
+        values: &[RecordValue],
+    ) -> Result<bool> {
+        match &self.mode {
+            None => Ok(true),
+            Some(Subsampling::EveryNth(n)) => Ok(index.is_multiple_of(*n)),
+            Some(Subsampling::Random { fraction, seed }) => {
+                Ok(random_fraction(*seed, index) < *fraction)
+            }
+            Some(Subsampling::Grid {
+                row_stride,
+                column_stride,
+            }) => match (self.row, self.column) {
+                (Some(row), Some(column)) => {
+                    let row = values[row].to_i64(&prototype[row].data_type)?;
+                    let column = values[column].to_i64(&prototype[column].data_type)?;
+                    Ok(row.rem_euclid(*row_stride as i64) == 0
+                        && column.rem_euclid(*column_stride as i64) == 0)
+                }
+                _ => Error::internal("Grid subsampling without row and column records"),
+            },
+            Some(Subsampling::Voxel { size }) => {
+                let p = self.coordinates.cartesian(prototype, values)?;
+                if p.iter().any(|c| !c.is_finite()) {
+                    return Ok(false);
+                }
+                let cell = p.map(|c| (c / size).floor() as i64);
+                Ok(self.voxels.insert(cell))
+            }
+        }
+    }
+}
+
+/// Maps the seed and point index to a pseudo random value between 0 and 1.
+/// Uses the `SplitMix64` mixing function, which gives well distributed values for consecutive inputs.
+fn random_fraction(seed: u64, index: u64) -> f64 {
+    let mut z = seed
+        .wrapping_add(index.wrapping_mul(0x9E3779B97F4A7C15))
+        .wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_fraction_distribution() {
+        let count = 100_000;
+        let selected = (0..count)
+            .filter(|i| random_fraction(42, *i) < 0.25)
+            .count();
+        assert!((selected as f64 / count as f64 - 0.25).abs() < 0.01);
+        assert_ne!(random_fraction(1, 0), random_fraction(2, 0));
+        assert_eq!(random_fraction(7, 123), random_fraction(7, 123));
+    }
+
+    #[test]
+    fn next_selected() {
+        let mut subsampler = Subsampler::new(&[]);
+        assert_eq!(subsampler.next_selected(5), None);
+        subsampler.set(Subsampling::EveryNth(10)).unwrap();
+        assert_eq!(subsampler.next_selected(0), Some(0));
+        assert_eq!(subsampler.next_selected(1), Some(10));
+        assert_eq!(subsampler.next_selected(10), Some(10));
+        assert!(subsampler.set(Subsampling::EveryNth(0)).is_err());
+        assert!(subsampler.set(Subsampling::Voxel { size: 1.0 }).is_err());
+    }
+}
@@ -1,9 +1,12 @@
 use crate::xml;
-use crate::Result;
+use crate::{Error, Result};
 use roxmltree::Node;
 
 /// Describes the rotation of a point cloud.
-#[derive(Clone, Debug)]
+///
+/// All rotation matrices used by the conversion functions are row-major,
+/// which means `m[row][column]`, and rotate column vectors.
+#[derive(Clone, Debug, PartialEq)]
 pub struct Quaternion {
     /// The scalar part of the quaternion. Shall be nonnegative.
     pub w: f64,
@@ -23,6 +26,239 @@ impl Quaternion {
         let z = xml::req_f64(node, "z")?;
         Ok(Self { w, x, y, z })
     }
+
+    /// Returns the length of the quaternion, which is one for valid rotations.
+    pub fn norm(&self) -> f64 {
+        (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+    }
+
+    /// Returns a unit quaternion with the same rotation.
+    /// Returns the identity rotation for quaternions with zero length.
+    pub fn normalized(&self) -> Self {
+        let norm = self.norm();
+        if norm == 0.0 || !norm.is_finite() {
+            return Self::default();
+        }
+        Self {
+            w: self.w / norm,
+            x: self.x / norm,
+            y: self.y / norm,
+            z: self.z / norm,
+        }
+    }
+
+    /// Returns the conjugate, which is the inverse rotation for unit quaternions.
+    pub fn conjugate(&self) -> Self {
+        Self {
+            w: self.w,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+
+    /// Returns the inverse rotation.
+    pub fn inverse(&self) -> Self {
+        let sq = self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z;
+        if sq == 0.0 {
+            return Self::default();
+        }
+        let c = self.conjugate();
+        Self {
+            w: c.w / sq,
+            x: c.x / sq,
+            y: c.y / sq,
+            z: c.z / sq,
+        }
+    }
+
+    /// Combines two rotations into one.
+    /// The resulting rotation is equal to rotating with `other` first and then with `self`.
+    pub fn compose(&self, other: &Quaternion) -> Self {
+        let (a, b) = (self, other);
+        Self {
+            w: a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+            x: a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+            y: a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+            z: a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+        }
+    }
+
+    /// Rotates a vector.
+    pub fn rotate(&self, v: [f64; 3]) -> [f64; 3] {
+        let m = self.to_matrix();
+        [
+            m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+            m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+            m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+        ]
+    }
+
+    /// Converts the quaternion to a row-major 3x3 rotation matrix.
+    pub fn to_matrix(&self) -> [[f64; 3]; 3] {
+        let Self { w, x, y, z } = *self;
+        [
+            [
+                w * w + x * x - y * y - z * z,
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+            ],
+            [
+                2.0 * (x * y + w * z),
+                w * w + y * y - x * x - z * z,
+                2.0 * (y * z - w * x),
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                w * w + z * z - x * x - y * y,
+            ],
+        ]
+    }
+
+    /// Creates a unit quaternion from a row-major 3x3 rotation matrix.
+    /// The matrix is expected to be orthonormal with a determinant of one.
+    pub fn from_matrix(m: &[[f64; 3]; 3]) -> Self {
+        let trace = m[0][0] + m[1][1] + m[2][2];
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Self {
+                w: 0.25 * s,
+                x: (m[2][1] - m[1][2]) / s,
+                y: (m[0][2] - m[2][0]) / s,
+                z: (m[1][0] - m[0][1]) / s,
+            }
+        } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+            let s = (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt() * 2.0;
+            Self {
+                w: (m[2][1] - m[1][2]) / s,
+                x: 0.25 * s,
+                y: (m[0][1] + m[1][0]) / s,
+                z: (m[0][2] + m[2][0]) / s,
+            }
+        } else if m[1][1] > m[2][2] {
+            let s = (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt() * 2.0;
+            Self {
+                w: (m[0][2] - m[2][0]) / s,
+                x: (m[0][1] + m[1][0]) / s,
+                y: 0.25 * s,
+                z: (m[1][2] + m[2][1]) / s,
+            }
+        } else {
+            let s = (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt() * 2.0;
+            Self {
+                w: (m[1][0] - m[0][1]) / s,
+                x: (m[0][2] + m[2][0]) / s,
+                y: (m[1][2] + m[2][1]) / s,
+                z: 0.25 * s,
+            }
+        };
+        q.positive().normalized()
+    }
+
+    /// Creates a unit quaternion from a rotation axis and an angle in radians.
+    /// The axis does not need to be normalized.
+    /// Returns the identity rotation if the axis has zero length.
+    pub fn from_axis_angle(axis: [f64; 3], angle: f64) -> Self {
+        let len = (axis[0] * axis[0] + axis[1] * axis[1] + axis[2] * axis[2]).sqrt();
+        if len == 0.0 {
+            return Self::default();
+        }
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        Self {
+            w: cos,
+            x: axis[0] / len * sin,
+            y: axis[1] / len * sin,
+            z: axis[2] / len * sin,
+        }
+    }
+
+    /// Converts the rotation to a normalized rotation axis and an angle in radians between 0 and PI.
+    /// Returns the X axis for rotations with an angle of zero.
+    pub fn to_axis_angle(&self) -> ([f64; 3], f64) {
+        let q = self.normalized().positive();
+        let len = (q.x * q.x + q.y * q.y + q.z * q.z).sqrt();
+        if len == 0.0 {
+            return ([1.0, 0.0, 0.0], 0.0);
+        }
+        let angle = 2.0 * len.atan2(q.w);
+        ([q.x / len, q.y / len, q.z / len], angle)
+    }
+
+    /// Creates a unit quaternion from Euler angles in radians.
+    /// The rotation is applied around the fixed X axis (roll) first,
+    /// then around the fixed Y axis (pitch) and finally around the fixed Z axis (yaw).
+    pub fn from_euler(roll: f64, pitch: f64, yaw: f64) -> Self {
+        let (sr, cr) = (roll / 2.0).sin_cos();
+        let (sp, cp) = (pitch / 2.0).sin_cos();
+        let (sy, cy) = (yaw / 2.0).sin_cos();
+        Self {
+            w: cr * cp * cy + sr * sp * sy,
+            x: sr * cp * cy - cr * sp * sy,
+            y: cr * sp * cy + sr * cp * sy,
+            z: cr * cp * sy - sr * sp * cy,
+        }
+        .positive()
+    }
+
+    /// Converts the rotation to Euler angles in radians, returned as roll, pitch and yaw.
+    /// See [`Quaternion::from_euler`] for the used convention.
+    pub fn to_euler(&self) -> [f64; 3] {
+        let Self { w, x, y, z } = self.normalized();
+        let roll = (2.0 * (w * x + y * z)).atan2(1.0 - 2.0 * (x * x + y * y));
+        let pitch = (2.0 * (w * y - z * x)).clamp(-1.0, 1.0).asin();
+        let yaw = (2.0 * (w * z + x * y)).atan2(1.0 - 2.0 * (y * y + z * z));
+        [roll, pitch, yaw]
+    }
+
+    /// Spherical linear interpolation between two rotations.
+    /// A factor of zero returns this rotation and a factor of one returns the other rotation.
+    pub fn slerp(&self, other: &Quaternion, t: f64) -> Self {
+        let a = self.normalized();
+        let mut b = other.normalized();
+        let mut dot = a.w * b.w + a.x * b.x + a.y * b.y + a.z * b.z;
+
+        // Take the shorter path
+        if dot < 0.0 {
+            b = Self {
+                w: -b.w,
+                x: -b.x,
+                y: -b.y,
+                z: -b.z,
+            };
+            dot = -dot;
+        }
+
+        // Use linear interpolation for very close rotations to avoid division by zero
+        let (fa, fb) = if dot > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let theta = dot.acos();
+            let sin = theta.sin();
+            (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+        Self {
+            w: fa * a.w + fb * b.w,
+            x: fa * a.x + fb * b.x,
+            y: fa * a.y + fb * b.y,
+            z: fa * a.z + fb * b.z,
+        }
+        .normalized()
+    }
+
+    /// Returns the equivalent quaternion with a nonnegative scalar part as required by the E57 standard.
+    fn positive(self) -> Self {
+        if self.w < 0.0 {
+            Self {
+                w: -self.w,
+                x: -self.x,
+                y: -self.y,
+                z: -self.z,
+            }
+        } else {
+            self
+        }
+    }
 }
 
 impl Default for Quaternion {
@@ -37,7 +273,7 @@ impl Default for Quaternion {
 }
 
 /// Describes the translation of a point cloud.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Translation {
     /// The X coordinate of the translation in meters.
     pub x: f64,
@@ -67,7 +303,10 @@ impl Default for Translation {
 }
 
 /// Describes a transformation of a point cloud with a rotation and translation component.
-#[derive(Clone, Debug, Default)]
+///
+/// Applying the transform to a point first rotates and then translates the point.
+/// For point clouds, this transforms the local coordinates into the file-level coordinate system.
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Transform {
     /// A unit quaternion representing the rotation of the transform.
     pub rotation: Quaternion,
@@ -91,6 +330,104 @@ impl Transform {
         })
     }
 
+    /// Applies rotation and translation to a point.
+    pub fn apply_point(&self, p: [f64; 3]) -> [f64; 3] {
+        let r = self.rotation.rotate(p);
+        [
+            r[0] + self.translation.x,
+            r[1] + self.translation.y,
+            r[2] + self.translation.z,
+        ]
+    }
+
+    /// Applies only the rotation to a direction vector.
+    pub fn apply_direction(&self, d: [f64; 3]) -> [f64; 3] {
+        self.rotation.rotate(d)
+    }
+
+    /// Combines two transforms into one.
+    /// The resulting transform is equal to applying `other` first and then `self`.
+    /// For example, use `parent.compose(&child)` for nested coordinate systems.
+    pub fn compose(&self, other: &Transform) -> Self {
+        let t = self.apply_point([
+            other.translation.x,
+            other.translation.y,
+            other.translation.z,
+        ]);
+        Self {
+            rotation: self.rotation.compose(&other.rotation),
+            translation: Translation {
+                x: t[0],
+                y: t[1],
+                z: t[2],
+            },
+        }
+    }
+
+    /// Returns the inverse transform.
+    pub fn inverse(&self) -> Self {
+        let rotation = self.rotation.inverse();
+        let t = rotation.rotate([self.translation.x, self.translation.y, self.translation.z]);
+        Self {
+            rotation,
+            translation: Translation {
+                x: -t[0],
+                y: -t[1],
+                z: -t[2],
+            },
+        }
+    }
+
+    /// Converts the transform to a row-major 4x4 matrix for homogeneous coordinates.
+    pub fn to_matrix4(&self) -> [[f64; 4]; 4] {
+        let m = self.to_matrix3x4();
+        [m[0], m[1], m[2], [0.0, 0.0, 0.0, 1.0]]
+    }
+
+    /// Creates a transform from a row-major 4x4 matrix for homogeneous coordinates.
+    /// The matrix must not contain any scaling, shearing or projection.
+    pub fn from_matrix4(m: &[[f64; 4]; 4]) -> Result<Self> {
+        if m[3] != [0.0, 0.0, 0.0, 1.0] {
+            Error::invalid(format!(
+                "Last row of transform matrix must be [0, 0, 0, 1], got {:?}",
+                m[3]
+            ))?
+        }
+        Self::from_matrix3x4(&[m[0], m[1], m[2]])
+    }
+
+    /// Converts the transform to a row-major 3x4 matrix with the translation in the last column.
+    pub fn to_matrix3x4(&self) -> [[f64; 4]; 3] {
+        let r = self.rotation.to_matrix();
+        let t = &self.translation;
+        [
+            [r[0][0], r[0][1], r[0][2], t.x],
+            [r[1][0], r[1][1], r[1][2], t.y],
+            [r[2][0], r[2][1], r[2][2], t.z],
+        ]
+    }
+
+    /// Creates a transform from a row-major 3x4 matrix with the translation in the last column.
+    /// The left 3x3 part must be a rotation matrix without any scaling or shearing.
+    pub fn from_matrix3x4(m: &[[f64; 4]; 3]) -> Result<Self> {
+        let r = [
+            [m[0][0], m[0][1], m[0][2]],
+            [m[1][0], m[1][1], m[1][2]],
+            [m[2][0], m[2][1], m[2][2]],
+        ];
+        if !is_rotation(&r) {
+            Error::invalid("Transform matrix does not contain a valid rotation")?
+        }
+        Ok(Self {
+            rotation: Quaternion::from_matrix(&r),
+            translation: Translation {
+                x: m[0][3],
+                y: m[1][3],
+                z: m[2][3],
+            },
+        })
+    }
+
     pub(crate) fn xml_string(&self, tag_name: &str) -> String {
         let w = xml::gen_float("w", self.rotation.w);
         let x = xml::gen_float("x", self.rotation.x);
@@ -106,3 +443,186 @@ impl Transform {
         format!("<{tag_name} type=\"Structure\">\n{quat}{trans}</{tag_name}>\n")
     }
 }
+
+/// Checks if the matrix is orthonormal with a determinant of one.
+fn is_rotation(m: &[[f64; 3]; 3]) -> bool {
+    const EPSILON: f64 = 1e-6;
+    for i in 0..3 {
+        for j in 0..3 {
+            let dot = (0..3).map(|k| m[i][k] * m[j][k]).sum::<f64>();
+            let expected = if i == j { 1.0 } else { 0.0 };
+            if (dot - expected).abs() > EPSILON {
+                return false;
+            }
+        }
+    }
+    let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+    (det - 1.0).abs() < EPSILON
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CartesianBounds;
+    use std::f64::consts::PI;
+
+    fn assert_close(a: [f64; 3], b: [f64; 3]) {
+        for i in 0..3 {
+            assert!((a[i] - b[i]).abs() < 1e-9, "{a:?} != {b:?}");
+        }
+    }
+
+    #[test]
+    fn apply_and_inverse() {
+        let transform = Transform {
+            rotation: Quaternion::from_axis_angle([0.0, 0.0, 1.0], PI / 2.0),
+            translation: Translation {
+                x: 1.0,
+                y: 2.0,
+                z: 3.0,
+            },
+        };
+        let p = transform.apply_point([1.0, 0.0, 0.0]);
+        assert_close(p, [1.0, 3.0, 3.0]);
+        assert_close(transform.apply_direction([1.0, 0.0, 0.0]), [0.0, 1.0, 0.0]);
+        assert_close(transform.inverse().apply_point(p), [1.0, 0.0, 0.0]);
+
+        let identity = transform.compose(&transform.inverse());
+        assert_close(identity.apply_point([4.0, 5.0, 6.0]), [4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn compose_order() {
+        let rotate = Transform {
+            rotation: Quaternion::from_axis_angle([0.0, 0.0, 1.0], PI / 2.0),
+            translation: Translation::default(),
+        };
+        let shift = Transform {
+            rotation: Quaternion::default(),
+            translation: Translation {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            },
+        };
+        let p = [1.0, 0.0, 0.0];
+        let combined = rotate.compose(&shift);
+        assert_close(
+            combined.apply_point(p),
+            rotate.apply_point(shift.apply_point(p)),
+        );
+        assert_close(combined.apply_point(p), [0.0, 2.0, 0.0]);
+    }
+
+    #[test]
+    fn matrix_roundtrip() {
+        let transform = Transform {
+            rotation: Quaternion::from_euler(0.1, -0.2, 2.5),
+            translation: Translation {
+                x: -1.0,
+                y: 0.5,
+                z: 10.0,
+            },
+        };
+        let m4 = transform.to_matrix4();
+        let back = Transform::from_matrix4(&m4).unwrap();
+        assert!((back.rotation.w - transform.rotation.w).abs() < 1e-9);
+        assert_close(
+            back.apply_point([1.0, 2.0, 3.0]),
+            transform.apply_point([1.0, 2.0, 3.0]),
+        );
+        let m34 = transform.to_matrix3x4();
+        assert_eq!(m34, [m4[0], m4[1], m4[2]]);
+
+        let mut invalid = m4;
+        invalid[0][0] *= 2.0;
+        assert!(Transform::from_matrix4(&invalid).is_err());
+        invalid = m4;
+        invalid[3][0] = 1.0;
+        assert!(Transform::from_matrix4(&invalid).is_err());
+
+        // Rotations of 180 degrees need the special cases of the matrix conversion
+        for axis in [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]] {
+            let q = Quaternion::from_axis_angle(axis, PI);
+            let back = Quaternion::from_matrix(&q.to_matrix());
+            assert_close(back.rotate([1.0, 2.0, 3.0]), q.rotate([1.0, 2.0, 3.0]));
+        }
+    }
+
+    #[test]
+    fn axis_angle_and_euler() {
+        let q = Quaternion::from_axis_angle([0.0, 2.0, 0.0], 0.75);
+        let (axis, angle) = q.to_axis_angle();
+        assert_close(axis, [0.0, 1.0, 0.0]);
+        assert!((angle - 0.75).abs() < 1e-9);
+        assert_eq!(Quaternion::default().to_axis_angle().1, 0.0);
+
+        let euler = [0.3, -0.4, 1.5];
+        let q = Quaternion::from_euler(euler[0], euler[1], euler[2]);
+        assert_close(q.to_euler(), euler);
+        let expected = Quaternion::from_axis_angle([0.0, 0.0, 1.0], euler[2])
+            .compose(&Quaternion::from_axis_angle([0.0, 1.0, 0.0], euler[1]))
+            .compose(&Quaternion::from_axis_angle([1.0, 0.0, 0.0], euler[0]));
+        assert_close(q.rotate([1.0, 2.0, 3.0]), expected.rotate([1.0, 2.0, 3.0]));
+    }
+
+    #[test]
+    fn transform_bounds() {
+        let bounds = CartesianBounds {
+            x_min: Some(0.0),
+            x_max: Some(2.0),
+            y_min: Some(0.0),
+            y_max: Some(1.0),
+            z_min: Some(-1.0),
+            z_max: Some(1.0),
+        };
+        let transform = Transform {
+            rotation: Quaternion::from_axis_angle([0.0, 0.0, 1.0], PI / 2.0),
+            translation: Translation {
+                x: 10.0,
+                y: 0.0,
+                z: 0.0,
+            },
+        };
+        let world = bounds.transform(&transform).unwrap();
+        assert!((world.x_min.unwrap() - 9.0).abs() < 1e-9);
+        assert!((world.x_max.unwrap() - 10.0).abs() < 1e-9);
+        assert!((world.y_min.unwrap() - 0.0).abs() < 1e-9);
+        assert!((world.y_max.unwrap() - 2.0).abs() < 1e-9);
+        assert_eq!(world.z_min, Some(-1.0));
+        assert_eq!(world.z_max, Some(1.0));
+
+        let open = CartesianBounds {
+            x_min: None,
+            ..bounds
+        };
+        assert!(open.transform(&transform).is_none());
+    }
+
+    #[test]
+    fn normalize_and_slerp() {
+        let q = Quaternion {
+            w: 2.0,
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        assert_eq!(q.normalized(), Quaternion::default());
+        let zero = Quaternion {
+            w: 0.0,
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        assert_eq!(zero.normalized(), Quaternion::default());
+
+        let a = Quaternion::default();
+        let b = Quaternion::from_axis_angle([0.0, 0.0, 1.0], PI / 2.0);
+        let half = a.slerp(&b, 0.5);
+        assert!((half.to_axis_angle().1 - PI / 4.0).abs() < 1e-9);
+        assert_close(a.slerp(&b, 0.0).rotate([1.0, 0.0, 0.0]), [1.0, 0.0, 0.0]);
+        assert_close(a.slerp(&b, 1.0).rotate([1.0, 0.0, 0.0]), [0.0, 1.0, 0.0]);
+    }
+}
@@ -0,0 +1,306 @@
+use crate::error::Converter;
+use crate::{Error, Extension, Result};
+use roxmltree::Node;
+
+/// XML namespace URL of the E57 standard.
+pub(crate) const E57_NAMESPACE: &str = "http://www.astm.org/COMMIT/E57/2010-e57-v1.0";
+
+/// Owned XML element to store and exchange additional metadata, like extension data.
+///
+/// E57 uses typed XML elements, the `type` attribute defines the kind of value stored in the element.
+/// There are helper functions to create and read elements for the common types
+/// `Structure`, `String`, `Integer` and `Float`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct XmlElement {
+    /// Namespace prefix of the element, empty for elements of the default E57 namespace.
+    pub namespace: String,
+    /// Namespace URL of the element.
+    /// When reading, this is resolved from the namespace declarations of the file.
+    /// Typed extension data is identified by this URL and not by the namespace prefix.
+    /// Empty for new elements until the namespace of an extension is assigned.
+    pub url: String,
+    /// Local name of the element without namespace prefix.
+    pub name: String,
+    /// Attributes with name and value in document order.
+    /// Attributes of other namespaces have their prefix included in the name.
+    pub attributes: Vec<(String, String)>,
+    /// Text content of the element, empty for elements without text.
+    pub text: String,
+    /// Child elements in document order.
+    pub children: Vec<XmlElement>,
+}
+
+impl XmlElement {
+    /// Creates a new element without namespace, attributes, text or children.
+    pub fn new(name: &str) -> Self {
+        Self {
+            namespace: String::new(),
+            url: String::new(),
+            name: name.to_owned(),
+            attributes: Vec::new(),
+            text: String::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Creates a new element with the type `Structure` and the given children.
+    pub fn structure(name: &str, children: Vec<XmlElement>) -> Self {
+        let mut element = Self::new(name);
+        element.set_attribute("type", "Structure");
+        element.children = children;
+        element
+    }
+
+    /// Creates a new element with the type `String` and the given text.
+    pub fn string(name: &str, value: &str) -> Self {
+        let mut element = Self::new(name);
+        element.set_attribute("type", "String");
+        element.text = value.to_owned();
+        element
+    }
+
+    /// Creates a new element with the type `Integer` and the given value.
+    pub fn integer(name: &str, value: i64) -> Self {
+        let mut element = Self::new(name);
+        element.set_attribute("type", "Integer");
+        element.text = value.to_string();
+        element
+    }
+
+    /// Creates a new element with the type `Float` and the given value.
+    pub fn float(name: &str, value: f64) -> Self {
+        let mut element = Self::new(name);
+        element.set_attribute("type", "Float");
+        element.text = value.to_string();
+        element
+    }
+
+    /// Returns the value of the attribute with the given name.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Sets or replaces the value of the attribute with the given name.
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        if let Some(attribute) = self.attributes.iter_mut().find(|(n, _)| n == name) {
+            attribute.1 = value.to_owned();
+        } else {
+            self.attributes.push((name.to_owned(), value.to_owned()));
+        }
+    }
+
+    /// Returns the first child element with the given local name.
+    pub fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    /// Same as [`XmlElement::child`] but returns an error if there is no such child element.
+    pub fn req_child(&self, name: &str) -> Result<&XmlElement> {
+        self.child(name)
+            .invalid_err(format!("XML tag '{name}' was not found in '{}'", self.name))
+    }
+
+    /// Returns the text of an element with the type `String`.
+    pub fn as_string(&self) -> Result<&str> {
+        self.check_type("String")?;
+        Ok(&self.text)
+    }
+
+    /// Returns the value of an element with the type `Integer`.
+    /// Empty elements have the value zero, as defined by the E57 standard.
+    pub fn as_integer(&self) -> Result<i64> {
+        self.check_type("Integer")?;
+        self.parse_number()
+    }
+
+    /// Returns the value of an element with the type `Float`.
+    /// Empty elements have the value zero, as defined by the E57 standard.
+    pub fn as_float(&self) -> Result<f64> {
+        self.check_type("Float")?;
+        self.parse_number()
+    }
+
+    pub(crate) fn from_node(node: &Node) -> Self {
+        let url = node.tag_name().namespace().unwrap_or_default();
+        let namespace = node.lookup_prefix(url).unwrap_or_default().to_owned();
+        let attributes = node
+            .attributes()
+            .map(|a| {
+                let prefix = a.namespace().and_then(|ns| node.lookup_prefix(ns));
+                let name = match prefix {
+                    Some(prefix) => format!("{prefix}:{}", a.name()),
+                    None => a.name().to_owned(),
+                };
+                (name, a.value().to_owned())
+            })
+            .collect();
+        let text: String = node
+            .children()
+            .filter(|c| c.is_text())
+            .filter_map(|c| c.text())
+            .collect();
+        let children: Vec<Self> = node
+            .children()
+            .filter(|c| c.is_element())
+            .map(|c| Self::from_node(&c))
+            .collect();
+        Self {
+            namespace,
+            url: url.to_owned(),
+            name: node.tag_name().name().to_owned(),
+            attributes,
+            // Ignore formatting whitespace between child elements
+            text: if !children.is_empty() && text.trim().is_empty() {
+                String::new()
+            } else {
+                text
+            },
+            children,
+        }
+    }
+
+    /// Collects all child elements of a node that are not in the list of known E57 tag names.
+    /// This includes all elements of extension namespaces and unknown elements of the E57 namespace.
+    pub(crate) fn unknown_children(node: &Node, known: &[&str]) -> Vec<Self> {
+        node.children()
+            .filter(|c| c.is_element())
+            .filter(|c| {
+                let tag = c.tag_name();
+                let e57 = matches!(tag.namespace(), None | Some(E57_NAMESPACE));
+                !e57 || !known.contains(&tag.name())
+            })
+            .map(|c| Self::from_node(&c))
+            .collect()
+    }
+
+    /// Sets the extension namespace of this element and all descendants without explicit namespace.
+    pub(crate) fn set_default_namespace(&mut self, extension: &Extension) {
+        if self.namespace.is_empty() {
+            self.namespace = extension.namespace.clone();
+            self.url = extension.url.clone();
+        }
+        for child in &mut self.children {
+            child.set_default_namespace(extension);
+        }
+    }
+
+    /// Checks that the namespace prefixes of this element, its attributes and
+    /// all descendants belong to one of the given extensions.
+    pub(crate) fn check_namespaces(&self, extensions: &[Extension]) -> Result<()> {
+        let attribute_prefixes = self
+            .attributes
+            .iter()
+            .filter_map(|(name, _)| name.split_once(':'))
+            .map(|(prefix, _)| prefix)
+            .filter(|prefix| *prefix != "xml");
+        for prefix in std::iter::once(self.namespace.as_str()).chain(attribute_prefixes) {
+            if !prefix.is_empty() && !extensions.iter().any(|e| e.namespace == prefix) {
+                Error::invalid(format!(
+                    "XML tag '{}' uses the namespace prefix '{prefix}' without registered extension",
+                    self.name
+                ))?
+            }
+        }
+        for child in &self.children {
+            child.check_namespaces(extensions)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn xml_string(&self) -> String {
+        let tag_name = if self.namespace.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}:{}", self.namespace, self.name)
+        };
+        let mut xml = format!("<{tag_name}");
+        for (name, value) in &self.attributes {
+            xml += &format!(" {name}=\"{}\"", escape(value));
+        }
+        xml += ">";
+        xml += &escape(&self.text);
+        if !self.children.is_empty() {
+            xml += "\n";
+            for child in &self.children {
+                xml += &child.xml_string();
+            }
+        }
+        xml += &format!("</{tag_name}>\n");
+        xml
+    }
+
+    fn check_type(&self, expected: &str) -> Result<()> {
+        match self.attribute("type") {
+            Some(found) if found == expected => Ok(()),
+            Some(found) => Error::invalid(format!(
+                "Found XML tag '{}' with type '{found}' instead of '{expected}'",
+                self.name
+            )),
+            None => Error::invalid(format!("XML tag '{}' has no 'type' attribute", self.name)),
+        }
+    }
+
+    fn parse_number<T: std::str::FromStr + Default>(&self) -> Result<T> {
+        let text = self.text.trim();
+        if text.is_empty() {
+            return Ok(T::default());
+        }
+        text.parse::<T>().ok().invalid_err(format!(
+            "Cannot parse value '{text}' of XML tag '{}'",
+            self.name
+        ))
+    }
+}
+
+/// Escapes the special XML characters in text and attribute values.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '"' => escaped += "&quot;",
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use roxmltree::Document;
+
+    #[test]
+    fn roundtrip() {
+        let mut element = XmlElement::structure(
+            "data",
+            vec![
+                XmlElement::string("name", "a < b & \"c\""),
+                XmlElement::integer("count", -42),
+                XmlElement::float("value", 1.5),
+            ],
+        );
+        element.set_default_namespace(&Extension::new("ext", "https://example.com/ext"));
+        let xml = format!(
+            "<root xmlns:ext=\"https://example.com/ext\">{}</root>",
+            element.xml_string()
+        );
+        let document = Document::parse(&xml).unwrap();
+        let node = document.root_element().first_element_child().unwrap();
+        let parsed = XmlElement::from_node(&node);
+        assert_eq!(parsed, element);
+        assert_eq!(
+            parsed.child("name").unwrap().as_string().unwrap(),
+            "a < b & \"c\""
+        );
+        assert_eq!(parsed.child("count").unwrap().as_integer().unwrap(), -42);
+        assert_eq!(parsed.child("value").unwrap().as_float().unwrap(), 1.5);
+        assert!(parsed.child("value").unwrap().as_integer().is_err());
+        assert!(parsed.child("missing").is_none());
+    }
+}
@@ -1,4 +1,5 @@
-use e57::{Blob, E57Reader, E57Writer, Error, Extension, RawValues};
+use e57::{Blob, E57Reader, E57Writer, Error, Extension, ExtensionData, RawValues};
+use e57::{ImageFormat, VisualReferenceImageProperties, XmlElement};
 use e57::{Record, RecordDataType, RecordName, RecordValue};
 use roxmltree::Document;
 use std::fs::remove_file;
@@ -123,3 +124,250 @@ fn extensions_example() {
 
     remove_file(path).unwrap();
 }
+
+// Custom metadata type that is stored as typed extension data in the XML section.
+#[derive(Debug, PartialEq)]
+struct ScanInfo {
+    operator: String,
+    station: i64,
+    accuracy: f64,
+}
+
+impl ExtensionData for ScanInfo {
+    fn extension() -> Extension {
+        Extension::new("scaninfo", "https://www.mycorp.com/scaninfo")
+    }
+
+    fn element_name() -> &'static str {
+        "scanInfo"
+    }
+
+    fn from_xml(element: &XmlElement) -> Result<Self, Error> {
+        Ok(Self {
+            operator: element.req_child("operator")?.as_string()?.to_owned(),
+            station: element.req_child("station")?.as_integer()?,
+            accuracy: element.req_child("accuracy")?.as_float()?,
+        })
+    }
+
+    fn to_xml(&self) -> Result<XmlElement, Error> {
+        Ok(XmlElement::structure(
+            "scanInfo",
+            vec![
+                XmlElement::string("operator", &self.operator),
+                XmlElement::integer("station", self.station),
+                XmlElement::float("accuracy", self.accuracy),
+            ],
+        ))
+    }
+}
+
+#[test]
+fn typed_extension_data() {
+    let path = Path::new("typed_extension_data.e57");
+    let info = |station| ScanInfo {
+        operator: String::from("Jane <Doe>"),
+        station,
+        accuracy: 0.25,
+    };
+
+    {
+        let mut writer = E57Writer::from_file(path, "file_guid").unwrap();
+        writer.set_extension_data(&info(0)).unwrap();
+
+        let prototype = vec![
+            Record::CARTESIAN_X_F32,
+            Record::CARTESIAN_Y_F32,
+            Record::CARTESIAN_Z_F32,
+        ];
+        let mut pc_writer = writer.add_pointcloud("pc_guid", prototype).unwrap();
+        pc_writer.set_extension_data(&info(1)).unwrap();
+        pc_writer.set_extension_data(&info(2)).unwrap();
+        pc_writer
+            .add_point(vec![
+                RecordValue::Single(1.0),
+                RecordValue::Single(2.0),
+                RecordValue::Single(3.0),
+            ])
+            .unwrap();
+        pc_writer.finalize().unwrap();
+
+        let mut img_writer = writer.add_image("img_guid").unwrap();
+        img_writer.set_extension_data(&info(3)).unwrap();
+        let properties = VisualReferenceImageProperties {
+            width: 1,
+            height: 1,
+        };
+        img_writer
+            .add_visual_reference(
+                ImageFormat::Png,
+                &mut Cursor::new(vec![0]),
+                properties,
+                None,
+            )
+            .unwrap();
+        img_writer.finalize().unwrap();
+
+        // Same namespace with another URL is rejected
+        let conflict = Extension::new("scaninfo", "https://www.othercorp.com/scaninfo");
+        assert!(writer.register_extension(conflict).is_err());
+
+        writer.finalize().unwrap();
+    }
+
+    {
+        let e57 = E57Reader::from_file(path).unwrap();
+        let extensions = e57.extensions();
+        assert_eq!(extensions.len(), 1);
+        assert_eq!(extensions[0].namespace, "scaninfo");
+        assert_eq!(e57.extension_data::<ScanInfo>().unwrap(), Some(info(0)));
+
+        let pointclouds = e57.pointclouds();
+        let pc = pointclouds.first().unwrap();
+        assert_eq!(pc.additional_xml.len(), 1);
+        assert_eq!(pc.extension_data::<ScanInfo>().unwrap(), Some(info(2)));
+
+        let images = e57.images();
+        let img = images.first().unwrap();
+        assert_eq!(img.extension_data::<ScanInfo>().unwrap(), Some(info(3)));
+    }
+
+    remove_file(path).unwrap();
+}
+
+#[test]
+fn extension_data_with_other_prefix() {
+    let path = Path::new("extension_data_with_other_prefix.e57");
+    let info = ScanInfo {
+        operator: String::from("John"),
+        station: 7,
+        accuracy: 0.5,
+    };
+
+    {
+        // Use another namespace prefix for the same extension URL
+        let mut writer = E57Writer::from_file(path, "file_guid").unwrap();
+        writer.set_extension_data(&info).unwrap();
+        let transformer = |xml: String| {
+            let xml = xml.replace("xmlns:scaninfo=", "xmlns:si=");
+            Ok(xml.replace("scaninfo:", "si:"))
+        };
+        writer.finalize_customized_xml(transformer).unwrap();
+    }
+
+    let e57 = E57Reader::from_file(path).unwrap();
+    let extensions = e57.extensions();
+    assert_eq!(extensions[0].namespace, "si");
+    assert_eq!(extensions[0].url, ScanInfo::extension().url);
+    assert_eq!(e57.additional_xml()[0].namespace, "si");
+    assert_eq!(e57.extension_data::<ScanInfo>().unwrap(), Some(info));
+
+    remove_file(path).unwrap();
+}
+
+#[test]
+fn preserve_unknown_xml() {
+    let original_path = Path::new("preserve_unknown_xml_original.e57");
+    let copy_path = Path::new("preserve_unknown_xml_copy.e57");
+    let prototype = vec![
+        Record::CARTESIAN_X_F32,
+        Record::CARTESIAN_Y_F32,
+        Record::CARTESIAN_Z_F32,
+    ];
+    let point = vec![
+        RecordValue::Single(1.0),
+        RecordValue::Single(2.0),
+        RecordValue::Single(3.0),
+    ];
+    let properties = VisualReferenceImageProperties {
+        width: 1,
+        height: 1,
+    };
+
+    {
+        // Write file with vendor elements that are unknown to the library
+        let mut writer = E57Writer::from_file(original_path, "file_guid").unwrap();
+        let ext = Extension::new("vendor", "https://www.vendor.com/e57");
+        writer.register_extension(ext).unwrap();
+        let mut pc_writer = writer.add_pointcloud("pc_guid", prototype).unwrap();
+        pc_writer.add_point(point.clone()).unwrap();
+        pc_writer.finalize().unwrap();
+        let mut img_writer = writer.add_image("img_guid").unwrap();
+        img_writer
+            .add_visual_reference(
+                ImageFormat::Png,
+                &mut Cursor::new(vec![0]),
+                properties.clone(),
+                None,
+            )
+            .unwrap();
+        img_writer.finalize().unwrap();
+        let transformer = |xml: String| {
+            let vendor = "<vendor:info type=\"Structure\" vendor:level=\"2\">\
+                <vendor:note type=\"String\">a &amp; b</vendor:note></vendor:info>\
+                <scanQuality type=\"Integer\">5</scanQuality>\n";
+            let xml = xml.replace("</vectorChild>", &format!("{vendor}</vectorChild>"));
+            Ok(xml.replace("</e57Root>", &format!("{vendor}</e57Root>")))
+        };
+        writer.finalize_customized_xml(transformer).unwrap();
+    }
+
+    let original = E57Reader::from_file(original_path).unwrap();
+    let root_xml = original.additional_xml();
+    assert_eq!(root_xml.len(), 2);
+    assert_eq!(root_xml[0].namespace, "vendor");
+    assert_eq!(root_xml[0].attribute("vendor:level"), Some("2"));
+    let note = root_xml[0].child("note").unwrap();
+    assert_eq!(note.as_string().unwrap(), "a & b");
+    assert_eq!(root_xml[1].namespace, "");
+    assert_eq!(root_xml[1].as_integer().unwrap(), 5);
+    let pointclouds = original.pointclouds();
+    let images = original.images();
+    assert_eq!(pointclouds[0].additional_xml, root_xml);
+    assert_eq!(images[0].additional_xml, root_xml);
+
+    {
+        // Copy the file including all unknown elements
+        let mut writer = E57Writer::from_file(copy_path, "copy_guid").unwrap();
+        for ext in original.extensions() {
+            writer.register_extension(ext).unwrap();
+        }
+        writer.set_additional_xml(root_xml.to_vec());
+        let pc = &pointclouds[0];
+        let mut pc_writer = writer
+            .add_pointcloud("pc_guid", pc.prototype.clone())
+            .unwrap();
+        pc_writer.set_additional_xml(pc.additional_xml.clone());
+        pc_writer.add_point(point).unwrap();
+        pc_writer.finalize().unwrap();
+        let mut img_writer = writer.add_image("img_guid").unwrap();
+        img_writer.set_additional_xml(images[0].additional_xml.clone());
+        img_writer
+            .add_visual_reference(
+                ImageFormat::Png,
+                &mut Cursor::new(vec![0]),
+                properties,
+                None,
+            )
+            .unwrap();
+        img_writer.finalize().unwrap();
+        writer.finalize().unwrap();
+    }
+
+    {
+        let copy = E57Reader::from_file(copy_path).unwrap();
+        assert_eq!(copy.additional_xml(), root_xml);
+        assert_eq!(copy.pointclouds()[0].additional_xml, root_xml);
+        assert_eq!(copy.images()[0].additional_xml, root_xml);
+    }
+
+    {
+        // Elements with unregistered namespaces are rejected
+        let mut writer = E57Writer::from_file(copy_path, "copy_guid").unwrap();
+        writer.set_additional_xml(root_xml.to_vec());
+        assert!(writer.finalize().is_err());
+    }
+
+    remove_file(original_path).unwrap();
+    remove_file(copy_path).unwrap();
+}
@@ -1,9 +1,11 @@
 use e57::{
-    CartesianCoordinate, Color, ColorLimits, E57Reader, ImageFormat, IntensityLimits, Point,
-    Projection, RawValues, Record, RecordDataType, RecordName, RecordValue, Result,
+    CartesianCoordinate, Color, ColorLimits, Column, ColumnBuffers, CrcValidation, E57Reader,
+    E57ReaderOptions, ImageFormat, IntensityLimits, Normal, Point, PointFilter, Projection,
+    Quaternion, RawValues, Record, RecordDataType, RecordName, RecordValue, Result,
     SphericalCoordinate,
 };
 use std::fs::File;
+use std::io::Cursor;
 
 #[test]
 fn header() {
@@ -28,6 +30,103 @@ fn validate_crc() {
     assert!(E57Reader::validate_crc(file).is_err());
 }
 
+#[test]
+fn reader_options() {
+    let path = "testdata/bunnyDouble.e57";
+    let expected: Vec<RawValues> = {
+        let mut reader = E57Reader::from_file(path).unwrap();
+        let pc = reader.pointclouds().first().unwrap().clone();
+        let iter = reader.pointcloud_raw(&pc).unwrap();
+        iter.collect::<Result<_>>().unwrap()
+    };
+    let read_all = |data: &[u8], options: E57ReaderOptions| -> Result<Vec<RawValues>> {
+        let mut reader = E57Reader::with_options(Cursor::new(data), options)?;
+        let pc = reader.pointclouds().first().unwrap().clone();
+        reader.pointcloud_raw(&pc)?.collect()
+    };
+
+    // Break the checksum of the fourth page, which contains point data
+    let mut data = std::fs::read(path).unwrap();
+    data[4 * 1024 - 1] ^= 0xFF;
+    assert!(read_all(&data, E57ReaderOptions::new()).is_err());
+    let never = E57ReaderOptions::new().crc_validation(CrcValidation::Never);
+    assert_eq!(read_all(&data, never).unwrap(), expected);
+    let sampled = E57ReaderOptions::new().crc_validation(CrcValidation::Sampled(2));
+    assert_eq!(read_all(&data, sampled.clone()).unwrap(), expected);
+
+    // Sampling still detects errors on checked pages
+    data[5 * 1024 - 1] ^= 0xFF;
+    assert!(read_all(&data, sampled).is_err());
+
+    // Limits for XML and page size
+    let options = E57ReaderOptions::new().max_xml_size(1000);
+    assert!(E57Reader::from_file_with_options(path, options).is_err());
+    let options = E57ReaderOptions::new().max_page_size(512);
+    assert!(E57Reader::from_file_with_options(path, options).is_err());
+    let options = E57ReaderOptions::new().buffer_size(1024);
+    assert!(E57Reader::from_file_with_options(path, options).is_ok());
+}
+
+#[test]
+fn recover_corrupt_pages() {
+    let path = "testdata/bunnyDouble.e57";
+    let expected: Vec<RawValues> = {
+        let mut reader = E57Reader::from_file(path).unwrap();
+        let pc = reader.pointclouds().first().unwrap().clone();
+        let iter = reader.pointcloud_raw(&pc).unwrap();
+        iter.collect::<Result<_>>().unwrap()
+    };
+
+    // Break some point data in the middle of the point cloud
+    let mut data = std::fs::read(path).unwrap();
+    data[100 * 1024 + 500] ^= 0xFF;
+    let mut reader = E57Reader::with_options(Cursor::new(&data), E57ReaderOptions::new()).unwrap();
+    let pc = reader.pointclouds().first().unwrap().clone();
+    let result: Result<Vec<RawValues>> = reader.pointcloud_raw(&pc).unwrap().collect();
+    assert!(result.is_err());
+
+    // Recovery mode skips the affected points
+    let options = E57ReaderOptions::new().recover_corrupt_pages(true);
+    let mut reader = E57Reader::with_options(Cursor::new(&data), options).unwrap();
+    let mut iter = reader.pointcloud_raw(&pc).unwrap();
+    let points: Vec<RawValues> = iter.by_ref().collect::<Result<_>>().unwrap();
+    let lost = iter.lost_points() as usize;
+    assert_eq!(iter.corrupt_pages(), &[100]);
+    assert!(lost > 0);
+    assert_eq!(points.len() + lost, expected.len());
+
+    // All points before and after the lost points are intact
+    let first_lost = points
+        .iter()
+        .zip(&expected)
+        .position(|(p, e)| p != e)
+        .unwrap();
+    assert_eq!(points[..first_lost], expected[..first_lost]);
+    assert_eq!(points[first_lost..], expected[first_lost + lost..]);
+}
+
+#[test]
+fn blobs_ignore_recovery_mode() {
+    // Break some image data
+    let mut data = std::fs::read("testdata/tiny_pc_and_images.e57").unwrap();
+    data[3 * 1024 + 10] ^= 0xFF;
+
+    let options = E57ReaderOptions::new().recover_corrupt_pages(true);
+    let mut reader = E57Reader::with_options(Cursor::new(&data), options.clone()).unwrap();
+    let blob = reader.images()[0]
+        .visual_reference
+        .clone()
+        .unwrap()
+        .blob
+        .data;
+    let mut blob_dump = Vec::new();
+    assert!(reader.blob(&blob, &mut blob_dump).is_err());
+
+    let reader = E57Reader::from_source_with_options(data, options).unwrap();
+    let mut blob_dump = Vec::new();
+    assert!(reader.blob_shared(&blob, &mut blob_dump).is_err());
+}
+
 #[test]
 fn raw_xml() {
     let reader = E57Reader::from_file("testdata/bunnyDouble.e57").unwrap();
@@ -38,6 +137,14 @@ fn raw_xml() {
 
     assert_eq!(xml.len(), 2172);
     assert_eq!(xml.len(), header.xml_length as usize);
+
+    // Options are applied
+    let reader = File::open("testdata/bunnyDouble.e57").unwrap();
+    let options = E57ReaderOptions::new().max_xml_size(2000);
+    assert!(E57Reader::raw_xml_with_options(reader, options).is_err());
+    let reader = File::open("testdata/bunnyDouble.e57").unwrap();
+    let options = E57ReaderOptions::new().max_page_size(512);
+    assert!(E57Reader::raw_xml_with_options(reader, options).is_err());
 }
 
 #[test]
@@ -240,6 +347,289 @@ fn iterator_size_hint() {
     assert_eq!(points.unwrap().len(), 2089);
 }
 
+#[test]
+fn raw_iterator_seek() {
+    let files = [
+        "testdata/bunnyDouble.e57",
+        "testdata/bunnyInt19.e57",
+        "testdata/tinyCartesianFloatRgb.e57",
+    ];
+    for file in files {
+        let mut reader = E57Reader::from_file(file).unwrap();
+        let pcs = reader.pointclouds();
+        let pc = pcs.first().unwrap();
+        let all: Vec<RawValues> = reader
+            .pointcloud_raw(pc)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+
+        let mut iter = reader.pointcloud_raw(pc).unwrap();
+        for index in [pc.records / 2, 7, 0, pc.records - 1, 1234] {
+            iter.seek_to_point(index).unwrap();
+            assert_eq!(iter.size_hint().0 as u64, pc.records - index);
+            for expected in all.iter().skip(index as usize).take(100) {
+                assert_eq!(&iter.next().unwrap().unwrap(), expected);
+            }
+        }
+
+        // Seeking to the end finishes the iteration
+        iter.seek_to_point(pc.records).unwrap();
+        assert!(iter.next().is_none());
+
+        // Seeking behind the end is an error
+        assert!(iter.seek_to_point(pc.records + 1).is_err());
+    }
+}
+
+#[test]
+fn raw_read_batch() {
+    let files = [
+        "testdata/bunnyDouble.e57",
+        "testdata/bunnyInt19.e57",
+        "testdata/tinyCartesianFloatRgb.e57",
+    ];
+    for file in files {
+        let mut reader = E57Reader::from_file(file).unwrap();
+        let pcs = reader.pointclouds();
+        let pc = pcs.first().unwrap();
+        let all: Vec<RawValues> = reader
+            .pointcloud_raw(pc)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+
+        let mut iter = reader.pointcloud_raw(pc).unwrap();
+        let mut buffers = ColumnBuffers::new(&pc.prototype);
+        let mut offset = 0;
+        loop {
+            let read = iter.read_batch(&mut buffers, 1000).unwrap();
+            assert_eq!(read, buffers.len());
+            if read == 0 {
+                break;
+            }
+            for (i, column) in buffers.columns().iter().enumerate() {
+                let data_type = &pc.prototype[i].data_type;
+                for j in 0..read {
+                    let expected = &all[offset + j][i];
+                    match column {
+                        Column::F64(values) => {
+                            assert_eq!(values[j], expected.to_f64(data_type).unwrap())
+                        }
+                        Column::I64(values) => {
+                            assert_eq!(values[j], expected.to_i64(data_type).unwrap())
+                        }
+                        Column::String(_) => panic!("Unexpected string column"),
+                    }
+                }
+            }
+            offset += read;
+        }
+        assert_eq!(offset as u64, pc.records);
+    }
+
+    // Buffers for a different prototype are rejected
+    let mut reader = E57Reader::from_file("testdata/bunnyDouble.e57").unwrap();
+    let pc = reader.pointclouds().first().unwrap().clone();
+    let mut iter = reader.pointcloud_raw(&pc).unwrap();
+    let mut buffers = ColumnBuffers::new(&pc.prototype[..2]);
+    assert!(iter.read_batch(&mut buffers, 10).is_err());
+}
+
+#[test]
+fn raw_iterator_projected() {
+    let mut reader = E57Reader::from_file("testdata/tinyCartesianFloatRgb.e57").unwrap();
+    let pcs = reader.pointclouds();
+    let pc = pcs.first().unwrap();
+    let all: Vec<RawValues> = reader
+        .pointcloud_raw(pc)
+        .unwrap()
+        .collect::<Result<_>>()
+        .unwrap();
+
+    // Selected records are returned in prototype order
+    let selection = [RecordName::ColorBlue, RecordName::CartesianX];
+    let indices: Vec<usize> = pc
+        .prototype
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| selection.contains(&r.name))
+        .map(|(i, _)| i)
+        .collect();
+    assert_eq!(indices.len(), 2);
+    let iter = reader.pointcloud_raw_projected(pc, &selection).unwrap();
+    let names: Vec<RecordName> = iter.prototype().iter().map(|r| r.name.clone()).collect();
+    assert_eq!(names, vec![RecordName::CartesianX, RecordName::ColorBlue]);
+    let projected: Vec<RawValues> = iter.collect::<Result<_>>().unwrap();
+    assert_eq!(projected.len(), all.len());
+    for (p, a) in projected.iter().zip(&all) {
+        let expected: RawValues = indices.iter().map(|i| a[*i].clone()).collect();
+        assert_eq!(p, &expected);
+    }
+
+    // Projected batches and seeking
+    let mut iter = reader
+        .pointcloud_raw_projected(pc, &[RecordName::CartesianZ])
+        .unwrap();
+    iter.seek_to_point(100).unwrap();
+    let mut buffers = ColumnBuffers::new(iter.prototype());
+    assert_eq!(iter.read_batch(&mut buffers, 10).unwrap(), 10);
+    let z = buffers.f64(&RecordName::CartesianZ).unwrap();
+    let z_index = pc
+        .prototype
+        .iter()
+        .position(|r| r.name == RecordName::CartesianZ)
+        .unwrap();
+    for (i, value) in z.iter().enumerate() {
+        let expected = all[100 + i][z_index]
+            .to_f64(&pc.prototype[z_index].data_type)
+            .unwrap();
+        assert_eq!(*value, expected);
+    }
+
+    // Unknown and duplicated records are rejected
+    assert!(reader
+        .pointcloud_raw_projected(pc, &[RecordName::TimeStamp])
+        .is_err());
+    assert!(reader
+        .pointcloud_raw_projected(pc, &[RecordName::CartesianX, RecordName::CartesianX])
+        .is_err());
+}
+
+#[test]
+fn parallel_iterator() {
+    let files = [
+        "testdata/bunnyDouble.e57",
+        "testdata/bunnyInt19.e57",
+        "testdata/bunnyInt21.e57",
+        "testdata/tinyCartesianFloatRgb.e57",
+        "testdata/tiny_spherical.e57",
+        "testdata/empty_pc.e57",
+    ];
+    for file in files {
+        let mut reader = E57Reader::from_file(file).unwrap();
+        let pcs = reader.pointclouds();
+        let pc = pcs.first().unwrap();
+        let all: Vec<RawValues> = reader
+            .pointcloud_raw(pc)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+
+        for (threads, chunk_size) in [(0, 100000), (3, 1000), (2, 7)] {
+            let mut iter = reader.pointcloud_raw_parallel(pc, threads).unwrap();
+            iter.chunk_size(chunk_size);
+            assert_eq!(iter.size_hint().0, all.len());
+            let points: Vec<RawValues> = iter.collect::<Result<_>>().unwrap();
+            assert_eq!(points, all);
+        }
+
+        // Batches of columns
+        let mut iter = reader.pointcloud_raw_parallel(pc, 4).unwrap();
+        iter.chunk_size(999);
+        let mut buffers = ColumnBuffers::new(&pc.prototype);
+        let mut offset = 0;
+        while iter.read_batch(&mut buffers, 1234).unwrap() > 0 {
+            for (i, column) in buffers.columns().iter().enumerate() {
+                if let Column::F64(values) = column {
+                    for (j, value) in values.iter().enumerate() {
+                        let expected = all[offset + j][i].to_f64(&pc.prototype[i].data_type);
+                        assert_eq!(*value, expected.unwrap());
+                    }
+                }
+            }
+            offset += buffers.len();
+        }
+        assert_eq!(offset, all.len());
+
+        // Workers with readers for a shared in-memory source
+        let mut reader = E57Reader::from_source(std::fs::read(file).unwrap()).unwrap();
+        let mut iter = reader.pointcloud_raw_parallel(pc, 2).unwrap();
+        iter.chunk_size(500);
+        let points: Vec<RawValues> = iter.collect::<Result<_>>().unwrap();
+        assert_eq!(points, all);
+    }
+}
+
+#[test]
+fn shared_source_concurrent_readers() {
+    fn assert_sync<T: Sync>(_: &T) {}
+
+    let file = File::open("testdata/bunnyDouble.e57").unwrap();
+    let reader = E57Reader::from_source(file).unwrap();
+    assert_sync(&reader);
+    let pcs = reader.pointclouds();
+    let pc = pcs.first().unwrap();
+    let expected: Vec<RawValues> = reader
+        .pointcloud_raw_shared(pc)
+        .unwrap()
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(expected.len() as u64, pc.records);
+
+    // Multiple readers of the same E57 reader are used at the same time
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                scope.spawn(|| {
+                    reader
+                        .pointcloud_raw_shared(pc)
+                        .unwrap()
+                        .collect::<Result<Vec<RawValues>>>()
+                        .unwrap()
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), expected);
+        }
+    });
+
+    // In-memory sources work as well
+    let data = std::fs::read("testdata/bunnyDouble.e57").unwrap();
+    let reader = E57Reader::from_source(data).unwrap();
+    let pc = reader.pointclouds().first().unwrap().clone();
+    let mut first = reader.pointcloud_simple_shared(&pc).unwrap();
+    let mut second = reader.pointcloud_simple_shared(&pc).unwrap();
+    let a = first.next().unwrap().unwrap();
+    for _ in 0..10 {
+        second.next().unwrap().unwrap();
+    }
+    let b = first.next().unwrap().unwrap();
+    let c = second.next().unwrap().unwrap();
+    let expected: Vec<Point> = reader
+        .pointcloud_simple_shared(&pc)
+        .unwrap()
+        .take(12)
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(a.cartesian, expected[0].cartesian);
+    assert_eq!(b.cartesian, expected[1].cartesian);
+    assert_eq!(c.cartesian, expected[10].cartesian);
+}
+
+#[test]
+fn simple_iterator_seek() {
+    let file = "testdata/bunnyInt21.e57";
+    let mut reader = E57Reader::from_file(file).unwrap();
+    let pcs = reader.pointclouds();
+    let pc = pcs.first().unwrap();
+    let all: Vec<Point> = reader
+        .pointcloud_simple(pc)
+        .unwrap()
+        .collect::<Result<_>>()
+        .unwrap();
+
+    let mut iter = reader.pointcloud_simple(pc).unwrap();
+    iter.next().unwrap().unwrap();
+    iter.seek_to_point(20000).unwrap();
+    let rest: Vec<Point> = iter.collect::<Result<_>>().unwrap();
+    assert_eq!(rest.len(), all.len() - 20000);
+    for (p, e) in rest.iter().zip(all.iter().skip(20000)) {
+        assert_eq!(p.cartesian, e.cartesian);
+    }
+}
+
 #[test]
 fn empty_e57_file() {
     let file = "testdata/empty.e57";
@@ -309,6 +699,22 @@ fn with_extension() {
         assert_eq!(values[4], RecordValue::Single(0.0));
         assert_eq!(values[5], RecordValue::Single(0.0));
     }
+
+    assert!(pointcloud.has_normals());
+    let points = reader
+        .pointcloud_simple(pointcloud)
+        .unwrap()
+        .collect::<Result<Vec<Point>>>()
+        .unwrap();
+    assert_eq!(points.len(), 1);
+    assert_eq!(
+        points[0].normal,
+        Some(Normal {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0
+        })
+    );
 }
 
 #[test]
@@ -1080,3 +1486,134 @@ fn cartesian_invalid_state_single() {
     );
     assert_eq!(points[2].cartesian, CartesianCoordinate::Invalid);
 }
+
+#[test]
+fn point_filters() {
+    let mut reader = E57Reader::from_file("testdata/bunnyFloat.e57").unwrap();
+    let pc = reader.pointclouds().first().unwrap().clone();
+    let all: Vec<[f64; 3]> = reader
+        .pointcloud_raw(&pc)
+        .unwrap()
+        .map(|p| {
+            let p = p.unwrap();
+            [
+                p[0].to_f64(&pc.prototype[0].data_type).unwrap(),
+                p[1].to_f64(&pc.prototype[1].data_type).unwrap(),
+                p[2].to_f64(&pc.prototype[2].data_type).unwrap(),
+            ]
+        })
+        .collect();
+
+    // Box filter with raw reader
+    let min = [-0.05, 0.05, -0.05];
+    let max = [0.05, 0.15, 0.05];
+    let inside = |p: &[f64; 3]| (0..3).all(|i| p[i] >= min[i] && p[i] <= max[i]);
+    let expected = all.iter().filter(|p| inside(p)).count();
+    assert!(expected > 0 && expected < all.len());
+    let mut iter = reader.pointcloud_raw(&pc).unwrap();
+    iter.add_filter(PointFilter::Box { min, max }).unwrap();
+    let filtered: Vec<RawValues> = iter.collect::<Result<_>>().unwrap();
+    assert_eq!(filtered.len(), expected);
+
+    // Oriented box with a non-unit identity rotation matches the axis-aligned box
+    let mut iter = reader.pointcloud_raw(&pc).unwrap();
+    iter.add_filter(PointFilter::OrientedBox {
+        center: [0.0, 0.1, 0.0],
+        half_size: [0.05, 0.05, 0.05],
+        rotation: Quaternion {
+            w: 2.0,
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        },
+    })
+    .unwrap();
+    assert_eq!(iter.count(), expected);
+
+    // Sphere filter with simple reader
+    let center = [0.0, 0.1, 0.0];
+    let radius = 0.05;
+    let expected = all
+        .iter()
+        .filter(|p| (0..3).map(|i| (p[i] - center[i]).powi(2)).sum::<f64>() <= radius * radius)
+        .count();
+    assert!(expected > 0);
+    let mut iter = reader.pointcloud_simple(&pc).unwrap();
+    iter.add_filter(PointFilter::Sphere { center, radius })
+        .unwrap();
+    let points: Vec<Point> = iter.collect::<Result<_>>().unwrap();
+    assert_eq!(points.len(), expected);
+
+    // Custom filter combined with box filter in batch mode
+    let expected = all.iter().filter(|p| inside(p) && p[0] > 0.0).count();
+    let mut iter = reader.pointcloud_raw(&pc).unwrap();
+    iter.add_filter(PointFilter::Box { min, max }).unwrap();
+    iter.add_filter(PointFilter::custom(|proto, values| {
+        values[0].to_f64(&proto[0].data_type).unwrap() > 0.0
+    }))
+    .unwrap();
+    let mut buffers = ColumnBuffers::new(iter.prototype());
+    let mut count = 0;
+    loop {
+        let read = iter.read_batch(&mut buffers, 1000).unwrap();
+        if read == 0 {
+            break;
+        }
+        count += read;
+    }
+    assert_eq!(count, expected);
+
+    // Box outside of the point cloud bounds
+    let mut iter = reader.pointcloud_simple(&pc).unwrap();
+    iter.add_filter(PointFilter::Box {
+        min: [10.0, 10.0, 10.0],
+        max: [11.0, 11.0, 11.0],
+    })
+    .unwrap();
+    assert_eq!(iter.size_hint(), (0, Some(0)));
+    assert_eq!(iter.count(), 0);
+
+    // Filters that require missing records
+    let mut iter = reader.pointcloud_raw(&pc).unwrap();
+    assert!(iter
+        .add_filter(PointFilter::Intensity { min: 0.5 })
+        .is_err());
+    assert!(iter
+        .add_filter(PointFilter::ExcludeInvalid(RecordName::IsColorInvalid))
+        .is_err());
+}
+
+#[test]
+fn point_filters_attributes() {
+    let mut reader = E57Reader::from_file("testdata/integer_intensity.e57").unwrap();
+    let pc = reader.pointclouds().first().unwrap().clone();
+    let all: Vec<Point> = reader
+        .pointcloud_simple(&pc)
+        .unwrap()
+        .collect::<Result<_>>()
+        .unwrap();
+    let mut iter = reader.pointcloud_simple(&pc).unwrap();
+    iter.add_filter(PointFilter::Intensity { min: 1.0 })
+        .unwrap();
+    let filtered: Vec<Point> = iter.collect::<Result<_>>().unwrap();
+    assert!(!filtered.is_empty());
+    assert!(filtered.len() < all.len());
+
+    let mut reader = E57Reader::from_file("testdata/cartesian_invalid_state_single.e57").unwrap();
+    let pc = reader.pointclouds().first().unwrap().clone();
+    let mut iter = reader.pointcloud_simple(&pc).unwrap();
+    iter.add_filter(PointFilter::ExcludeInvalid(
+        RecordName::CartesianInvalidState,
+    ))
+    .unwrap();
+    let points: Vec<Point> = iter.collect::<Result<_>>().unwrap();
+    assert_eq!(points.len(), 1);
+    assert_eq!(
+        points[0].cartesian,
+        CartesianCoordinate::Valid {
+            x: -1.0,
+            y: -2.0,
+            z: -3.0
+        }
+    );
+}
@@ -1,7 +1,8 @@
 use e57::{
-    Blob, CartesianCoordinate, DateTime, E57Reader, E57Writer, Extension, ImageFormat, Point,
-    Projection, Quaternion, RawValues, Record, RecordDataType, RecordName, RecordValue, Result,
-    SphericalImageProperties, Transform, Translation, VisualReferenceImageProperties,
+    Blob, CartesianCoordinate, DateTime, E57Editor, E57Reader, E57Salvager, E57Writer, Extension,
+    ImageFormat, Normal, Point, Projection, Quaternion, RawValues, Record, RecordDataType,
+    RecordName, RecordValue, Result, SphericalImageProperties, StructuredGrid, Subsampling,
+    Transform, Translation, VisualReferenceImageProperties,
 };
 use std::f32::consts::PI;
 use std::fs::{remove_file, File};
@@ -213,6 +214,433 @@ fn copy_tiny() {
     remove_file(out_path).unwrap();
 }
 
+#[test]
+fn write_read_index_packets() {
+    let in_path = Path::new("testdata/bunnyInt19.e57");
+    let out_path = Path::new("write_read_index_packets.e57");
+
+    // Only keep the Cartesian coordinates with their 19 bit integer values
+    let (org_points, prototype) = {
+        let mut reader = E57Reader::from_file(in_path).unwrap();
+        let pcs = reader.pointclouds();
+        let pc = pcs.first().unwrap().clone();
+        let iter = reader.pointcloud_raw(&pc).unwrap();
+        let points: Vec<RawValues> = iter
+            .map(|p| p.unwrap().into_iter().take(3).collect())
+            .collect();
+        (points, pc.prototype[..3].to_vec())
+    };
+
+    {
+        let mut writer = E57Writer::from_file(out_path, "file_guid").unwrap();
+        let mut pc_writer = writer.add_pointcloud("pc_guid", prototype).unwrap();
+        pc_writer.write_index_packets(true);
+        for p in &org_points {
+            pc_writer.add_point(p.clone()).unwrap();
+        }
+        pc_writer.finalize().unwrap();
+        writer.finalize().unwrap();
+    }
+
+    let mut reader = E57Reader::from_file(out_path).unwrap();
+    let pcs = reader.pointclouds();
+    let pc = pcs.first().unwrap();
+
+    // Index offset in compressed vector section header must be set
+    let content = std::fs::read(out_path).unwrap();
+    let index_offset_pos = pc.file_offset as usize + 24;
+    let index_offset = &content[index_offset_pos..index_offset_pos + 8];
+    assert_ne!(u64::from_le_bytes(index_offset.try_into().unwrap()), 0);
+
+    // Sequential reading is not affected by the index packets
+    let points: Vec<RawValues> = reader
+        .pointcloud_raw(pc)
+        .unwrap()
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(points, org_points);
+
+    // Seeking uses the index packets
+    let mut iter = reader.pointcloud_raw(pc).unwrap();
+    for index in [20000, 3, 30570, 12345] {
+        iter.seek_to_point(index).unwrap();
+        let point = iter.next().unwrap().unwrap();
+        assert_eq!(point, org_points[index as usize]);
+    }
+
+    remove_file(out_path).unwrap();
+}
+
+#[test]
+fn subsampling() {
+    let in_path = Path::new("testdata/bunnyInt19.e57");
+    let out_path = Path::new("subsampling.e57");
+
+    let (org_points, prototype) = {
+        let mut reader = E57Reader::from_file(in_path).unwrap();
+        let pcs = reader.pointclouds();
+        let pc = pcs.first().unwrap().clone();
+        let iter = reader.pointcloud_raw(&pc).unwrap();
+        let points: Vec<RawValues> = iter
+            .map(|p| p.unwrap().into_iter().take(3).collect())
+            .collect();
+        (points, pc.prototype[..3].to_vec())
+    };
+
+    // Every Nth point, large steps skip whole data packets if there are index packets
+    for index_packets in [false, true] {
+        {
+            let mut writer = E57Writer::from_file(out_path, "file_guid").unwrap();
+            let mut pc_writer = writer.add_pointcloud("pc_guid", prototype.clone()).unwrap();
+            pc_writer.write_index_packets(index_packets);
+            for p in &org_points {
+                pc_writer.add_point(p.clone()).unwrap();
+            }
+            pc_writer.finalize().unwrap();
+            writer.finalize().unwrap();
+        }
+
+        let mut reader = E57Reader::from_file(out_path).unwrap();
+        let pc = reader.pointclouds().first().unwrap().clone();
+        for step in [1, 7, 5000] {
+            let mut iter = reader.pointcloud_raw(&pc).unwrap();
+            iter.subsample(Subsampling::EveryNth(step)).unwrap();
+            let points: Vec<RawValues> = iter.collect::<Result<_>>().unwrap();
+            let expected: Vec<RawValues> =
+                org_points.iter().step_by(step as usize).cloned().collect();
+            assert_eq!(points, expected);
+
+            let mut iter = reader.pointcloud_simple(&pc).unwrap();
+            iter.subsample(Subsampling::EveryNth(step)).unwrap();
+            assert_eq!(iter.count(), expected.len());
+        }
+    }
+
+    let mut reader = E57Reader::from_file(out_path).unwrap();
+    let pc = reader.pointclouds().first().unwrap().clone();
+
+    // Random selection is deterministic for the same seed
+    let random = |reader: &mut E57Reader<_>, seed: u64| -> Vec<RawValues> {
+        let mut iter = reader.pointcloud_raw(&pc).unwrap();
+        let mode = Subsampling::Random {
+            fraction: 0.1,
+            seed,
+        };
+        iter.subsample(mode).unwrap();
+        iter.collect::<Result<_>>().unwrap()
+    };
+    let first = random(&mut reader, 1);
+    assert_eq!(first, random(&mut reader, 1));
+    assert_ne!(first, random(&mut reader, 2));
+    let expected = org_points.len() as f64 * 0.1;
+    assert!((first.len() as f64 - expected).abs() < expected * 0.1);
+
+    // Voxel grid keeps one point per cell
+    let mut iter = reader.pointcloud_simple(&pc).unwrap();
+    iter.apply_pose(false);
+    iter.subsample(Subsampling::Voxel { size: 0.01 }).unwrap();
+    let points: Vec<Point> = iter.collect::<Result<_>>().unwrap();
+    assert!(!points.is_empty() && points.len() < org_points.len());
+    let mut cells = std::collections::HashSet::new();
+    for p in &points {
+        if let CartesianCoordinate::Valid { x, y, z } = p.cartesian {
+            let cell = [x, y, z].map(|c| (c / 0.01).floor() as i64);
+            assert!(cells.insert(cell));
+        }
+    }
+
+    // Grid mode requires row and column indices
+    let mut iter = reader.pointcloud_raw(&pc).unwrap();
+    let mode = Subsampling::Grid {
+        row_stride: 2,
+        column_stride: 2,
+    };
+    assert!(iter.subsample(mode).is_err());
+
+    remove_file(out_path).unwrap();
+}
+
+#[test]
+fn subsampling_grid() {
+    let path = Path::new("subsampling_grid.e57");
+    let (rows, columns) = (10, 15);
+
+    {
+        let mut writer = E57Writer::from_file(path, "file_guid").unwrap();
+        let prototype = vec![
+            Record::CARTESIAN_X_F32,
+            Record::CARTESIAN_Y_F32,
+            Record::CARTESIAN_Z_F32,
+            Record {
+                name: RecordName::RowIndex,
+                data_type: RecordDataType::Integer { min: 0, max: 100 },
+            },
+            Record {
+                name: RecordName::ColumnIndex,
+                data_type: RecordDataType::Integer { min: 0, max: 100 },
+            },
+        ];
+        let mut pc_writer = writer.add_pointcloud("pc_guid", prototype).unwrap();
+        for row in 0..rows {
+            for column in 0..columns {
+                pc_writer
+                    .add_point(vec![
+                        RecordValue::Single(column as f32),
+                        RecordValue::Single(row as f32),
+                        RecordValue::Single(0.0),
+                        RecordValue::Integer(row),
+                        RecordValue::Integer(column),
+                    ])
+                    .unwrap();
+            }
+        }
+        pc_writer.finalize().unwrap();
+        writer.finalize().unwrap();
+    }
+
+    let mut reader = E57Reader::from_file(path).unwrap();
+    let pc = reader.pointclouds().first().unwrap().clone();
+    let mut iter = reader.pointcloud_simple(&pc).unwrap();
+    iter.subsample(Subsampling::Grid {
+        row_stride: 3,
+        column_stride: 5,
+    })
+    .unwrap();
+    let points: Vec<Point> = iter.collect::<Result<_>>().unwrap();
+    assert_eq!(points.len(), 4 * 3);
+    for p in &points {
+        assert_eq!(p.row % 3, 0);
+        assert_eq!(p.column % 5, 0);
+    }
+
+    remove_file(path).unwrap();
+}
+
+#[test]
+fn structured_grid() {
+    let path = Path::new("structured_grid.e57");
+
+    {
+        let mut writer = E57Writer::from_file(path, "file_guid").unwrap();
+        let int = |min, max| RecordDataType::Integer { min, max };
+        let prototype = vec![
+            Record::CARTESIAN_X_F32,
+            Record::CARTESIAN_Y_F32,
+            Record::CARTESIAN_Z_F32,
+            Record {
+                name: RecordName::RowIndex,
+                data_type: int(0, 100),
+            },
+            Record {
+                name: RecordName::ColumnIndex,
+                data_type: int(0, 100),
+            },
+            Record {
+                name: RecordName::ReturnIndex,
+                data_type: int(0, 1),
+            },
+            Record {
+                name: RecordName::ReturnCount,
+                data_type: int(1, 2),
+            },
+        ];
+        let mut pc_writer = writer.add_pointcloud("pc_guid", prototype).unwrap();
+        for row in 2..5 {
+            for column in 10..14 {
+                // Leave a hole in the middle and add a second return to the first cell
+                if row == 3 && column == 11 {
+                    continue;
+                }
+                let returns = if row == 2 && column == 10 { 2 } else { 1 };
+                for ret in 0..returns {
+                    pc_writer
+                        .add_point(vec![
+                            RecordValue::Single(column as f32),
+                            RecordValue::Single(row as f32),
+                            RecordValue::Single(ret as f32),
+                            RecordValue::Integer(row),
+                            RecordValue::Integer(column),
+                            RecordValue::Integer(ret),
+                            RecordValue::Integer(returns),
+                        ])
+                        .unwrap();
+                }
+            }
+        }
+        pc_writer.finalize().unwrap();
+        writer.finalize().unwrap();
+    }
+
+    let mut reader = E57Reader::from_file(path).unwrap();
+    let pc = reader.pointclouds().first().unwrap().clone();
+    let grid = reader.structured_grid(&pc).unwrap();
+    assert_eq!(grid.rows(), 3);
+    assert_eq!(grid.columns(), 4);
+    assert_eq!(grid.returns(), 2);
+    assert_eq!(grid.row_offset(), 2);
+    assert_eq!(grid.column_offset(), 10);
+    assert_eq!(grid.points().len(), 12);
+
+    let p = grid.get(2, 3).unwrap();
+    assert_eq!((p.row, p.column), (4, 13));
+    assert!(grid.get(3, 0).is_none());
+    assert!(grid.get(0, 4).is_none());
+    assert!(!grid.is_valid(1, 1));
+    assert_eq!(grid.returns_at(0, 0).count(), 2);
+    assert_eq!(grid.get_return(0, 0, 1).unwrap().return_index, 1);
+    assert!(grid.get_return(0, 1, 1).is_none());
+
+    let mask = grid.validity_mask();
+    assert_eq!(mask.len(), 12);
+    assert_eq!(mask.iter().filter(|v| !**v).count(), 1);
+    assert!(!mask[4 + 1]);
+
+    assert_eq!(grid.neighbours(0, 0).count(), 2);
+    assert_eq!(grid.neighbours(1, 2).count(), 7);
+    assert_eq!(grid.neighbours(0, 1).count(), 4);
+
+    assert_eq!(grid.row(1).count(), 4);
+    assert_eq!(grid.row(1).filter(|p| p.is_none()).count(), 1);
+    assert_eq!(grid.column(3).flatten().count(), 3);
+    assert_eq!(grid.row(3).count(), 0);
+
+    // Negative indices are shifted by the offsets
+    let shifted = grid.points().iter().cloned().map(|mut p| {
+        p.row -= 10;
+        p.column -= 20;
+        Ok(p)
+    });
+    let grid = StructuredGrid::from_points(shifted, None).unwrap();
+    assert_eq!(grid.rows(), 3);
+    assert_eq!(grid.columns(), 4);
+    assert_eq!(grid.row_offset(), -8);
+    assert_eq!(grid.column_offset(), -10);
+
+    // Index ranges that do not fit into a grid are rejected
+    let mut first = grid.points()[0].clone();
+    let mut last = first.clone();
+    first.row = i64::MIN;
+    last.row = i64::MAX;
+    assert!(StructuredGrid::from_points([Ok(first), Ok(last)], None).is_err());
+
+    remove_file(path).unwrap();
+}
+
+#[test]
+fn write_read_string_attributes() {
+    let out_path = Path::new("write_read_string_attributes.e57");
+    let label = |i: usize| match i % 3 {
+        0 => format!("{i}").repeat(50),
+        1 => format!("Pünktchen {i}"),
+        _ => String::new(),
+    };
+
+    for index_packets in [false, true] {
+        {
+            let mut writer = E57Writer::from_file(out_path, "file_guid").unwrap();
+            let ext = Extension {
+                namespace: "ext".to_owned(),
+                url: "https://www.corp.com/ext".to_owned(),
+            };
+            writer.register_extension(ext.clone()).unwrap();
+            let prototype = vec![
+                Record::CARTESIAN_X_F64,
+                Record::CARTESIAN_Y_F64,
+                Record::CARTESIAN_Z_F64,
+                Record {
+                    name: RecordName::Unknown {
+                        namespace: ext.namespace,
+                        name: String::from("label"),
+                    },
+                    data_type: RecordDataType::String,
+                },
+            ];
+            let mut pc_writer = writer.add_pointcloud("pc_guid", prototype).unwrap();
+            pc_writer.write_index_packets(index_packets);
+            for i in 0..10000 {
+                pc_writer
+                    .add_point(vec![
+                        RecordValue::Double(i as f64),
+                        RecordValue::Double(0.0),
+                        RecordValue::Double(0.0),
+                        RecordValue::String(label(i)),
+                    ])
+                    .unwrap();
+            }
+            pc_writer.finalize().unwrap();
+            writer.finalize().unwrap();
+        }
+
+        {
+            let mut reader = E57Reader::from_file(out_path).unwrap();
+            let pcs = reader.pointclouds();
+            let pc = pcs.first().unwrap();
+            assert_eq!(pc.records, 10000);
+            assert!(matches!(pc.prototype[3].data_type, RecordDataType::String));
+
+            let points: Vec<RawValues> = reader
+                .pointcloud_raw(pc)
+                .unwrap()
+                .collect::<Result<_>>()
+                .unwrap();
+            assert_eq!(points.len(), 10000);
+            for (i, p) in points.iter().enumerate() {
+                assert_eq!(p[0], RecordValue::Double(i as f64));
+                assert_eq!(p[3], RecordValue::String(label(i)));
+            }
+
+            let mut parallel = reader.pointcloud_raw_parallel(pc, 3).unwrap();
+            parallel.chunk_size(1000);
+            let parallel_points: Vec<RawValues> = parallel.collect::<Result<_>>().unwrap();
+            assert_eq!(parallel_points, points);
+
+            let mut iter = reader.pointcloud_raw(pc).unwrap();
+            for index in [7777, 3, 9999, 4242] {
+                iter.seek_to_point(index).unwrap();
+                let point = iter.next().unwrap().unwrap();
+                assert_eq!(point, points[index as usize]);
+            }
+        }
+    }
+
+    remove_file(out_path).unwrap();
+}
+
+#[test]
+fn string_attribute_too_big() {
+    let path = Path::new("string_attribute_too_big.e57");
+    let mut writer = E57Writer::from_file(path, "file_guid").unwrap();
+    let ext = Extension::new("ext", "https://www.corp.com/ext");
+    writer.register_extension(ext.clone()).unwrap();
+    let prototype = vec![
+        Record::CARTESIAN_X_F64,
+        Record::CARTESIAN_Y_F64,
+        Record::CARTESIAN_Z_F64,
+        Record {
+            name: RecordName::Unknown {
+                namespace: ext.namespace,
+                name: String::from("label"),
+            },
+            data_type: RecordDataType::String,
+        },
+    ];
+    let mut pc_writer = writer.add_pointcloud("pc_guid", prototype).unwrap();
+    let point = |label: String| {
+        vec![
+            RecordValue::Double(0.0),
+            RecordValue::Double(0.0),
+            RecordValue::Double(0.0),
+            RecordValue::String(label),
+        ]
+    };
+    pc_writer.add_point(point("x".repeat(60_000))).unwrap();
+    let err = pc_writer.add_point(point("x".repeat(70_000))).unwrap_err();
+    assert!(err.to_string().contains("single data packet"));
+    pc_writer.finalize().unwrap();
+    writer.finalize().unwrap();
+    remove_file(path).unwrap();
+}
+
 #[test]
 fn scaled_integers() {
     let out_path = Path::new("scaled_integers.e57");
@@ -400,6 +828,22 @@ fn attribute_extensions() {
         assert_eq!(bounds.x_max.unwrap(), 1.0);
         assert_eq!(bounds.y_max.unwrap(), 2.0);
         assert_eq!(bounds.z_max.unwrap(), 3.0);
+
+        // Extension attributes with the simple reader
+        let mut iter = reader.pointcloud_simple(pc).unwrap();
+        let names = [proto[4].name.clone(), proto[3].name.clone()];
+        iter.extension_attributes(&names).unwrap();
+        let points = iter.collect::<Result<Vec<Point>>>().unwrap();
+        assert_eq!(points[0].extensions, vec![-1.0, -10.0]);
+
+        let mut iter = reader.pointcloud_simple(pc).unwrap();
+        let unknown = RecordName::Unknown {
+            namespace: "ext1".to_owned(),
+            name: "missing".to_owned(),
+        };
+        assert!(iter.extension_attributes(&[unknown]).is_err());
+        let points = iter.collect::<Result<Vec<Point>>>().unwrap();
+        assert!(points[0].extensions.is_empty());
     }
 
     remove_file(out_path).unwrap();
@@ -1196,3 +1640,831 @@ fn no_invalid_spherical_coordinates_for_bounds() {
 
     remove_file(path).unwrap();
 }
+
+#[test]
+fn read_line_groups() {
+    let path = Path::new("read_line_groups.e57");
+
+    {
+        let mut e57 = E57Writer::from_file(path, "file_guid").unwrap();
+
+        // Point cloud with three lines identified by the row index
+        let prototype = vec![
+            Record::CARTESIAN_X_F64,
+            Record::CARTESIAN_Y_F64,
+            Record::CARTESIAN_Z_F64,
+            Record {
+                name: RecordName::RowIndex,
+                data_type: RecordDataType::Integer { min: 0, max: 2 },
+            },
+        ];
+        let mut pc_writer = e57.add_pointcloud("pc_guid", prototype).unwrap();
+        for row in [0, 0, 0, 1, 1, 2] {
+            let values = vec![
+                RecordValue::Double(row as f64),
+                RecordValue::Double(0.0),
+                RecordValue::Double(0.0),
+                RecordValue::Integer(row),
+            ];
+            pc_writer.add_point(values).unwrap();
+        }
+        pc_writer.finalize().unwrap();
+
+        // Abuse a second point cloud to write the binary section with the line groups
+        let int = |max| RecordDataType::Integer { min: 0, max };
+        let prototype = vec![
+            Record {
+                name: RecordName::CartesianX,
+                data_type: int(2),
+            },
+            Record {
+                name: RecordName::CartesianY,
+                data_type: int(5),
+            },
+            Record {
+                name: RecordName::CartesianZ,
+                data_type: int(3),
+            },
+        ];
+        let mut groups_writer = e57.add_pointcloud("groups_guid", prototype).unwrap();
+        for (id, start, count) in [(0, 0, 3), (1, 3, 2), (2, 5, 1)] {
+            let values = vec![
+                RecordValue::Integer(id),
+                RecordValue::Integer(start),
+                RecordValue::Integer(count),
+            ];
+            groups_writer.add_point(values).unwrap();
+        }
+        groups_writer.finalize().unwrap();
+
+        // Reference the groups section from the first point cloud
+        e57.finalize_customized_xml(|xml| {
+            let offset = xml.split("fileOffset=\"").nth(2).unwrap();
+            let offset = offset.split('"').next().unwrap();
+            let grouping = format!(
+                "<pointGroupingSchemes type=\"Structure\"><groupingByLine type=\"Structure\">\
+                <idElementName type=\"String\"><![CDATA[rowIndex]]></idElementName>\
+                <groups type=\"CompressedVector\" fileOffset=\"{offset}\" recordCount=\"3\">\
+                <prototype type=\"Structure\">\
+                <idElementValue type=\"Integer\" minimum=\"0\" maximum=\"2\"/>\
+                <startPointIndex type=\"Integer\" minimum=\"0\" maximum=\"5\"/>\
+                <pointCount type=\"Integer\" minimum=\"0\" maximum=\"3\"/>\
+                </prototype></groups></groupingByLine></pointGroupingSchemes>\n<points "
+            );
+            Ok(xml.replacen("<points ", &grouping, 1))
+        })
+        .unwrap();
+    }
+
+    let mut e57 = E57Reader::from_file(path).unwrap();
+    let pcs = e57.pointclouds();
+    let grouping = pcs[0].line_grouping.as_ref().unwrap();
+    assert_eq!(grouping.id_element_name, "rowIndex");
+    assert_eq!(grouping.group_count, 3);
+    assert!(pcs[1].line_grouping.is_none());
+
+    let groups = e57
+        .line_groups(grouping)
+        .unwrap()
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(groups.len(), 3);
+    assert_eq!(groups[1].id, 1);
+    assert_eq!(groups[1].start_point_index, 3);
+    assert_eq!(groups[1].point_count, 2);
+    assert_eq!(groups[2].id, 2);
+    assert_eq!(groups[2].start_point_index, 5);
+    assert_eq!(groups[2].point_count, 1);
+    assert!(groups[2].cartesian_bounds.is_none());
+
+    remove_file(path).unwrap();
+}
+
+#[test]
+fn write_read_line_groups() {
+    let path = Path::new("write_read_line_groups.e57");
+
+    {
+        let mut e57 = E57Writer::from_file(path, "file_guid").unwrap();
+        let prototype = vec![
+            Record::CARTESIAN_X_F64,
+            Record::CARTESIAN_Y_F64,
+            Record::CARTESIAN_Z_F64,
+            Record {
+                name: RecordName::ColumnIndex,
+                data_type: RecordDataType::Integer { min: 0, max: 99 },
+            },
+        ];
+        let mut pc_writer = e57.add_pointcloud("pc_guid", prototype).unwrap();
+        assert!(pc_writer
+            .set_line_grouping(Some(RecordName::RowIndex))
+            .is_err());
+        pc_writer
+            .set_line_grouping(Some(RecordName::ColumnIndex))
+            .unwrap();
+        for column in 0..100 {
+            for i in 0..=column {
+                let values = vec![
+                    RecordValue::Double(column as f64),
+                    RecordValue::Double(i as f64),
+                    RecordValue::Double(-i as f64),
+                    RecordValue::Integer(column),
+                ];
+                pc_writer.add_point(values).unwrap();
+            }
+        }
+        pc_writer.finalize().unwrap();
+        e57.finalize().unwrap();
+    }
+
+    let mut e57 = E57Reader::from_file(path).unwrap();
+    let pcs = e57.pointclouds();
+    let pc = pcs.first().unwrap();
+    assert_eq!(pc.records, 5050);
+    let grouping = pc.line_grouping.as_ref().unwrap();
+    assert_eq!(grouping.id_element_name, "columnIndex");
+    assert_eq!(grouping.group_count, 100);
+
+    let groups = e57
+        .line_groups(grouping)
+        .unwrap()
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(groups.len(), 100);
+    let mut start = 0;
+    for (column, group) in groups.iter().enumerate() {
+        assert_eq!(group.id, column as i64);
+        assert_eq!(group.start_point_index, start);
+        assert_eq!(group.point_count, column as u64 + 1);
+        let bounds = group.cartesian_bounds.as_ref().unwrap();
+        assert_eq!(bounds.x_min, Some(column as f64));
+        assert_eq!(bounds.x_max, Some(column as f64));
+        assert_eq!(bounds.y_min, Some(0.0));
+        assert_eq!(bounds.y_max, Some(column as f64));
+        assert_eq!(bounds.z_min, Some(-(column as f64)));
+        assert_eq!(bounds.z_max, Some(0.0));
+        start += group.point_count;
+    }
+
+    // Points must still be readable
+    let points = e57.pointcloud_raw(pc).unwrap();
+    assert_eq!(points.count(), 5050);
+
+    remove_file(path).unwrap();
+}
+
+#[test]
+fn line_groups_must_be_contiguous() {
+    let path = Path::new("line_groups_must_be_contiguous.e57");
+    let mut e57 = E57Writer::from_file(path, "file_guid").unwrap();
+    let prototype = vec![
+        Record::CARTESIAN_X_F64,
+        Record::CARTESIAN_Y_F64,
+        Record::CARTESIAN_Z_F64,
+        Record {
+            name: RecordName::RowIndex,
+            data_type: RecordDataType::Integer { min: 0, max: 1 },
+        },
+    ];
+    let mut pc_writer = e57.add_pointcloud("pc_guid", prototype).unwrap();
+    pc_writer
+        .set_line_grouping(Some(RecordName::RowIndex))
+        .unwrap();
+    let point = |row| {
+        vec![
+            RecordValue::Double(0.0),
+            RecordValue::Double(0.0),
+            RecordValue::Double(0.0),
+            RecordValue::Integer(row),
+        ]
+    };
+    pc_writer.add_point(point(0)).unwrap();
+    pc_writer.add_point(point(1)).unwrap();
+    assert!(pc_writer.add_point(point(0)).is_err());
+    drop(e57);
+    remove_file(path).unwrap();
+}
+
+#[test]
+fn salvage_truncated_file() {
+    // Create file with an image and two point clouds in memory
+    let mut cursor = Cursor::new(Vec::new());
+    let mut e57_writer = E57Writer::new(&mut cursor, "guid_file").unwrap();
+    let mut img_writer = e57_writer.add_image("guid_image").unwrap();
+    let mut image = File::open("testdata/castle.jpg").unwrap();
+    let props = VisualReferenceImageProperties {
+        width: 100,
+        height: 100,
+    };
+    img_writer
+        .add_visual_reference(ImageFormat::Jpeg, &mut image, props, None)
+        .unwrap();
+    img_writer.finalize().unwrap();
+    let mut double_points = Vec::new();
+    let prototype = vec![
+        Record::CARTESIAN_X_F64,
+        Record::CARTESIAN_Y_F64,
+        Record::CARTESIAN_Z_F64,
+    ];
+    let mut pc_writer = e57_writer.add_pointcloud("guid_double", prototype).unwrap();
+    for i in 0..10000 {
+        let values = vec![
+            RecordValue::Double(i as f64),
+            RecordValue::Double(i as f64 * 0.5),
+            RecordValue::Double(-1.0),
+        ];
+        pc_writer.add_point(values.clone()).unwrap();
+        double_points.push(values);
+    }
+    pc_writer.finalize().unwrap();
+    let mut single_points = Vec::new();
+    let single_prototype = vec![
+        Record::CARTESIAN_X_F32,
+        Record::CARTESIAN_Y_F32,
+        Record::CARTESIAN_Z_F32,
+        Record::COLOR_RED_U8,
+        Record::COLOR_GREEN_U8,
+        Record::COLOR_BLUE_U8,
+    ];
+    let mut pc_writer = e57_writer
+        .add_pointcloud("guid_single", single_prototype.clone())
+        .unwrap();
+    for i in 0..10000 {
+        let values = vec![
+            RecordValue::Single(i as f32),
+            RecordValue::Single(1.0),
+            RecordValue::Single(2.0),
+            RecordValue::Integer(i % 256),
+            RecordValue::Integer(0),
+            RecordValue::Integer(255),
+        ];
+        pc_writer.add_point(values.clone()).unwrap();
+        single_points.push(values);
+    }
+    pc_writer.finalize().unwrap();
+    e57_writer.finalize().unwrap();
+    drop(e57_writer);
+
+    // Cut off the XML section and the end of the last point cloud
+    let mut data = cursor.into_inner();
+    data.truncate(data.len() - 20000);
+    assert!(E57Reader::new(Cursor::new(&data)).is_err());
+
+    // Find the salvageable sections
+    let mut salvager = E57Salvager::new(Cursor::new(&data)).unwrap();
+    assert_eq!(salvager.blobs().len(), 1);
+    let pcs = salvager.pointclouds().to_vec();
+    assert_eq!(pcs.len(), 2);
+    assert_eq!(pcs[0].stream_sizes, vec![80000; 3]);
+    assert_eq!(pcs[1].stream_sizes.len(), 6);
+    assert!(pcs[1].infer_prototype().is_none());
+    let pc = pcs[0]
+        .pointcloud(pcs[0].infer_prototype().unwrap())
+        .unwrap();
+    assert_eq!(pc.records, 10000);
+    let points: Vec<RawValues> = salvager
+        .pointcloud_raw(&pcs[0], &pc)
+        .unwrap()
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(points, double_points);
+
+    // Write everything into a new file
+    let mut cursor = Cursor::new(Vec::new());
+    let mut e57_writer = E57Writer::new(&mut cursor, "guid_salvaged").unwrap();
+    let report = salvager
+        .write(&mut e57_writer, Some(&single_prototype), "salvaged")
+        .unwrap();
+    e57_writer.finalize().unwrap();
+    drop(e57_writer);
+    assert_eq!(report.pointclouds, 2);
+    assert_eq!(report.skipped_pointclouds, 0);
+    assert_eq!(report.images, 1);
+    assert!(report.blobs.is_empty());
+
+    // Check salvaged content
+    let mut reader = E57Reader::new(Cursor::new(cursor.into_inner())).unwrap();
+    let images = reader.images();
+    assert_eq!(images.len(), 1);
+    let visual_ref = images[0].visual_reference.as_ref().unwrap();
+    assert_eq!(visual_ref.properties.width, 100);
+    assert_eq!(visual_ref.properties.height, 100);
+    let mut image_data = Vec::new();
+    reader.blob(&visual_ref.blob.data, &mut image_data).unwrap();
+    assert_eq!(image_data, std::fs::read("testdata/castle.jpg").unwrap());
+    let pcs = reader.pointclouds();
+    assert_eq!(pcs.len(), 2);
+    let points: Vec<RawValues> = reader
+        .pointcloud_raw(&pcs[0])
+        .unwrap()
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(points, double_points);
+    let points: Vec<RawValues> = reader
+        .pointcloud_raw(&pcs[1])
+        .unwrap()
+        .collect::<Result<_>>()
+        .unwrap();
+    assert!(!points.is_empty());
+    assert!(points.len() < single_points.len());
+    assert_eq!(points[..], single_points[..points.len()]);
+    assert_eq!(report.points, 10000 + points.len() as u64);
+}
+
+#[test]
+fn timestamps_and_returns() {
+    let path = Path::new("timestamps_and_returns.e57");
+    {
+        let mut e57_writer = E57Writer::from_file(path, "guid_file").unwrap();
+        let prototype = vec![
+            Record::CARTESIAN_X_F32,
+            Record::CARTESIAN_Y_F32,
+            Record::CARTESIAN_Z_F32,
+            Record {
+                name: RecordName::TimeStamp,
+                data_type: RecordDataType::F64,
+            },
+            Record {
+                name: RecordName::IsTimeStampInvalid,
+                data_type: RecordDataType::Integer { min: 0, max: 1 },
+            },
+            Record {
+                name: RecordName::ReturnIndex,
+                data_type: RecordDataType::Integer { min: 0, max: 3 },
+            },
+            Record {
+                name: RecordName::ReturnCount,
+                data_type: RecordDataType::Integer { min: 1, max: 4 },
+            },
+        ];
+        let mut pc_writer = e57_writer.add_pointcloud("guid_pc", prototype).unwrap();
+        pc_writer.set_acquisition_start(Some(DateTime {
+            gps_time: 1000.0,
+            atomic_reference: false,
+        }));
+        for i in 0..10 {
+            let values = vec![
+                RecordValue::Single(i as f32),
+                RecordValue::Single(0.0),
+                RecordValue::Single(0.0),
+                RecordValue::Double(i as f64 * 0.5),
+                RecordValue::Integer(if i == 5 { 1 } else { 0 }),
+                RecordValue::Integer(i % 4),
+                RecordValue::Integer(4),
+            ];
+            pc_writer.add_point(values).unwrap();
+        }
+        pc_writer.finalize().unwrap();
+        e57_writer.finalize().unwrap();
+    }
+
+    {
+        let mut e57 = E57Reader::from_file(path).unwrap();
+        let pcs = e57.pointclouds();
+        let pc = pcs.first().unwrap();
+        assert!(pc.has_timestamp());
+        assert!(pc.has_return());
+        let points: Vec<Point> = e57
+            .pointcloud_simple(pc)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(points.len(), 10);
+        for (i, p) in points.iter().enumerate() {
+            if i == 5 {
+                assert_eq!(p.timestamp, None);
+            } else {
+                assert_eq!(p.timestamp, Some(i as f64 * 0.5));
+            }
+            assert_eq!(p.return_index, i as i64 % 4);
+            assert_eq!(p.return_count, 4);
+        }
+        assert_eq!(pc.gps_time(points[2].timestamp.unwrap()), Some(1001.0));
+    }
+
+    remove_file(path).unwrap();
+}
+
+#[test]
+fn write_read_cylindrical_image() {
+    let path = Path::new("write_read_cylindrical_image.e57");
+    let properties = e57::CylindricalImageProperties {
+        width: 100,
+        height: 50,
+        radius: 2.5,
+        principal_y: 24.5,
+        pixel_width: 0.01,
+        pixel_height: 0.02,
+    };
+
+    {
+        let mut e57_writer = E57Writer::from_file(path, "guid_file").unwrap();
+        let mut img_writer = e57_writer.add_image("guid_image").unwrap();
+        img_writer
+            .add_cylindrical(
+                ImageFormat::Png,
+                &mut Cursor::new(vec![1, 2, 3]),
+                properties.clone(),
+                None,
+            )
+            .unwrap();
+        img_writer.finalize().unwrap();
+        e57_writer.finalize().unwrap();
+    }
+
+    {
+        let e57 = E57Reader::from_file(path).unwrap();
+        let img = e57.images().remove(0);
+        let rep = match img.projection.unwrap() {
+            Projection::Pinhole(_) => None,
+            Projection::Spherical(_) => None,
+            Projection::Cylindrical(c) => Some(c),
+        }
+        .unwrap();
+        assert_eq!(rep.properties.width, properties.width);
+        assert_eq!(rep.properties.height, properties.height);
+        assert_eq!(rep.properties.radius, properties.radius);
+        assert_eq!(rep.properties.principal_y, properties.principal_y);
+        assert_eq!(rep.properties.pixel_width, properties.pixel_width);
+        assert_eq!(rep.properties.pixel_height, properties.pixel_height);
+        assert_eq!(rep.blob.data.length, 3);
+    }
+
+    remove_file(path).unwrap();
+}
+
+#[test]
+fn surface_normals() {
+    let path = Path::new("surface_normals.e57");
+    let prefix_path = Path::new("surface_normals_prefix.e57");
+    {
+        let mut e57_writer = E57Writer::from_file(path, "guid_file").unwrap();
+        let incomplete = vec![
+            Record::CARTESIAN_X_F32,
+            Record::CARTESIAN_Y_F32,
+            Record::CARTESIAN_Z_F32,
+            Record::normal_x_f32(),
+        ];
+        assert!(e57_writer.add_pointcloud("guid_pc", incomplete).is_err());
+
+        let prototype = vec![
+            Record::CARTESIAN_X_F32,
+            Record::CARTESIAN_Y_F32,
+            Record::CARTESIAN_Z_F32,
+            Record::normal_x_f32(),
+            Record::normal_y_f32(),
+            Record::normal_z_f32(),
+        ];
+        let mut pc_writer = e57_writer.add_pointcloud("guid_pc", prototype).unwrap();
+        let half_sqrt2 = std::f64::consts::FRAC_1_SQRT_2;
+        pc_writer.set_transform(Some(Transform {
+            rotation: Quaternion {
+                w: half_sqrt2,
+                x: 0.0,
+                y: 0.0,
+                z: half_sqrt2,
+            },
+            translation: Translation {
+                x: 10.0,
+                y: 0.0,
+                z: 0.0,
+            },
+        }));
+        pc_writer
+            .add_point(vec![
+                RecordValue::Single(1.0),
+                RecordValue::Single(0.0),
+                RecordValue::Single(0.0),
+                RecordValue::Single(1.0),
+                RecordValue::Single(0.0),
+                RecordValue::Single(0.0),
+            ])
+            .unwrap();
+        pc_writer.finalize().unwrap();
+        e57_writer.finalize().unwrap();
+    }
+
+    {
+        // Normals are identified by the namespace URL and not by the namespace name
+        let custom = |namespace: &str, name: &str| Record {
+            name: RecordName::Unknown {
+                namespace: namespace.to_owned(),
+                name: name.to_owned(),
+            },
+            data_type: RecordDataType::NORMAL_F32,
+        };
+        let url = Extension::SURFACE_NORMALS_URL;
+        let mut writer = E57Writer::from_file(prefix_path, "guid_file").unwrap();
+        writer.register_extension(Extension::new("n", url)).unwrap();
+        writer
+            .register_extension(Extension::new("other", "http://www.example.com/other"))
+            .unwrap();
+        let incomplete = vec![
+            Record::CARTESIAN_X_F32,
+            Record::CARTESIAN_Y_F32,
+            Record::CARTESIAN_Z_F32,
+            custom("n", "normalX"),
+        ];
+        assert!(writer.add_pointcloud("guid_pc", incomplete).is_err());
+        let unrelated = vec![
+            Record::CARTESIAN_X_F32,
+            Record::CARTESIAN_Y_F32,
+            Record::CARTESIAN_Z_F32,
+            custom("other", "normalX"),
+        ];
+        let mut pc_writer = writer.add_pointcloud("guid_pc", unrelated).unwrap();
+        pc_writer.finalize().unwrap();
+
+        let prototype = vec![
+            Record::CARTESIAN_X_F32,
+            Record::CARTESIAN_Y_F32,
+            Record::CARTESIAN_Z_F32,
+            custom("n", "normalX"),
+            custom("n", "normalY"),
+            custom("n", "normalZ"),
+        ];
+        let mut pc_writer = writer.add_pointcloud("guid_pc2", prototype).unwrap();
+        pc_writer.set_transform(Some(Transform {
+            rotation: Quaternion {
+                w: std::f64::consts::FRAC_1_SQRT_2,
+                x: 0.0,
+                y: 0.0,
+                z: std::f64::consts::FRAC_1_SQRT_2,
+            },
+            translation: Translation::default(),
+        }));
+        pc_writer
+            .add_point(vec![
+                RecordValue::Single(1.0),
+                RecordValue::Single(0.0),
+                RecordValue::Single(0.0),
+                RecordValue::Single(1.0),
+                RecordValue::Single(0.0),
+                RecordValue::Single(0.0),
+            ])
+            .unwrap();
+        pc_writer.finalize().unwrap();
+        writer.finalize().unwrap();
+    }
+
+    {
+        // Reading a file that uses another namespace name for the normals
+        let mut e57 = E57Reader::from_file(prefix_path).unwrap();
+        assert!(e57.xml().contains("<n:normalX"));
+        let pcs = e57.pointclouds();
+        assert!(!pcs[0].has_normals());
+        assert!(pcs[1].has_normals());
+
+        let points: Vec<Point> = e57
+            .pointcloud_simple(&pcs[1])
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        let normal = points[0].normal.as_ref().unwrap();
+        assert!(normal.x.abs() < 1e-9);
+        assert!((normal.y - 1.0).abs() < 1e-9);
+        assert!(normal.z.abs() < 1e-9);
+    }
+
+    {
+        let mut e57 = E57Reader::from_file(path).unwrap();
+        let extensions = e57.extensions();
+        assert_eq!(extensions.len(), 1);
+        assert_eq!(
+            extensions[0].namespace,
+            Extension::SURFACE_NORMALS_NAMESPACE
+        );
+        assert_eq!(extensions[0].url, Extension::SURFACE_NORMALS_URL);
+        assert!(e57.xml().contains("<nor:normalX"));
+
+        let pcs = e57.pointclouds();
+        let pc = pcs.first().unwrap();
+        assert!(pc.has_normals());
+        assert_eq!(
+            pc.prototype[3].name,
+            RecordName::Unknown {
+                namespace: String::from("nor"),
+                name: String::from("normalX")
+            }
+        );
+
+        let mut reader = e57.pointcloud_simple(pc).unwrap();
+        reader.apply_pose(false);
+        let points: Vec<Point> = reader.collect::<Result<_>>().unwrap();
+        assert_eq!(
+            points[0].normal,
+            Some(Normal {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0
+            })
+        );
+
+        let points: Vec<Point> = e57
+            .pointcloud_simple(pc)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        let normal = points[0].normal.as_ref().unwrap();
+        assert!(normal.x.abs() < 1e-9);
+        assert!((normal.y - 1.0).abs() < 1e-9);
+        assert!(normal.z.abs() < 1e-9);
+    }
+
+    remove_file(path).unwrap();
+    remove_file(prefix_path).unwrap();
+}
+
+#[test]
+fn edit_metadata() {
+    let original_path = Path::new("testdata/tiny_pc_and_images.e57");
+    let path = Path::new("edit_metadata.e57");
+    std::fs::copy(original_path, path).unwrap();
+    let original_bytes = std::fs::read(path).unwrap();
+    let read_points = |reader: &mut E57Reader<_>| {
+        let pc = reader.pointclouds().remove(0);
+        reader
+            .pointcloud_raw(&pc)
+            .unwrap()
+            .collect::<Result<Vec<RawValues>>>()
+            .unwrap()
+    };
+    let mut original = E57Reader::from_file(original_path).unwrap();
+    let original_points = read_points(&mut original);
+
+    {
+        let mut editor = E57Editor::from_file(path).unwrap();
+        let pc = &mut editor.pointclouds_mut()[0];
+        pc.name = Some(String::from("renamed"));
+        pc.sensor_serial = Some(String::from("fixed serial"));
+        pc.transform = Some(Transform {
+            rotation: Quaternion::default(),
+            translation: Translation {
+                x: 1.0,
+                y: 2.0,
+                z: 3.0,
+            },
+        });
+        editor.images_mut()[1].name = Some(String::from("renamed image"));
+        editor.set_coordinate_metadata(Some(String::from("crs")));
+        editor.save().unwrap();
+    }
+
+    // Check modified metadata and unchanged binary data
+    E57Reader::validate_crc(File::open(path).unwrap()).unwrap();
+    let mut reader = E57Reader::from_file(path).unwrap();
+    assert_eq!(reader.guid(), original.guid());
+    assert_eq!(reader.coordinate_metadata(), Some("crs"));
+    let pc = reader.pointclouds().remove(0);
+    assert_eq!(pc.name.as_deref(), Some("renamed"));
+    assert_eq!(pc.sensor_serial.as_deref(), Some("fixed serial"));
+    assert_eq!(pc.transform.unwrap().translation.z, 3.0);
+    let images = reader.images();
+    assert_eq!(images[1].name.as_deref(), Some("renamed image"));
+    assert_eq!(images[2].name, original.images()[2].name);
+    assert_eq!(read_points(&mut reader), original_points);
+    let header = reader.header();
+    assert_eq!(header.phys_xml_offset, original_bytes.len() as u64);
+    let edited_bytes = std::fs::read(path).unwrap();
+    assert_eq!(header.phys_length, edited_bytes.len() as u64);
+    assert_eq!(
+        edited_bytes[1024..original_bytes.len()],
+        original_bytes[1024..]
+    );
+
+    {
+        // References to binary data cannot be modified
+        let mut editor = E57Editor::from_file(path).unwrap();
+        editor.pointclouds_mut()[0].records += 1;
+        assert!(editor.save().is_err());
+    }
+
+    {
+        // Blobs of images cannot be modified
+        let mut editor = E57Editor::from_file(path).unwrap();
+        let rep = editor.images_mut()[0].visual_reference.as_mut().unwrap();
+        rep.blob.data.length += 1;
+        assert!(editor.save().is_err());
+        let mut editor = E57Editor::from_file(path).unwrap();
+        editor.images_mut()[1].projection = None;
+        assert!(editor.save().is_err());
+    }
+
+    remove_file(path).unwrap();
+}
+
+#[test]
+fn edit_other_page_size() {
+    let mut bytes = std::fs::read("testdata/tiny_pc_and_images.e57").unwrap();
+    // Change the page size in the file header from 1024 to 2048 bytes
+    bytes[40..48].copy_from_slice(&2048_u64.to_le_bytes());
+    assert!(E57Editor::new(Cursor::new(bytes)).is_err());
+}
+
+#[test]
+fn append_other_page_size() {
+    let path = Path::new("append_other_page_size.e57");
+    let mut bytes = std::fs::read("testdata/tiny_pc_and_images.e57").unwrap();
+    // Change the page size in the file header from 1024 to 2048 bytes
+    bytes[40..48].copy_from_slice(&2048_u64.to_le_bytes());
+    std::fs::write(path, &bytes).unwrap();
+    assert!(E57Writer::open_append(path).is_err());
+    assert_eq!(std::fs::read(path).unwrap(), bytes);
+    remove_file(path).unwrap();
+}
+
+#[test]
+fn append_to_existing_file() {
+    let path = Path::new("append_to_existing_file.e57");
+    std::fs::copy("testdata/tiny_pc_and_images.e57", path).unwrap();
+    let mut original = E57Reader::from_file(path).unwrap();
+    let original_pc = original.pointclouds().remove(0);
+    let original_points = original
+        .pointcloud_raw(&original_pc)
+        .unwrap()
+        .collect::<Result<Vec<RawValues>>>()
+        .unwrap();
+    let original_images = original.images();
+    let original_header = original.header();
+    drop(original);
+
+    let prototype = vec![
+        Record::CARTESIAN_X_F32,
+        Record::CARTESIAN_Y_F32,
+        Record::CARTESIAN_Z_F32,
+    ];
+    let point = vec![
+        RecordValue::Single(1.0),
+        RecordValue::Single(2.0),
+        RecordValue::Single(3.0),
+    ];
+
+    // Append twice to make sure the previously appended data is kept as well
+    for i in 0..2 {
+        let mut writer = E57Writer::open_append(path).unwrap();
+        let mut pc_writer = writer
+            .add_pointcloud(&format!("appended_pc_{i}"), prototype.clone())
+            .unwrap();
+        pc_writer.add_point(point.clone()).unwrap();
+        pc_writer.finalize().unwrap();
+        let mut img_writer = writer.add_image(&format!("appended_img_{i}")).unwrap();
+        let properties = VisualReferenceImageProperties {
+            width: 1,
+            height: 1,
+        };
+        img_writer
+            .add_visual_reference(
+                ImageFormat::Png,
+                &mut Cursor::new(vec![i as u8; 100]),
+                properties,
+                None,
+            )
+            .unwrap();
+        img_writer.finalize().unwrap();
+        writer.finalize().unwrap();
+    }
+
+    E57Reader::validate_crc(File::open(path).unwrap()).unwrap();
+    let mut reader = E57Reader::from_file(path).unwrap();
+    assert_eq!(reader.header().phys_length, path.metadata().unwrap().len());
+    let pointclouds = reader.pointclouds();
+    assert_eq!(pointclouds.len(), 3);
+    assert_eq!(pointclouds[0].guid, original_pc.guid);
+    let points = reader
+        .pointcloud_raw(&pointclouds[0])
+        .unwrap()
+        .collect::<Result<Vec<RawValues>>>()
+        .unwrap();
+    assert_eq!(points, original_points);
+
+    // The first appended data overwrites the old XML section at the end of the file
+    let appended_offset = pointclouds[1].file_offset;
+    assert!(appended_offset >= original_header.phys_xml_offset);
+    assert!(appended_offset < original_header.phys_length);
+
+    for (i, pc) in pointclouds[1..].iter().enumerate() {
+        assert_eq!(pc.guid, Some(format!("appended_pc_{i}")));
+        let points = reader
+            .pointcloud_raw(pc)
+            .unwrap()
+            .collect::<Result<Vec<RawValues>>>()
+            .unwrap();
+        assert_eq!(points, vec![point.clone()]);
+    }
+
+    let images = reader.images();
+    assert_eq!(images.len(), original_images.len() + 2);
+    let first = original_images[0].visual_reference.as_ref().unwrap();
+    let mut data = Vec::new();
+    reader.blob(&first.blob.data, &mut data).unwrap();
+    assert_eq!(data.len() as u64, first.blob.data.length);
+    for (i, img) in images[original_images.len()..].iter().enumerate() {
+        assert_eq!(img.guid, Some(format!("appended_img_{i}")));
+        let blob = &img.visual_reference.as_ref().unwrap().blob.data;
+        let mut data = Vec::new();
+        reader.blob(blob, &mut data).unwrap();
+        assert_eq!(data, vec![i as u8; 100]);
+    }
+
+    remove_file(path).unwrap();
+}
@@ -0,0 +1,297 @@
+/*
+ * Small tool that adds colors to point clouds using the images embedded in an E57 file.
+ *
+ * Point clouds that already have colors or have no associated images are copied unchanged.
+ * All other point clouds get 8 bit RGB colors from the images that reference them.
+ * Points that are not visible in any image are marked with an invalid color.
+ * Projectable images in PNG and JPEG format are supported.
+ * All images are copied into the output file.
+ */
+
+use anyhow::{ensure, Context, Result};
+use e57::{
+    Blob, CartesianCoordinate, Colorizer, E57Reader, E57Writer, Image, PointCloud, Projection,
+    Record, RecordDataType, RecordName, RecordValue,
+};
+use std::env::args;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use uuid::Uuid;
+
+fn main() -> Result<()> {
+    let args: Vec<String> = args().collect();
+    ensure!(
+        args.len() >= 3,
+        "Usage: e57-colorize <path/to/input.e57> <path/to/output.e57>"
+    );
+
+    let mut reader = E57Reader::from_file(&args[1]).context("Failed to open E57 file")?;
+    let guid = Uuid::new_v4().to_string();
+    let mut writer =
+        E57Writer::from_file(&args[2], &guid).context("Failed to create output file")?;
+    writer.set_coordinate_metadata(reader.coordinate_metadata().map(String::from));
+    for extension in reader.extensions() {
+        writer
+            .register_extension(extension)
+            .context("Failed to register extension")?;
+    }
+
+    let pointclouds = reader.pointclouds();
+    for (index, pc) in pointclouds.iter().enumerate() {
+        let colorizer = if pc.has_color() {
+            None
+        } else {
+            Some(Colorizer::from_pointcloud(&mut reader, pc).context("Failed to load images")?)
+        };
+        match colorizer {
+            Some(colorizer) if colorizer.images() > 0 => {
+                println!(
+                    "Colorizing point cloud #{index} with {} image(s)...",
+                    colorizer.images()
+                );
+                colorize_pointcloud(&mut reader, &mut writer, pc, colorizer)?;
+            }
+            _ => {
+                println!("Copying point cloud #{index}...");
+                copy_pointcloud(&mut reader, &mut writer, pc, Vec::new(), |_| Vec::new())?;
+            }
+        }
+    }
+
+    for image in reader.images() {
+        copy_image(&mut reader, &mut writer, &image)?;
+    }
+
+    writer
+        .finalize()
+        .context("Failed to finalize output file")?;
+    Ok(())
+}
+
+fn colorize_pointcloud(
+    reader: &mut E57Reader<BufReader<File>>,
+    writer: &mut E57Writer<File>,
+    pc: &PointCloud,
+    mut colorizer: Colorizer,
+) -> Result<()> {
+    // First pass: Collect file-level coordinates and build depth maps
+    let mut coordinates = Vec::with_capacity(pc.records as usize);
+    let iter = reader
+        .pointcloud_simple(pc)
+        .context("Failed to open point cloud reader")?;
+    for p in iter {
+        let p = p.context("Failed to read point")?;
+        if let CartesianCoordinate::Valid { x, y, z } = p.cartesian {
+            colorizer.add_depth([x, y, z]);
+            coordinates.push(Some([x, y, z]));
+        } else {
+            coordinates.push(None);
+        }
+    }
+
+    // Second pass: Copy raw values and add colors
+    let records = vec![
+        Record::COLOR_RED_U8,
+        Record::COLOR_GREEN_U8,
+        Record::COLOR_BLUE_U8,
+        Record {
+            name: RecordName::IsColorInvalid,
+            data_type: RecordDataType::Integer { min: 0, max: 1 },
+        },
+    ];
+    let mut colored = 0;
+    let mut next = 0;
+    copy_pointcloud(reader, writer, pc, records, |_| {
+        let rgb = coordinates
+            .get(next)
+            .copied()
+            .flatten()
+            .and_then(|p| colorizer.color(p));
+        next += 1;
+        if rgb.is_some() {
+            colored += 1;
+        }
+        let [r, g, b] = rgb.unwrap_or_default();
+        vec![
+            RecordValue::Integer(r as i64),
+            RecordValue::Integer(g as i64),
+            RecordValue::Integer(b as i64),
+            RecordValue::Integer(if rgb.is_some() { 0 } else { 1 }),
+        ]
+    })?;
+    println!("Colorized {colored} of {} points", pc.records);
+    Ok(())
+}
+
+fn copy_pointcloud(
+    reader: &mut E57Reader<BufReader<File>>,
+    writer: &mut E57Writer<File>,
+    pc: &PointCloud,
+    records: Vec<Record>,
+    mut values: impl FnMut(&[RecordValue]) -> Vec<RecordValue>,
+) -> Result<()> {
+    let guid = pc
+        .guid
+        .clone()
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let mut prototype = pc.prototype.clone();
+    for record in &mut prototype {
+        // Some files use smaller ranges for invalid states, but the writer requires the full range
+        if matches!(
+            record.name,
+            RecordName::CartesianInvalidState | RecordName::SphericalInvalidState
+        ) {
+            record.data_type = RecordDataType::Integer { min: 0, max: 2 };
+        }
+    }
+    prototype.extend(records);
+    let mut pc_writer = writer
+        .add_pointcloud(&guid, prototype)
+        .context("Failed to create point cloud writer")?;
+    pc_writer.set_name(pc.name.clone());
+    pc_writer.set_description(pc.description.clone());
+    pc_writer.set_original_guids(pc.original_guids.clone());
+    pc_writer.set_transform(pc.transform.clone());
+    pc_writer.set_acquisition_start(pc.acquisition_start.clone());
+    pc_writer.set_acquisition_end(pc.acquisition_end.clone());
+    pc_writer.set_sensor_vendor(pc.sensor_vendor.clone());
+    pc_writer.set_sensor_model(pc.sensor_model.clone());
+    pc_writer.set_sensor_serial(pc.sensor_serial.clone());
+    pc_writer.set_sensor_sw_version(pc.sensor_sw_version.clone());
+    pc_writer.set_sensor_hw_version(pc.sensor_hw_version.clone());
+    pc_writer.set_sensor_fw_version(pc.sensor_fw_version.clone());
+    pc_writer.set_temperature(pc.temperature);
+    pc_writer.set_humidity(pc.humidity);
+    pc_writer.set_atmospheric_pressure(pc.atmospheric_pressure);
+    pc_writer.set_intensity_limits(pc.intensity_limits.clone());
+    if pc.has_color() {
+        pc_writer.set_color_limits(pc.color_limits.clone());
+    }
+
+    let iter = reader
+        .pointcloud_raw(pc)
+        .context("Failed to open point cloud reader")?;
+    for p in iter {
+        let mut p = p.context("Failed to read point")?;
+        let extra = values(&p);
+        p.extend(extra);
+        pc_writer.add_point(p).context("Failed to write point")?;
+    }
+    pc_writer
+        .finalize()
+        .context("Failed to finalize point cloud")?;
+    Ok(())
+}
+
+fn copy_image(
+    reader: &mut E57Reader<BufReader<File>>,
+    writer: &mut E57Writer<File>,
+    image: &Image,
+) -> Result<()> {
+    let guid = image
+        .guid
+        .clone()
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let mut img_writer = writer
+        .add_image(&guid)
+        .context("Failed to create image writer")?;
+    if let Some(name) = &image.name {
+        img_writer.set_name(name);
+    }
+    if let Some(description) = &image.description {
+        img_writer.set_description(description);
+    }
+    if let Some(pc_guid) = &image.pointcloud_guid {
+        img_writer.set_pointcloud_guid(pc_guid);
+    }
+    if let Some(transform) = &image.transform {
+        img_writer.set_transform(transform.clone());
+    }
+    if let Some(acquisition) = &image.acquisition {
+        img_writer.set_acquisition(acquisition.clone());
+    }
+    if let Some(vendor) = &image.sensor_vendor {
+        img_writer.set_sensor_vendor(vendor);
+    }
+    if let Some(model) = &image.sensor_model {
+        img_writer.set_sensor_model(model);
+    }
+    if let Some(serial) = &image.sensor_serial {
+        img_writer.set_sensor_serial(serial);
+    }
+
+    if let Some(vr) = &image.visual_reference {
+        let (data, mask) = load_blobs(reader, &vr.blob.data, vr.mask.as_ref())?;
+        let mut mask = mask.as_deref();
+        img_writer
+            .add_visual_reference(
+                vr.blob.format.clone(),
+                &mut data.as_slice(),
+                vr.properties.clone(),
+                mask.as_mut().map(|m| m as &mut dyn Read),
+            )
+            .context("Failed to add visual reference image")?;
+    }
+    match &image.projection {
+        Some(Projection::Pinhole(p)) => {
+            let (data, mask) = load_blobs(reader, &p.blob.data, p.mask.as_ref())?;
+            let mut mask = mask.as_deref();
+            img_writer
+                .add_pinhole(
+                    p.blob.format.clone(),
+                    &mut data.as_slice(),
+                    p.properties.clone(),
+                    mask.as_mut().map(|m| m as &mut dyn Read),
+                )
+                .context("Failed to add pinhole image")?;
+        }
+        Some(Projection::Spherical(s)) => {
+            let (data, mask) = load_blobs(reader, &s.blob.data, s.mask.as_ref())?;
+            let mut mask = mask.as_deref();
+            img_writer
+                .add_spherical(
+                    s.blob.format.clone(),
+                    &mut data.as_slice(),
+                    s.properties.clone(),
+                    mask.as_mut().map(|m| m as &mut dyn Read),
+                )
+                .context("Failed to add spherical image")?;
+        }
+        Some(Projection::Cylindrical(c)) => {
+            let (data, mask) = load_blobs(reader, &c.blob.data, c.mask.as_ref())?;
+            let mut mask = mask.as_deref();
+            img_writer
+                .add_cylindrical(
+                    c.blob.format.clone(),
+                    &mut data.as_slice(),
+                    c.properties.clone(),
+                    mask.as_mut().map(|m| m as &mut dyn Read),
+                )
+                .context("Failed to add cylindrical image")?;
+        }
+        None => {}
+    }
+    img_writer.finalize().context("Failed to finalize image")?;
+    Ok(())
+}
+
+fn load_blobs(
+    reader: &mut E57Reader<BufReader<File>>,
+    data: &Blob,
+    mask: Option<&Blob>,
+) -> Result<(Vec<u8>, Option<Vec<u8>>)> {
+    let mut image = Vec::new();
+    reader
+        .blob(data, &mut image)
+        .context("Failed to read image data")?;
+    let mask = if let Some(mask) = mask {
+        let mut buffer = Vec::new();
+        reader
+            .blob(mask, &mut buffer)
+            .context("Failed to read image mask")?;
+        Some(buffer)
+    } else {
+        None
+    };
+    Ok((image, mask))
+}
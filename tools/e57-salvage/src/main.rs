@@ -0,0 +1,117 @@
+/*
+ * Small example application that recovers point clouds and images
+ * from damaged E57 files with a missing or truncated XML section.
+ *
+ * All recovered data is written into a new E57 file.
+ * The prototype of point clouds with three byte streams is guessed automatically.
+ * For other point clouds a prototype can be supplied as optional third argument.
+ * It is a comma-separated list of records with name and type, for example:
+ * cartesianX:f32,cartesianY:f32,cartesianZ:f32,colorRed:u8,colorGreen:u8,colorBlue:u8
+ * Supported types are f32, f64, u8, u16 and int:<min>:<max> for integers with custom limits.
+ */
+
+use anyhow::{bail, ensure, Context, Result};
+use e57::{E57Salvager, E57Writer, Record, RecordDataType, RecordName};
+use uuid::Uuid;
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    ensure!(
+        args.len() >= 3,
+        "Usage: e57-salvage <path/to/damaged.e57> <path/to/output.e57> [prototype]"
+    );
+
+    let prototype = match args.get(3) {
+        Some(arg) => Some(parse_prototype(arg).context("Failed to parse prototype argument")?),
+        None => None,
+    };
+
+    let mut salvager = E57Salvager::from_file(&args[1]).context("Failed to open damaged file")?;
+    for (i, pc) in salvager.pointclouds().iter().enumerate() {
+        println!(
+            "Found point cloud #{i} with {} data packets and {} byte streams",
+            pc.packets,
+            pc.stream_sizes.len()
+        );
+    }
+    println!("Found {} blobs", salvager.blobs().len());
+
+    let guid = Uuid::new_v4().to_string();
+    let mut writer =
+        E57Writer::from_file(&args[2], &guid).context("Failed to create output file")?;
+    let report = salvager
+        .write(&mut writer, prototype.as_deref(), &guid)
+        .context("Failed to write salvaged data")?;
+    writer
+        .finalize()
+        .context("Failed to finalize output file")?;
+
+    let corrupt_pages = salvager.corrupt_pages().len();
+    println!(
+        "Recovered {} point clouds with {} points and {} images",
+        report.pointclouds, report.points, report.images
+    );
+    if report.skipped_pointclouds > 0 {
+        println!(
+            "Skipped {} point clouds with unknown prototype",
+            report.skipped_pointclouds
+        );
+    }
+    if !report.blobs.is_empty() {
+        println!("Copied {} unknown blobs", report.blobs.len());
+    }
+    if corrupt_pages > 0 {
+        println!("Found {corrupt_pages} pages with invalid checksums");
+    }
+    Ok(())
+}
+
+fn parse_prototype(arg: &str) -> Result<Vec<Record>> {
+    let mut prototype = Vec::new();
+    for entry in arg.split(',') {
+        let (name, data_type) = entry
+            .split_once(':')
+            .context(format!("Record '{entry}' has no type"))?;
+        let name = match name {
+            "cartesianX" => RecordName::CartesianX,
+            "cartesianY" => RecordName::CartesianY,
+            "cartesianZ" => RecordName::CartesianZ,
+            "cartesianInvalidState" => RecordName::CartesianInvalidState,
+            "sphericalRange" => RecordName::SphericalRange,
+            "sphericalAzimuth" => RecordName::SphericalAzimuth,
+            "sphericalElevation" => RecordName::SphericalElevation,
+            "sphericalInvalidState" => RecordName::SphericalInvalidState,
+            "intensity" => RecordName::Intensity,
+            "isIntensityInvalid" => RecordName::IsIntensityInvalid,
+            "colorRed" => RecordName::ColorRed,
+            "colorGreen" => RecordName::ColorGreen,
+            "colorBlue" => RecordName::ColorBlue,
+            "isColorInvalid" => RecordName::IsColorInvalid,
+            "rowIndex" => RecordName::RowIndex,
+            "columnIndex" => RecordName::ColumnIndex,
+            "returnCount" => RecordName::ReturnCount,
+            "returnIndex" => RecordName::ReturnIndex,
+            "timeStamp" => RecordName::TimeStamp,
+            "isTimeStampInvalid" => RecordName::IsTimeStampInvalid,
+            _ => bail!("Unknown record name '{name}'"),
+        };
+        let data_type = match data_type {
+            "f32" => RecordDataType::F32,
+            "f64" => RecordDataType::F64,
+            "u8" => RecordDataType::U8,
+            "u16" => RecordDataType::U16,
+            _ => match data_type
+                .strip_prefix("int:")
+                .and_then(|l| l.split_once(':'))
+            {
+                Some((min, max)) => RecordDataType::Integer {
+                    min: min.parse().context("Failed to parse integer minimum")?,
+                    max: max.parse().context("Failed to parse integer maximum")?,
+                },
+                None => bail!("Unknown record type '{data_type}'"),
+            },
+        };
+        prototype.push(Record { name, data_type });
+    }
+    Ok(prototype)
+}
@@ -182,6 +182,7 @@ fn main() -> Result<()> {
                         RecordValue::Double(d) => d.to_string(),
                         RecordValue::ScaledInteger(si) => si.to_string(),
                         RecordValue::Integer(i) => i.to_string(),
+                        RecordValue::String(s) => s.clone(),
                     })
                     .collect();
                 let line = values.join(";") + "\n";